@@ -0,0 +1,5 @@
+-- Your SQL goes here
+CREATE TABLE setting(
+  key VARCHAR(50) PRIMARY KEY,
+  value VARCHAR(200) NOT NULL
+);
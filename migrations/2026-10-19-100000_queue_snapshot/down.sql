@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE queue_snapshot;
+ALTER TABLE playlist_song DROP COLUMN position;
@@ -0,0 +1,8 @@
+-- Your SQL goes here
+ALTER TABLE playlist_song ADD COLUMN position INTEGER NOT NULL DEFAULT 0;
+UPDATE playlist_song SET position = rowid;
+
+CREATE TABLE queue_snapshot(
+  position INTEGER PRIMARY KEY,
+  file VARCHAR(200) NOT NULL
+);
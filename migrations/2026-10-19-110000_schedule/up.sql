@@ -0,0 +1,10 @@
+-- Your SQL goes here
+CREATE TABLE schedule(
+  id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+  kind VARCHAR(20) NOT NULL,
+  run_at BIGINT NOT NULL,
+  playlist_name VARCHAR(200),
+  fade INTEGER NOT NULL,
+  daily BOOLEAN NOT NULL DEFAULT 0,
+  FOREIGN KEY(playlist_name) REFERENCES playlist(name) ON DELETE CASCADE ON UPDATE CASCADE
+);
@@ -0,0 +1,2 @@
+-- Your SQL goes here
+ALTER TABLE song ADD COLUMN source VARCHAR(20) NOT NULL DEFAULT 'youtube';
@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE song DROP COLUMN gain;
+ALTER TABLE song DROP COLUMN true_peak;
+ALTER TABLE song DROP COLUMN loudness;
@@ -0,0 +1,4 @@
+-- Your SQL goes here
+ALTER TABLE song ADD COLUMN loudness DOUBLE;
+ALTER TABLE song ADD COLUMN true_peak DOUBLE;
+ALTER TABLE song ADD COLUMN gain DOUBLE;
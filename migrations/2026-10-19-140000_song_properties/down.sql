@@ -0,0 +1,7 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE song DROP COLUMN file_size;
+ALTER TABLE song DROP COLUMN codec;
+ALTER TABLE song DROP COLUMN channels;
+ALTER TABLE song DROP COLUMN sample_rate;
+ALTER TABLE song DROP COLUMN bitrate;
+ALTER TABLE song DROP COLUMN duration;
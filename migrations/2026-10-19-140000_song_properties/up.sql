@@ -0,0 +1,7 @@
+-- Your SQL goes here
+ALTER TABLE song ADD COLUMN duration DOUBLE;
+ALTER TABLE song ADD COLUMN bitrate INTEGER;
+ALTER TABLE song ADD COLUMN sample_rate INTEGER;
+ALTER TABLE song ADD COLUMN channels INTEGER;
+ALTER TABLE song ADD COLUMN codec VARCHAR(20);
+ALTER TABLE song ADD COLUMN file_size BIGINT;
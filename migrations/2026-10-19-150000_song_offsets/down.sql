@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE song DROP COLUMN end_offset;
+ALTER TABLE song DROP COLUMN start_offset;
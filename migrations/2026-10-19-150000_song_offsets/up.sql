@@ -0,0 +1,3 @@
+-- Your SQL goes here
+ALTER TABLE song ADD COLUMN start_offset DOUBLE;
+ALTER TABLE song ADD COLUMN end_offset DOUBLE;
@@ -0,0 +1,2 @@
+-- Your SQL goes here
+ALTER TABLE song ADD COLUMN parent_id TEXT;
@@ -0,0 +1,13 @@
+-- This file should undo anything in `up.sql`
+CREATE TABLE playlist_song_unique(
+  playlist_name VARCHAR(200) NOT NULL,
+  song_id VARCHAR(50) NOT NULL,
+  position INTEGER NOT NULL DEFAULT 0,
+  FOREIGN KEY(song_id) REFERENCES song(id) ON DELETE CASCADE ON UPDATE CASCADE,
+  FOREIGN KEY(playlist_name) REFERENCES playlist(name) ON DELETE CASCADE ON UPDATE CASCADE,
+  PRIMARY KEY(playlist_name, song_id)
+);
+INSERT OR IGNORE INTO playlist_song_unique
+  SELECT playlist_name, song_id, position FROM playlist_song ORDER BY position;
+DROP TABLE playlist_song;
+ALTER TABLE playlist_song_unique RENAME TO playlist_song;
@@ -0,0 +1,12 @@
+-- Your SQL goes here
+CREATE TABLE playlist_song_position(
+  playlist_name VARCHAR(200) NOT NULL,
+  song_id VARCHAR(50) NOT NULL,
+  position INTEGER NOT NULL,
+  FOREIGN KEY(song_id) REFERENCES song(id) ON DELETE CASCADE ON UPDATE CASCADE,
+  FOREIGN KEY(playlist_name) REFERENCES playlist(name) ON DELETE CASCADE ON UPDATE CASCADE,
+  PRIMARY KEY(playlist_name, position)
+);
+INSERT INTO playlist_song_position SELECT playlist_name, song_id, position FROM playlist_song;
+DROP TABLE playlist_song;
+ALTER TABLE playlist_song_position RENAME TO playlist_song;
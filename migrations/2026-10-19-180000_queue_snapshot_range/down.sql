@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE queue_snapshot DROP COLUMN end_offset;
+ALTER TABLE queue_snapshot DROP COLUMN start_offset;
@@ -0,0 +1,3 @@
+-- Your SQL goes here
+ALTER TABLE queue_snapshot ADD COLUMN start_offset DOUBLE;
+ALTER TABLE queue_snapshot ADD COLUMN end_offset DOUBLE;
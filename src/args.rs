@@ -1,9 +1,22 @@
-use clap::{Args, Parser, Subcommand};
+use std::{path::PathBuf, time::Duration};
+
+use clap::{ArgGroup, Args, Parser, Subcommand, ValueEnum};
+
+use yap::{
+    player::backend::SeekTarget, playlists::format::PlaylistFormat, sync::transcode::Codec,
+    utils::utils::parse_duration,
+};
 
 ///YAP cli
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 pub struct App {
+    ///Configuration file to use instead of the default one
+    #[arg(long, global = true, env = "YAP_CONFIG")]
+    pub config: Option<PathBuf>,
+    ///Profile to use instead of the one chosen with profile use
+    #[arg(long, global = true, env = "YAP_PROFILE")]
+    pub profile: Option<String>,
     #[clap(subcommand)]
     pub command: Command,
 }
@@ -24,21 +37,236 @@ pub enum Command {
     ///Mpd daemon options
     #[clap(subcommand)]
     Mpd(MpdOptions),
+    ///Prints mpd events as JSON lines
+    Watch {},
+    ///Fades out and pauses playback after a delay
+    Sleep(SleepOptions),
+    ///Starts a playlist at a given time
+    Alarm(AlarmOptions),
+    ///Sleep timers and alarms options
+    #[clap(subcommand)]
+    Schedule(ScheduleOptions),
+    ///Local player options
+    #[clap(subcommand)]
+    Player(PlayerOptions),
+    ///Configuration options
+    #[clap(subcommand)]
+    Config(ConfigOptions),
+    ///Profile options
+    #[clap(subcommand)]
+    Profile(ProfileOptions),
+    ///Library backup options
+    #[clap(subcommand)]
+    Backup(BackupOptions),
+    ///Library export and import options
+    #[clap(subcommand)]
+    Library(LibraryOptions),
+    ///Copies playlists to a device or directory, with an m3u per playlist
+    Sync(SyncOptions),
+}
+
+#[derive(Debug, Args)]
+pub struct SyncOptions {
+    ///The playlists to sync
+    #[arg(required = true)]
+    pub playlists: Vec<String>,
+    ///Directory to sync to, songs it has from earlier syncs are only copied again when they changed
+    #[arg(long)]
+    pub to: PathBuf,
+    ///opus, mp3, aac, vorbis or flac, for devices that can't play opus
+    #[arg(long, short, default_value = "opus")]
+    pub codec: Codec,
+    ///Bitrate to transcode to, e.g. 192k
+    #[arg(long, short)]
+    pub bitrate: Option<String>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum LibraryOptions {
+    ///Describe the songs and playlists of the library
+    Export(LibraryExportOptions),
+    ///Recreate an exported library, downloading the missing songs again
+    Import(LibraryImportOptions),
+    ///Measure the loudness and audio properties of songs, and write their ReplayGain tags
+    Analyze(LibraryAnalyzeOptions),
+}
+
+#[derive(Debug, Args)]
+pub struct LibraryAnalyzeOptions {
+    ///Analyze every song again, not only the ones without measurements
+    #[arg(long, short)]
+    pub all: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct LibraryExportOptions {
+    ///Write a versioned json document that can be imported on another machine
+    #[arg(long)]
+    pub json: bool,
+    ///Write to a file instead of the standard output
+    #[arg(long, short)]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Debug, Args)]
+pub struct LibraryImportOptions {
+    ///The json document written by library export --json
+    pub file: PathBuf,
+    ///Only add the songs already downloaded and the playlists
+    #[arg(long)]
+    pub no_download: bool,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum BackupOptions {
+    ///Write the database and the configuration to a .tar, .tar.gz or .zip archive
+    Create(BackupCreateOptions),
+    ///Restore a backup archive
+    Restore(BackupRestoreOptions),
+}
+
+#[derive(Debug, Args)]
+pub struct BackupCreateOptions {
+    ///The archive to write
+    pub archive: PathBuf,
+    ///Include the audio files of the songs
+    #[arg(long, short)]
+    pub audio: bool,
+    ///Include the miniatures
+    #[arg(long, short)]
+    pub miniatures: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct BackupRestoreOptions {
+    ///The archive to restore
+    pub archive: PathBuf,
+    ///Add what the library is missing, or replace it with the backup
+    #[arg(long, short, value_enum, default_value_t = RestoreModeArg::Merge)]
+    pub mode: RestoreModeArg,
+    ///Only show what would be restored
+    #[arg(long, short = 'n')]
+    pub dry_run: bool,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum RestoreModeArg {
+    Merge,
+    Replace,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ProfileOptions {
+    ///List profiles, the one in use is marked with *
+    List {},
+    ///Use a profile by default, default goes back to the main configuration
+    Use(ProfileUseOptions),
+}
+
+#[derive(Debug, Args)]
+pub struct ProfileUseOptions {
+    ///Name of the profile, as in [profiles.<name>]
+    pub name: String,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ConfigOptions {
+    ///Prints the path of the configuration file
+    Path {},
+    ///Prints the whole configuration
+    Show {},
+    ///Prints a setting, given as section.key
+    Get(ConfigGetOptions),
+    ///Changes a setting, given as section.key
+    Set(ConfigSetOptions),
+    ///Opens the configuration file in $VISUAL or $EDITOR
+    Edit {},
+    ///Checks that the configured directories exist and match MPD's music directory
+    Validate {},
+}
+
+#[derive(Debug, Args)]
+pub struct ConfigGetOptions {
+    ///The setting, e.g. general.music_directory
+    pub key: String,
+}
+
+#[derive(Debug, Args)]
+pub struct ConfigSetOptions {
+    ///The setting, e.g. player.backend
+    pub key: String,
+    ///The new value
+    pub value: String,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum PlayerOptions {
+    ///Plays the queue with the local player until interrupted
+    Run {},
+}
+
+#[derive(Debug, Args)]
+pub struct SleepOptions {
+    ///Delay before pausing, e.g. 30m, 1h, 45:00
+    #[arg(value_parser = parse_duration)]
+    pub delay: Duration,
+    ///Seconds to fade the volume out
+    #[arg(long, short, default_value_t = 30)]
+    pub fade: u32,
+}
+
+#[derive(Debug, Args)]
+pub struct AlarmOptions {
+    ///Time to start playing, as HH:MM
+    pub time: String,
+    ///The playlist to play
+    #[arg(long, short)]
+    pub playlist: String,
+    ///Seconds to fade the volume in
+    #[arg(long, short, default_value_t = 60)]
+    pub fade: u32,
+    ///Repeat the alarm every day
+    #[arg(long, short)]
+    pub daily: bool,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ScheduleOptions {
+    ///List sleep timers and alarms
+    List {},
+    ///Cancel a sleep timer or an alarm
+    Cancel(ScheduleCancelOptions),
+    ///Run sleep timers and alarms when they are due
+    Run {},
+}
+
+#[derive(Debug, Args)]
+pub struct ScheduleCancelOptions {
+    ///Id of the schedule, as shown by schedule list
+    pub id: i32,
 }
 
 #[derive(Debug, Args)]
 pub struct DownloadOptions {
-    ///specify url of song to download
-    #[arg(long, short = 'i', requires = "name")]
+    ///specify youtube id, url or local path of song to download
+    #[arg(long, short = 'i')]
     pub id: String,
 
-    ///specify name of song to download
-    #[arg(long, short = 'n', requires = "id")]
-    pub name: String,
+    ///specify name of song to download, defaults to its title
+    #[arg(long, short = 'n', conflicts_with = "split_chapters")]
+    pub name: Option<String>,
 
     ///specify artist of song to download
     #[arg(long, short = 'a')]
     pub artist: Option<String>,
+
+    ///cut the video into a song per chapter, named after the chapters
+    #[arg(long, short = 'c')]
+    pub split_chapters: bool,
+
+    ///add the chapters to a playlist named after the video
+    #[arg(long, short = 'p', requires = "split_chapters")]
+    pub playlist: bool,
 }
 
 #[derive(Debug, Subcommand)]
@@ -53,6 +281,50 @@ pub enum PlaylistOptions {
     Insert(PlaylistInsertOptions),
     ///Delete song from playlist
     Remove(PlaylistRemoveOptions),
+    ///Export playlists for other players
+    Export(PlaylistExportOptions),
+    ///Create a playlist from a m3u, m3u8, xspf, pls or json file
+    Import(PlaylistImportOptions),
+}
+
+#[derive(Debug, Args)]
+pub struct PlaylistImportOptions {
+    ///The playlist file
+    pub file: PathBuf,
+    ///Name of the new playlist, defaults to the one in the file or the file name
+    #[arg(long, short)]
+    pub name: Option<String>,
+    ///Format of the file, guessed from its extension when missing
+    #[arg(long, short)]
+    pub format: Option<PlaylistFormat>,
+    ///Download entries with a youtube url that aren't in the library
+    #[arg(long, short)]
+    pub download: bool,
+}
+
+#[derive(Debug, Args)]
+#[clap(group(ArgGroup::new("playlists").required(true).args(["name", "all"])))]
+pub struct PlaylistExportOptions {
+    ///The playlist name
+    pub name: Option<String>,
+    ///Export every playlist, to --directory or MPD's playlist_directory
+    #[arg(long)]
+    pub all: bool,
+    ///m3u, m3u8, xspf, pls or json, defaults to m3u for MPD and m3u8 otherwise
+    #[arg(long, short)]
+    pub format: Option<PlaylistFormat>,
+    ///File to write the playlist to, it's printed when missing
+    #[arg(long, short, conflicts_with = "all")]
+    pub output: Option<PathBuf>,
+    ///Directory to write every playlist to
+    #[arg(long, short, requires = "all")]
+    pub directory: Option<PathBuf>,
+    ///Write absolute paths, the default unless exporting to MPD
+    #[arg(long, conflicts_with = "relative")]
+    pub absolute: bool,
+    ///Write paths relative to the music directory, as MPD expects them
+    #[arg(long)]
+    pub relative: bool,
 }
 
 #[derive(Debug, Args)]
@@ -121,6 +393,27 @@ pub enum SongOptions {
     List {},
     ///Delete song
     Delete(SongDeleteOptions),
+    ///Set the part of a song that gets played
+    Trim(SongTrimOptions),
+}
+
+#[derive(Debug, Args)]
+#[clap(group(ArgGroup::new("trim").required(true).multiple(true).args(["start", "end", "detect", "reset"])))]
+pub struct SongTrimOptions {
+    ///Name of the song to trim
+    pub name: String,
+    ///Where playback starts, e.g. 0:12
+    #[arg(long, short, value_parser = parse_duration)]
+    pub start: Option<Duration>,
+    ///Where playback stops, e.g. 3:40
+    #[arg(long, short, value_parser = parse_duration)]
+    pub end: Option<Duration>,
+    ///Skip the silence at the start and the end of the song
+    #[arg(long, short, conflicts_with_all = ["start", "end", "reset"])]
+    pub detect: bool,
+    ///Play the whole song again
+    #[arg(long, short, conflicts_with_all = ["start", "end"])]
+    pub reset: bool,
 }
 
 #[derive(Debug, Args)]
@@ -143,40 +436,153 @@ pub enum MpdOptions {
     ///Skips to next song in queue
     Next {},
     ///Gets current song's info
-    Current {},
+    Current(TemplateOptions),
     ///Toggles repeat function
     Repeat {},
     ///Goes to previous song in queue
     Previous {},
-    ///Seeks to specified percentage
+    ///Seeks to a position (1:23), an offset (+10s, -30s) or a percentage (50%)
     Seek(SeekOptions),
     ///Prints mpd status
-    Status {},
+    Status(TemplateOptions),
     ///Displays current queue
     Queue {},
     ///Add song to current queue
     QueueAdd(QueueAddOptions),
     ///Remove song from current queue
     QueueRemove(QueueRemoveOptions),
+    ///Removes repeated songs from current queue
+    QueueDedupe {},
+    ///Saves current queue as a playlist
+    QueueSave(QueueSaveOptions),
+    ///Restores the queue as it was before the last clear
+    QueueRestore {},
     ///Shuffles current queue
     QueueShuffle {},
+    ///Moves song in queue to another position
+    QueueMove(QueueMoveOptions),
+    ///Swaps two songs in queue
+    QueueSwap(QueueSwapOptions),
+    ///Plays song at position in queue
+    QueuePlay(QueuePlayOptions),
+    ///Toggles consume function
+    Consume {},
+    ///Toggles single function
+    Single {},
+    ///Sets volume, relative with +N/-N or absolute with N
+    Volume(VolumeOptions),
+    ///Toggles mute
+    Mute {},
+    ///Sets crossfade between songs
+    Crossfade(CrossfadeOptions),
+    ///Sets mixramp threshold and delay
+    Mixramp(MixrampOptions),
+    ///Sets replay gain mode
+    ReplayGain(ReplayGainOptions),
 }
 
 #[derive(Debug, Args)]
-pub struct SeekOptions {
-    ///Percentage to skip to
+pub struct TemplateOptions {
+    ///Output format, e.g. '{artist} - {title} [{elapsed}/{duration}]'. Available keys:
+    ///id, title, artist, file, state, elapsed, duration, percentage, position, length,
+    ///bitrate, format, volume, repeat, random, single, consume, paused, crossfade, replay_gain
     #[arg(long, short)]
-    pub percentage: u8,
+    pub template: Option<String>,
+}
+
+#[derive(Debug, Args)]
+pub struct SeekOptions {
+    ///Position to seek to
+    #[arg(allow_hyphen_values = true)]
+    pub position: SeekTarget,
 }
 
 #[derive(Debug, Args)]
 pub struct QueueAddOptions {
     #[arg(long, short)]
     pub song_name: String,
+    ///Insert song right after the current one instead of at the end
+    #[arg(long)]
+    pub next: bool,
 }
 
 #[derive(Debug, Args)]
+#[clap(group(ArgGroup::new("target").required(true).args(["song_name", "position", "range"])))]
 pub struct QueueRemoveOptions {
     #[arg(long, short)]
-    pub song_name: String,
+    pub song_name: Option<String>,
+    ///Remove every occurrence of the song instead of the first one
+    #[arg(long, short, requires = "song_name")]
+    pub all: bool,
+    ///Position of the song to remove
+    #[arg(long, short)]
+    pub position: Option<u32>,
+    ///Positions to remove, as start-end
+    #[arg(long, short)]
+    pub range: Option<String>,
+}
+
+#[derive(Debug, Args)]
+pub struct QueueSaveOptions {
+    ///Name of the playlist to create
+    pub playlist_name: String,
+}
+
+#[derive(Debug, Args)]
+pub struct QueueMoveOptions {
+    ///Position of the song to move
+    pub from: u32,
+    ///Position to move the song to
+    pub to: u32,
+}
+
+#[derive(Debug, Args)]
+pub struct QueueSwapOptions {
+    ///Position of the first song
+    pub first: u32,
+    ///Position of the second song
+    pub second: u32,
+}
+
+#[derive(Debug, Args)]
+pub struct QueuePlayOptions {
+    ///Position of the song to play
+    pub position: u32,
+}
+
+#[derive(Debug, Args)]
+pub struct VolumeOptions {
+    ///Volume to set, prefix with + or - to change it relatively
+    #[arg(allow_hyphen_values = true)]
+    pub volume: Option<String>,
+}
+
+#[derive(Debug, Args)]
+pub struct CrossfadeOptions {
+    ///Crossfade duration in seconds, 0 disables it
+    pub seconds: u64,
+}
+
+#[derive(Debug, Args)]
+pub struct MixrampOptions {
+    ///Mixramp threshold in dB
+    #[arg(long, short, allow_hyphen_values = true)]
+    pub db: Option<f32>,
+    ///Mixramp delay in seconds, nan disables it
+    #[arg(long, short = 'l')]
+    pub delay: Option<f64>,
+}
+
+#[derive(Debug, Args)]
+pub struct ReplayGainOptions {
+    ///Replay gain mode
+    pub mode: ReplayGainMode,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum ReplayGainMode {
+    Off,
+    Track,
+    Album,
+    Auto,
 }
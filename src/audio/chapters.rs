@@ -0,0 +1,79 @@
+use std::{fs, path::Path, process::Command};
+
+use crate::{
+    error::{Result, YapError},
+    sources::source::Chapter,
+};
+
+/**
+ * A song to cut from a video, times in seconds
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChapterSong {
+    pub id: String,
+    pub name: String,
+    pub start: f64,
+    //Missing when the song lasts until the end of the video
+    pub end: Option<f64>,
+}
+
+/**
+ * The songs a video is split into, ids follow the video's with the chapter number,
+ * untitled chapters are named after the video and a chapter without an end
+ * lasts until the next one
+ */
+pub fn chapter_songs(video_id: &str, title: &str, chapters: &[Chapter]) -> Vec<ChapterSong> {
+    chapters
+        .iter()
+        .enumerate()
+        .map(|(index, chapter)| ChapterSong {
+            id: format!("{}-{:02}", video_id, index + 1),
+            name: match &chapter.title {
+                Some(name) => name.clone(),
+                None => format!("{} - part {}", title, index + 1),
+            },
+            start: chapter.start,
+            end: chapter
+                .end
+                .or(chapters.get(index + 1).map(|next| next.start)),
+        })
+        .collect()
+}
+
+/**
+ * Copies part of an opus file to another file without re-encoding it, the end
+ * of the file is used when no end is given
+ */
+pub fn extract(
+    input: &Path,
+    output: &Path,
+    start: f64,
+    end: Option<f64>,
+    title: &str,
+) -> Result<()> {
+    let mut command = Command::new("ffmpeg");
+    command
+        .args(["-y", "-v", "quiet", "-i"])
+        .arg(input)
+        .arg("-ss")
+        .arg(start.to_string());
+    if let Some(end) = end {
+        command.arg("-to").arg(end.to_string());
+    }
+    let status = command
+        .args(["-vn", "-map_metadata", "-1", "-c:a", "copy"])
+        .arg("-metadata")
+        .arg(format!("title={}", title))
+        .args(["-f", "opus"])
+        .arg(output)
+        .status()?;
+    if !status.success() {
+        let _ = fs::remove_file(output);
+        return Err(YapError::Audio(format!(
+            "Couldn't cut {} from {}",
+            output.display(),
+            input.display()
+        )));
+    }
+    Ok(())
+}
@@ -0,0 +1,110 @@
+use std::{fs, path::Path, process::Command};
+
+use serde::Deserialize;
+
+use crate::error::{Result, YapError};
+
+//Loudness ReplayGain 2.0 brings tracks to, in LUFS
+pub const REFERENCE_LOUDNESS: f64 = -18.;
+//Loudness R128_TRACK_GAIN is relative to, in LUFS
+const R128_REFERENCE: f64 = -23.;
+
+/**
+ * EBU R128 measurements of a song
+ */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Loudness {
+    //Integrated loudness, in LUFS
+    pub integrated: f64,
+    //In dBTP
+    pub true_peak: f64,
+    //Gain that brings the song to the ReplayGain reference, in dB
+    pub gain: f64,
+}
+
+//Summary printed by ffmpeg's loudnorm filter, numbers are written as strings
+#[derive(Deserialize)]
+struct LoudnormSummary {
+    input_i: String,
+    input_tp: String,
+}
+
+/**
+ * Measures the loudness of an audio file with ffmpeg
+ */
+pub fn analyze(file: &Path) -> Result<Loudness> {
+    let output = Command::new("ffmpeg")
+        .args(["-hide_banner", "-nostats", "-i"])
+        .arg(file)
+        .args([
+            "-vn",
+            "-af",
+            "loudnorm=print_format=json",
+            "-f",
+            "null",
+            "-",
+        ])
+        .output()?;
+    let log = String::from_utf8_lossy(&output.stderr);
+    //The summary is the last json object ffmpeg logs
+    let summary = match (log.rfind('{'), log.rfind('}')) {
+        (Some(start), Some(end)) if output.status.success() && start < end => &log[start..=end],
+        _ => {
+            return Err(YapError::Audio(format!(
+                "Couldn't analyze {}",
+                file.display()
+            )))
+        }
+    };
+    let summary: LoudnormSummary = serde_json::from_str(summary)
+        .map_err(|error| YapError::Audio(format!("Couldn't read ffmpeg's analysis: {}", error)))?;
+    let integrated: f64 = summary.input_i.trim().parse().unwrap_or(f64::NAN);
+    let true_peak: f64 = summary.input_tp.trim().parse().unwrap_or(f64::NAN);
+    //Silence measures as -inf
+    if !integrated.is_finite() || !true_peak.is_finite() {
+        return Err(YapError::Audio(format!(
+            "{} is too quiet to measure its loudness",
+            file.display()
+        )));
+    }
+    Ok(Loudness {
+        integrated,
+        true_peak,
+        gain: REFERENCE_LOUDNESS - integrated,
+    })
+}
+
+/**
+ * Tags an opus file with its track gain and peak, both as ReplayGain tags and
+ * as the R128 tag opus players read
+ */
+pub fn write_replay_gain(file: &Path, loudness: &Loudness) -> Result<()> {
+    let peak = 10f64.powf(loudness.true_peak / 20.);
+    //Q7.8 fixed point, in dB
+    let r128_gain = ((R128_REFERENCE - loudness.integrated) * 256.)
+        .round()
+        .clamp(i16::MIN as f64, i16::MAX as f64) as i16;
+    let tagged = file.with_extension("tagged.opus");
+    let status = Command::new("ffmpeg")
+        .args(["-y", "-v", "quiet", "-i"])
+        .arg(file)
+        .args(["-map", "0", "-c", "copy", "-map_metadata", "0"])
+        .arg("-metadata:s:a:0")
+        .arg(format!("REPLAYGAIN_TRACK_GAIN={:.2} dB", loudness.gain))
+        .arg("-metadata:s:a:0")
+        .arg(format!("REPLAYGAIN_TRACK_PEAK={:.6}", peak))
+        .arg("-metadata:s:a:0")
+        .arg(format!("R128_TRACK_GAIN={}", r128_gain))
+        .args(["-f", "opus"])
+        .arg(&tagged)
+        .status()?;
+    if !status.success() {
+        let _ = fs::remove_file(&tagged);
+        return Err(YapError::Audio(format!(
+            "Couldn't write the replay gain of {}",
+            file.display()
+        )));
+    }
+    fs::rename(&tagged, file)?;
+    Ok(())
+}
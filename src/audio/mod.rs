@@ -0,0 +1,4 @@
+pub mod chapters;
+pub mod loudness;
+pub mod properties;
+pub mod silence;
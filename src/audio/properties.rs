@@ -0,0 +1,80 @@
+use std::{fs, path::Path, process::Command};
+
+use serde::Deserialize;
+
+use crate::error::Result;
+
+/**
+ * What ffprobe knows about an audio file, fields are missing when it can't tell
+ */
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AudioProperties {
+    //Seconds
+    pub duration: Option<f64>,
+    //Bits per second
+    pub bitrate: Option<i32>,
+    pub sample_rate: Option<i32>,
+    pub channels: Option<i32>,
+    pub codec: Option<String>,
+    //Bytes
+    pub file_size: i64,
+}
+
+//ffprobe writes most numbers as strings
+#[derive(Deserialize)]
+struct Probe {
+    #[serde(default)]
+    streams: Vec<ProbeStream>,
+    format: Option<ProbeFormat>,
+}
+
+#[derive(Deserialize)]
+struct ProbeStream {
+    codec_name: Option<String>,
+    sample_rate: Option<String>,
+    channels: Option<i32>,
+}
+
+#[derive(Deserialize)]
+struct ProbeFormat {
+    duration: Option<String>,
+    bit_rate: Option<String>,
+}
+
+fn number<T: std::str::FromStr>(value: Option<String>) -> Option<T> {
+    value?.trim().parse().ok()
+}
+
+/**
+ * Reads the duration and format of an audio file, only its size is known when
+ * ffprobe is missing or doesn't understand the file
+ */
+pub fn probe(file: &Path) -> Result<AudioProperties> {
+    let mut properties = AudioProperties {
+        file_size: fs::metadata(file)?.len() as i64,
+        ..Default::default()
+    };
+    let output = match Command::new("ffprobe")
+        .args(["-v", "quiet", "-print_format", "json"])
+        .args(["-show_format", "-show_streams", "-select_streams", "a:0"])
+        .arg(file)
+        .output()
+    {
+        Ok(output) if output.status.success() => output,
+        _ => return Ok(properties),
+    };
+    let probe: Probe = match serde_json::from_slice(&output.stdout) {
+        Ok(probe) => probe,
+        Err(_) => return Ok(properties),
+    };
+    if let Some(format) = probe.format {
+        properties.duration = number(format.duration);
+        properties.bitrate = number(format.bit_rate);
+    }
+    if let Some(stream) = probe.streams.into_iter().next() {
+        properties.sample_rate = number(stream.sample_rate);
+        properties.channels = stream.channels;
+        properties.codec = stream.codec_name;
+    }
+    Ok(properties)
+}
@@ -0,0 +1,95 @@
+use std::{fs, path::Path, process::Command};
+
+use crate::{
+    error::{Result, YapError},
+    player::backend::PlayRange,
+};
+
+//Quieter than this counts as silence
+const NOISE: &str = "-50dB";
+//Shorter silences are pauses inside the song, in seconds
+const MIN_SILENCE: f64 = 1.;
+//How close to the edges of the file leading and trailing silence have to be, in seconds
+const EDGE: f64 = 0.1;
+
+/**
+ * Value logged by silencedetect after the given key, like "silence_start: 12.5"
+ */
+fn logged_value(line: &str, key: &str) -> Option<f64> {
+    let value = &line[line.find(key)? + key.len()..];
+    value.split_whitespace().next()?.parse().ok()
+}
+
+/**
+ * Finds the silence at the start and the end of an audio file, returns the part
+ * between them. The duration is needed to tell silence that ends with the file
+ */
+pub fn detect_silence(file: &Path, duration: Option<f64>) -> Result<PlayRange> {
+    let output = Command::new("ffmpeg")
+        .args(["-hide_banner", "-nostats", "-i"])
+        .arg(file)
+        .arg("-vn")
+        .arg("-af")
+        .arg(format!("silencedetect=noise={}:d={}", NOISE, MIN_SILENCE))
+        .args(["-f", "null", "-"])
+        .output()?;
+    if !output.status.success() {
+        return Err(YapError::Audio(format!(
+            "Couldn't detect the silence of {}",
+            file.display()
+        )));
+    }
+    //Silences as (start, end), the end is missing when the silence lasts until the end
+    let mut silences: Vec<(f64, Option<f64>)> = Vec::new();
+    for line in String::from_utf8_lossy(&output.stderr).lines() {
+        if let Some(start) = logged_value(line, "silence_start: ") {
+            silences.push((start, None));
+        } else if let Some(end) = logged_value(line, "silence_end: ") {
+            if let Some(silence) = silences.last_mut() {
+                silence.1 = Some(end);
+            }
+        }
+    }
+
+    let start = match silences.first() {
+        Some((start, Some(end))) if *start <= EDGE => Some(*end),
+        _ => None,
+    };
+    let end = match silences.last() {
+        Some((start, None)) => Some(*start),
+        Some((start, Some(end))) if duration.is_some_and(|duration| *end >= duration - EDGE) => {
+            Some(*start)
+        }
+        _ => None,
+    };
+    //A song that is silent from start to end has nothing to trim to
+    match (start, end) {
+        (Some(start), Some(end)) if start >= end => Ok(PlayRange::default()),
+        (start, end) => Ok(PlayRange { start, end }),
+    }
+}
+
+/**
+ * Cuts an opus file down to a range, re-encoding it so the cut is exact
+ */
+pub fn trim_file(file: &Path, range: PlayRange) -> Result<()> {
+    let trimmed = file.with_extension("trimmed.opus");
+    let mut command = Command::new("ffmpeg");
+    command.args(["-y", "-v", "quiet", "-i"]).arg(file);
+    if let Some(start) = range.start {
+        command.arg("-ss").arg(start.to_string());
+    }
+    if let Some(end) = range.end {
+        command.arg("-to").arg(end.to_string());
+    }
+    let status = command
+        .args(["-vn", "-map_metadata", "0", "-c:a", "libopus", "-f", "opus"])
+        .arg(&trimmed)
+        .status()?;
+    if !status.success() {
+        let _ = fs::remove_file(&trimmed);
+        return Err(YapError::Audio(format!("Couldn't trim {}", file.display())));
+    }
+    fs::rename(&trimmed, file)?;
+    Ok(())
+}
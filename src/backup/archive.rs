@@ -0,0 +1,111 @@
+use std::{
+    path::{self, Path},
+    process::Command,
+};
+
+use crate::error::{Result, YapError};
+
+/**
+ * Archive formats backups can be written as, packed with the system tar and zip
+ */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ArchiveFormat {
+    Tar,
+    TarGz,
+    Zip,
+}
+
+/**
+ * Guesses the format of an archive from its extension
+ */
+pub fn format_of(archive: &Path) -> Result<ArchiveFormat> {
+    let name = archive.to_string_lossy().to_lowercase();
+    if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+        Ok(ArchiveFormat::TarGz)
+    } else if name.ends_with(".tar") {
+        Ok(ArchiveFormat::Tar)
+    } else if name.ends_with(".zip") {
+        Ok(ArchiveFormat::Zip)
+    } else {
+        Err(YapError::Config(format!(
+            "Unknown archive format for {}, use .tar, .tar.gz or .zip",
+            archive.display()
+        )))
+    }
+}
+
+fn run(command: &mut Command, what: &str) -> Result<()> {
+    let status = command.status()?;
+    if !status.success() {
+        return Err(YapError::Io(std::io::Error::other(format!(
+            "{} exited with {}",
+            what, status
+        ))));
+    }
+    Ok(())
+}
+
+/**
+ * Packs the content of a directory, following symbolic links
+ */
+pub fn pack(archive: &Path, directory: &Path) -> Result<()> {
+    let archive = path::absolute(archive)?;
+    match format_of(&archive)? {
+        ArchiveFormat::Tar => run(
+            Command::new("tar")
+                .arg("-chf")
+                .arg(&archive)
+                .arg("-C")
+                .arg(directory)
+                .arg("."),
+            "tar",
+        ),
+        ArchiveFormat::TarGz => run(
+            Command::new("tar")
+                .arg("-chzf")
+                .arg(&archive)
+                .arg("-C")
+                .arg(directory)
+                .arg("."),
+            "tar",
+        ),
+        ArchiveFormat::Zip => run(
+            Command::new("zip")
+                .args(["-q", "-r"])
+                .arg(&archive)
+                .arg(".")
+                .current_dir(directory),
+            "zip",
+        ),
+    }
+}
+
+/**
+ * Extracts an archive into a directory
+ */
+pub fn unpack(archive: &Path, directory: &Path) -> Result<()> {
+    if !archive.is_file() {
+        return Err(YapError::NotFound(format!(
+            "Archive {} doesn't exist",
+            archive.display()
+        )));
+    }
+    match format_of(archive)? {
+        ArchiveFormat::Tar | ArchiveFormat::TarGz => run(
+            Command::new("tar")
+                .arg("-xf")
+                .arg(archive)
+                .arg("-C")
+                .arg(directory),
+            "tar",
+        ),
+        ArchiveFormat::Zip => run(
+            Command::new("unzip")
+                .arg("-q")
+                .arg(archive)
+                .arg("-d")
+                .arg(directory),
+            "unzip",
+        ),
+    }
+}
@@ -0,0 +1,387 @@
+use std::{
+    env, fs,
+    os::unix::fs::symlink,
+    path::{Path, PathBuf},
+    process,
+};
+
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+
+use super::archive::{pack, unpack};
+use crate::{
+    config::Config,
+    db::{
+        backup::copy_database,
+        database::Database,
+        models::{NewPlaylist, NewPlaylistSong, NewSong, Song, SongLoudness, SongProperties},
+    },
+    error::{Result, YapError},
+    sources::source::audio_file,
+};
+
+//Layout of a backup archive
+const MANIFEST: &str = "backup.json";
+const DATABASE: &str = "yap.db";
+const CONFIG: &str = "yap.config.toml";
+const MUSIC: &str = "music";
+const MINIATURES: &str = "miniatures";
+
+/**
+ * Describes what a backup archive contains
+ */
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BackupManifest {
+    pub created_at: String,
+    pub songs: usize,
+    pub playlists: usize,
+    pub audio: bool,
+    pub miniatures: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RestoreMode {
+    //Adds what the library is missing, keeping the current configuration
+    Merge,
+    //Replaces the database and the configuration with the ones in the backup
+    Replace,
+}
+
+/**
+ * What a restore did, or would do when it's a dry run
+ */
+#[derive(Debug)]
+pub struct RestoreReport {
+    pub manifest: BackupManifest,
+    pub songs_added: Vec<String>,
+    //Songs of the current library the backup doesn't have, only lost when replacing
+    pub songs_removed: Vec<String>,
+    pub playlists_added: Vec<String>,
+    pub playlists_merged: Vec<String>,
+    pub files_copied: usize,
+    pub config_replaced: bool,
+}
+
+/**
+ * A temporary directory, removed when dropped
+ */
+struct Staging(PathBuf);
+
+impl Staging {
+    fn new() -> Result<Self> {
+        let path = env::temp_dir().join(format!(
+            "yap-backup-{}-{}",
+            process::id(),
+            Local::now().timestamp_micros()
+        ));
+        fs::create_dir_all(&path)?;
+        Ok(Self(path))
+    }
+}
+
+impl Drop for Staging {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+fn open_database(path: &Path) -> Result<Database> {
+    let database = Database::new(format!("sqlite://{}", path.display()), 1)?;
+    database.run_embedded_migrations()?;
+    Ok(database)
+}
+
+/**
+ * Writes an archive with a snapshot of the database, the configuration file and
+ * optionally the audio files and miniatures of the library
+ */
+pub fn create_backup(
+    config: &Config,
+    config_path: &Path,
+    archive: &Path,
+    audio: bool,
+    miniatures: bool,
+) -> Result<BackupManifest> {
+    let staging = Staging::new()?;
+    let database_path = &config.database.database_path;
+    if !database_path.is_file() {
+        return Err(YapError::NotFound(format!(
+            "Database {} doesn't exist",
+            database_path.display()
+        )));
+    }
+    copy_database(database_path, &staging.0.join(DATABASE))?;
+    if config_path.is_file() {
+        fs::copy(config_path, staging.0.join(CONFIG))?;
+    }
+
+    let snapshot = open_database(&staging.0.join(DATABASE))?;
+    let songs = snapshot.get_songs()?;
+    //Links are followed when packing, so nothing is copied twice
+    if audio {
+        fs::create_dir(staging.0.join(MUSIC))?;
+        for song in songs.iter() {
+            let file = audio_file(&config.general.music_directory, &song.id);
+            if file.is_file() {
+                symlink(&file, audio_file(&staging.0.join(MUSIC), &song.id))?;
+            }
+        }
+    }
+    let miniatures = miniatures && config.general.miniature_directory.is_dir();
+    if miniatures {
+        symlink(
+            &config.general.miniature_directory,
+            staging.0.join(MINIATURES),
+        )?;
+    }
+
+    let manifest = BackupManifest {
+        created_at: Local::now().to_rfc3339(),
+        songs: songs.len(),
+        playlists: snapshot.get_playlists()?.len(),
+        audio,
+        miniatures,
+    };
+    drop(snapshot);
+    fs::write(
+        staging.0.join(MANIFEST),
+        serde_json::to_string_pretty(&manifest).map_err(std::io::Error::other)?,
+    )?;
+    pack(archive, &staging.0)?;
+    Ok(manifest)
+}
+
+/**
+ * Copies the files of a directory, returns how many were or would be copied
+ */
+fn copy_files(from: &Path, to: &Path, overwrite: bool, dry_run: bool) -> Result<usize> {
+    if !from.is_dir() {
+        return Ok(0);
+    }
+    if !dry_run {
+        fs::create_dir_all(to)?;
+    }
+    let mut copied = 0;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let target = to.join(entry.file_name());
+        if !entry.path().is_file() || (!overwrite && target.exists()) {
+            continue;
+        }
+        if !dry_run {
+            fs::copy(entry.path(), &target)?;
+        }
+        copied += 1;
+    }
+    Ok(copied)
+}
+
+/**
+ * Adds a song of a backup with its trim, loudness and audio properties
+ */
+fn insert_song(database: &Database, song: &Song) -> Result<()> {
+    database.insert_song(NewSong {
+        id: &song.id,
+        name: &song.name,
+        artist: song.artist.clone(),
+        source: &song.source,
+        parent_id: song.parent_id.as_deref(),
+    })?;
+    database.set_song_offsets(&song.id, song.start_offset, song.end_offset)?;
+    if let (Some(loudness), Some(true_peak), Some(gain)) =
+        (song.loudness, song.true_peak, song.gain)
+    {
+        database.set_song_loudness(
+            &song.id,
+            SongLoudness {
+                loudness,
+                true_peak,
+                gain,
+            },
+        )?;
+    }
+    //Songs that were never probed have nothing to copy, and diesel refuses empty updates
+    let properties = SongProperties {
+        duration: song.duration,
+        bitrate: song.bitrate,
+        sample_rate: song.sample_rate,
+        channels: song.channels,
+        codec: song.codec.clone(),
+        file_size: song.file_size,
+    };
+    let probed = properties.duration.is_some()
+        || properties.bitrate.is_some()
+        || properties.sample_rate.is_some()
+        || properties.channels.is_some()
+        || properties.codec.is_some()
+        || properties.file_size.is_some();
+    if probed {
+        database.set_song_properties(&song.id, properties)?;
+    }
+    Ok(())
+}
+
+/**
+ * Restores a backup archive into the library of the given configuration,
+ * nothing is written on a dry run. Merging copies new playlists in order and
+ * appends to existing ones the songs they lack, without reordering them
+ */
+pub fn restore_backup(
+    config: &Config,
+    config_path: &Path,
+    archive: &Path,
+    mode: RestoreMode,
+    dry_run: bool,
+    profile: Option<&str>,
+) -> Result<RestoreReport> {
+    let staging = Staging::new()?;
+    unpack(archive, &staging.0)?;
+    let manifest: BackupManifest = match fs::read_to_string(staging.0.join(MANIFEST)) {
+        Ok(manifest) => serde_json::from_str(&manifest).map_err(std::io::Error::other)?,
+        Err(_) => {
+            return Err(YapError::NotFound(format!(
+                "{} is not a yap backup",
+                archive.display()
+            )))
+        }
+    };
+    let backup = open_database(&staging.0.join(DATABASE))?;
+
+    //When replacing, the library goes where the restored configuration says
+    let staged_config = staging.0.join(CONFIG);
+    let config_replaced = mode == RestoreMode::Replace && staged_config.is_file();
+    let target = if config_replaced {
+        let mut restored = Config::load(&staged_config)?;
+        restored.select_profile(profile)?;
+        restored.expand_paths()?;
+        restored
+    } else {
+        config.clone()
+    };
+    let database_path = &target.database.database_path;
+
+    let existing = match database_path.is_file() {
+        true => Some(open_database(database_path)?),
+        false => None,
+    };
+    let (current_songs, current_playlists) = match &existing {
+        Some(existing) => (
+            existing.get_songs()?,
+            existing
+                .get_playlists()?
+                .into_iter()
+                .map(|playlist| playlist.name)
+                .collect(),
+        ),
+        None => (Vec::new(), Vec::new()),
+    };
+    let backup_songs = backup.get_songs()?;
+    let songs_added: Vec<String> = backup_songs
+        .iter()
+        .filter(|song| !current_songs.iter().any(|current| current.id == song.id))
+        .map(|song| song.name.clone())
+        .collect();
+
+    let mut report = RestoreReport {
+        manifest,
+        songs_added,
+        songs_removed: Vec::new(),
+        playlists_added: Vec::new(),
+        playlists_merged: Vec::new(),
+        files_copied: 0,
+        config_replaced,
+    };
+
+    match mode {
+        RestoreMode::Replace => {
+            report.songs_removed = current_songs
+                .iter()
+                .filter(|song| !backup_songs.iter().any(|backup| backup.id == song.id))
+                .map(|song| song.name.clone())
+                .collect();
+            report.playlists_added = backup
+                .get_playlists()?
+                .into_iter()
+                .map(|playlist| playlist.name)
+                .filter(|name| !current_playlists.contains(name))
+                .collect();
+            if !dry_run {
+                if config_replaced {
+                    if let Some(parent) = config_path.parent() {
+                        fs::create_dir_all(parent)?;
+                    }
+                    fs::copy(&staged_config, config_path)?;
+                }
+                target.create_directories()?;
+                copy_database(&staging.0.join(DATABASE), database_path)?;
+            }
+        }
+        RestoreMode::Merge => {
+            let current = match (dry_run, existing.as_ref()) {
+                (true, _) => None,
+                (false, Some(existing)) => Some(existing.clone()),
+                (false, None) => {
+                    target.create_directories()?;
+                    Some(open_database(database_path)?)
+                }
+            };
+            if let Some(current) = &current {
+                for song in backup_songs.iter() {
+                    if current.get_song_by_id(&song.id)?.is_none() {
+                        insert_song(current, song)?;
+                    }
+                }
+            }
+            for playlist in backup.get_playlists()? {
+                let songs = backup.get_songs_of_playlist(&playlist.name)?;
+                let exists = current_playlists.contains(&playlist.name);
+                let present: Vec<String> = match (exists, &existing) {
+                    (true, Some(existing)) => existing
+                        .get_songs_of_playlist(&playlist.name)?
+                        .into_iter()
+                        .map(|song| song.song_id)
+                        .collect(),
+                    _ => Vec::new(),
+                };
+                let missing: Vec<&str> = songs
+                    .iter()
+                    .map(|song| song.song_id.as_str())
+                    .filter(|song_id| !present.iter().any(|present| present == song_id))
+                    .collect();
+                if !exists {
+                    report.playlists_added.push(playlist.name.clone());
+                } else if !missing.is_empty() {
+                    report.playlists_merged.push(playlist.name.clone());
+                }
+                if let Some(current) = &current {
+                    if !exists {
+                        current.insert_playlist(NewPlaylist {
+                            name: &playlist.name,
+                        })?;
+                    }
+                    //Repeated songs are kept, like in a saved queue
+                    for song_id in missing {
+                        current.append_to_playlist(NewPlaylistSong {
+                            playlist_name: &playlist.name,
+                            song_id,
+                        })?;
+                    }
+                }
+            }
+        }
+    }
+
+    let overwrite = mode == RestoreMode::Replace;
+    report.files_copied = copy_files(
+        &staging.0.join(MUSIC),
+        &target.general.music_directory,
+        overwrite,
+        dry_run,
+    )? + copy_files(
+        &staging.0.join(MINIATURES),
+        &target.general.miniature_directory,
+        overwrite,
+        dry_run,
+    )?;
+    Ok(report)
+}
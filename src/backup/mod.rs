@@ -0,0 +1,3 @@
+pub mod archive;
+#[allow(clippy::module_inception)]
+pub mod backup;
@@ -1,33 +1,372 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
 use serde::{Deserialize, Serialize};
+use toml::Value;
+
+use crate::{
+    error::{Result, YapError},
+    mpd::mpd_client::MpdClient,
+    utils::paths::{create_directory, expand_path},
+};
+
+pub const APP_NAME: &str = "yap";
+pub const CONFIG_NAME: &str = "yap.config";
 
-#[derive(Deserialize, Serialize)]
+#[derive(Deserialize, Serialize, Clone)]
 pub struct Config {
+    //Profile used when none is given with --profile or YAP_PROFILE
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profile: Option<String>,
     pub general: General,
     pub database: Database,
+    #[serde(default)]
+    pub notifications: Notifications,
+    #[serde(default)]
+    pub player: Player,
+    #[serde(default)]
+    pub mpd: Mpd,
+    #[serde(default)]
+    pub profiles: BTreeMap<String, Profile>,
 }
-#[derive(Deserialize, Serialize)]
+#[derive(Deserialize, Serialize, Clone)]
 pub struct General {
-    pub music_directory: String,
-    pub miniature_directory: String,
+    pub music_directory: PathBuf,
+    pub miniature_directory: PathBuf,
     pub download_miniature: bool,
+    //What to do with the silence at the start and the end of downloaded songs
+    #[serde(default)]
+    pub trim_silence: TrimSilence,
+}
+
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum TrimSilence {
+    //Songs are played whole
+    #[default]
+    Off,
+    //The silence is skipped with play ranges, the file stays as downloaded
+    Range,
+    //The silence is cut from the file
+    Rewrite,
 }
 
-#[derive(Deserialize, Serialize)]
+#[derive(Deserialize, Serialize, Clone)]
 pub struct Database {
-    pub database_path: String,
+    pub database_path: PathBuf,
+}
+
+#[derive(Deserialize, Serialize, Clone)]
+pub struct Notifications {
+    pub enabled: bool,
+    pub show_miniature: bool,
+    pub timeout: u32,
+}
+
+impl Default for Notifications {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            show_miniature: true,
+            timeout: 5000,
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Backend {
+    Mpd,
+    Local,
+}
+
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum SinkKind {
+    Command,
+    Null,
+    Wav,
+}
+
+#[derive(Deserialize, Serialize, Clone)]
+pub struct Player {
+    pub backend: Backend,
+    pub sink: SinkKind,
+    //Command reading signed 16 bit, 48kHz, stereo samples from stdin
+    pub sink_command: String,
+    pub wav_path: PathBuf,
+}
+
+#[derive(Deserialize, Serialize, Clone)]
+pub struct Mpd {
+    pub address: String,
+}
+
+impl Default for Mpd {
+    fn default() -> Self {
+        Self {
+            address: "127.0.0.1:6600".to_string(),
+        }
+    }
+}
+
+impl Default for Player {
+    fn default() -> Self {
+        Self {
+            backend: Backend::Mpd,
+            sink: SinkKind::Command,
+            sink_command: "aplay -q -f S16_LE -r 48000 -c 2".to_string(),
+            wav_path: PathBuf::from("yap.wav"),
+        }
+    }
+}
+
+/**
+ * A separate library, what isn't set is taken from the main configuration
+ */
+#[derive(Deserialize, Serialize, Clone, Default)]
+pub struct Profile {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub music_directory: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub miniature_directory: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub database_path: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mpd_address: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub backend: Option<Backend>,
 }
 
 impl Default for Config {
     fn default() -> Self {
         Self {
+            profile: None,
             general: General {
-                music_directory: "~/Music/songs/".to_string(),
-                miniature_directory: "~/Music/miniatures".to_string(),
+                music_directory: PathBuf::from("~/Music/songs"),
+                miniature_directory: PathBuf::from("~/Music/miniatures"),
                 download_miniature: false,
+                trim_silence: TrimSilence::Off,
             },
             database: Database {
-                database_path: "~/.config/yap/yap.db".to_string(),
+                database_path: PathBuf::from("$XDG_DATA_HOME/yap/yap.db"),
             },
+            notifications: Notifications::default(),
+            player: Player::default(),
+            mpd: Mpd::default(),
+            profiles: BTreeMap::new(),
+        }
+    }
+}
+
+/**
+ * The configuration file to use, the given one or the default one from confy
+ */
+pub fn config_path(path: Option<PathBuf>) -> Result<PathBuf> {
+    match path {
+        Some(path) => expand_path(&path),
+        None => Ok(confy::get_configuration_file_path(APP_NAME, CONFIG_NAME)?),
+    }
+}
+
+impl Config {
+    /**
+     * Loads the configuration file, writing the default one if it doesn't exist
+     */
+    pub fn load(path: &Path) -> Result<Self> {
+        Ok(confy::load_path(path)?)
+    }
+
+    pub fn store(&self, path: &Path) -> Result<()> {
+        Ok(confy::store_path(path, self)?)
+    }
+
+    /**
+     * Name of the profile in use, the given one or the one chosen with profile use,
+     * None when that is the main configuration, called default
+     */
+    pub fn active_profile<'a>(&'a self, profile: Option<&'a str>) -> Option<&'a str> {
+        profile
+            .or(self.profile.as_deref())
+            .filter(|name| *name != "default")
+    }
+
+    /**
+     * Applies the settings of the profile in use on top of the main configuration
+     */
+    pub fn select_profile(&mut self, profile: Option<&str>) -> Result<()> {
+        let name = match self.active_profile(profile) {
+            Some(name) => name.to_string(),
+            None => return Ok(()),
+        };
+        let profile = self
+            .profiles
+            .get(&name)
+            .cloned()
+            .ok_or(YapError::NotFound(format!(
+                "Profile {} doesn't exist",
+                name
+            )))?;
+        if let Some(music_directory) = profile.music_directory {
+            self.general.music_directory = music_directory;
+        }
+        if let Some(miniature_directory) = profile.miniature_directory {
+            self.general.miniature_directory = miniature_directory;
+        }
+        if let Some(database_path) = profile.database_path {
+            self.database.database_path = database_path;
+        }
+        if let Some(address) = profile.mpd_address {
+            self.mpd.address = address;
+        }
+        if let Some(backend) = profile.backend {
+            self.player.backend = backend;
+        }
+        Ok(())
+    }
+
+    pub fn to_toml(&self) -> Result<String> {
+        toml::to_string(self).map_err(|error| YapError::Config(error.to_string()))
+    }
+
+    /**
+     * Value of a setting given as section.key, or of a whole section
+     */
+    pub fn get(&self, key: &str) -> Result<Value> {
+        let mut value =
+            Value::try_from(self).map_err(|error| YapError::Config(error.to_string()))?;
+        for part in key.split('.') {
+            value = value
+                .get(part)
+                .cloned()
+                .ok_or(YapError::NotFound(format!("Unknown setting {}", key)))?;
+        }
+        Ok(value)
+    }
+
+    /**
+     * Changes a setting given as section.key, the value is parsed as the type the setting already has
+     */
+    pub fn set(&mut self, key: &str, value: &str) -> Result<()> {
+        let unknown = || YapError::NotFound(format!("Unknown setting {}", key));
+        let invalid =
+            |error: String| YapError::Config(format!("Invalid value for {}: {}", key, error));
+        let mut root =
+            Value::try_from(&*self).map_err(|error| YapError::Config(error.to_string()))?;
+        let (section, name) = key.rsplit_once('.').ok_or_else(unknown)?;
+        let mut table = &mut root;
+        for part in section.split('.') {
+            table = table.get_mut(part).ok_or_else(unknown)?;
+        }
+        let current = table.get_mut(name).ok_or_else(unknown)?;
+        *current = match current {
+            Value::String(_) => Value::String(value.to_string()),
+            Value::Boolean(_) => Value::Boolean(
+                value
+                    .parse()
+                    .map_err(|_| invalid(format!("{} is not true or false", value)))?,
+            ),
+            Value::Integer(_) => Value::Integer(
+                value
+                    .parse()
+                    .map_err(|_| invalid(format!("{} is not an integer", value)))?,
+            ),
+            Value::Float(_) => Value::Float(
+                value
+                    .parse()
+                    .map_err(|_| invalid(format!("{} is not a number", value)))?,
+            ),
+            _ => {
+                return Err(YapError::Config(format!(
+                    "{} is a section, set its keys instead",
+                    key
+                )))
+            }
+        };
+        *self = root
+            .try_into()
+            .map_err(|error: toml::de::Error| invalid(error.to_string()))?;
+        Ok(())
+    }
+
+    /**
+     * Checks that the configured directories exist and that MPD plays from the music directory
+     */
+    pub fn validate(&self) -> Result<()> {
+        let mut config = self.clone();
+        config.expand_paths()?;
+        let mut problems = Vec::new();
+        let mut directories = vec![("music_directory", config.general.music_directory.clone())];
+        if config.general.download_miniature {
+            directories.push((
+                "miniature_directory",
+                config.general.miniature_directory.clone(),
+            ));
+        }
+        if let Some(parent) = config.database.database_path.parent() {
+            if !parent.as_os_str().is_empty() {
+                directories.push(("database_path directory", parent.to_path_buf()));
+            }
+        }
+        for (name, directory) in directories {
+            if !directory.is_dir() {
+                problems.push(format!("{} {} doesn't exist", name, directory.display()));
+            }
+        }
+        if config.player.backend == Backend::Mpd {
+            match MpdClient::new(config.mpd.address.clone()).music_directory() {
+                Ok(mpd_directory) => {
+                    let canonical =
+                        |path: &Path| fs::canonicalize(path).unwrap_or(path.to_path_buf());
+                    if canonical(&mpd_directory) != canonical(&config.general.music_directory) {
+                        problems.push(format!(
+                            "MPD plays from {} but music_directory is {}",
+                            mpd_directory.display(),
+                            config.general.music_directory.display()
+                        ));
+                    }
+                }
+                Err(error) => {
+                    problems.push(format!("Couldn't get MPD's music directory: {}", error))
+                }
+            }
+        }
+        if problems.is_empty() {
+            return Ok(());
+        }
+        Err(YapError::Config(format!(
+            "Configuration is not valid:\n{}",
+            problems.join("\n")
+        )))
+    }
+
+    /**
+     * Expands ~ and environment variables in every configured path
+     */
+    pub fn expand_paths(&mut self) -> Result<()> {
+        self.general.music_directory = expand_path(&self.general.music_directory)?;
+        self.general.miniature_directory = expand_path(&self.general.miniature_directory)?;
+        self.database.database_path = expand_path(&self.database.database_path)?;
+        self.player.wav_path = expand_path(&self.player.wav_path)?;
+        Ok(())
+    }
+
+    /**
+     * Creates the directories yap writes to, the miniature one only when miniatures are downloaded
+     */
+    pub fn create_directories(&self) -> Result<()> {
+        create_directory("music directory", &self.general.music_directory)?;
+        if self.general.download_miniature {
+            create_directory("miniature directory", &self.general.miniature_directory)?;
+        }
+        if let Some(parent) = self.database.database_path.parent() {
+            if !parent.as_os_str().is_empty() {
+                create_directory("database directory", parent)?;
+            }
         }
+        Ok(())
     }
 }
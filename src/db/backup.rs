@@ -0,0 +1,81 @@
+use std::{
+    ffi::{CStr, CString},
+    path::Path,
+    ptr, thread,
+    time::Duration,
+};
+
+use libsqlite3_sys as ffi;
+
+use crate::error::{Result, YapError};
+
+//Pages copied per step, the source is only locked while a step runs
+const PAGES_PER_STEP: i32 = 256;
+
+/**
+ * A raw sqlite connection, closed when dropped
+ */
+struct Connection(*mut ffi::sqlite3);
+
+impl Connection {
+    fn open(path: &Path, flags: i32) -> Result<Self> {
+        let path = CString::new(path.to_string_lossy().as_bytes())
+            .map_err(|_| YapError::db(format!("Invalid database path {}", path.display())))?;
+        let mut handle = ptr::null_mut();
+        let code = unsafe { ffi::sqlite3_open_v2(path.as_ptr(), &mut handle, flags, ptr::null()) };
+        let connection = Self(handle);
+        if code != ffi::SQLITE_OK {
+            return Err(connection.error());
+        }
+        Ok(connection)
+    }
+
+    fn error(&self) -> YapError {
+        if self.0.is_null() {
+            return YapError::db("Out of memory opening the database");
+        }
+        let message = unsafe { CStr::from_ptr(ffi::sqlite3_errmsg(self.0)) };
+        YapError::db(message.to_string_lossy())
+    }
+}
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        unsafe {
+            ffi::sqlite3_close(self.0);
+        }
+    }
+}
+
+/**
+ * Copies a database with sqlite's online backup API, the copy is consistent
+ * even while other yap processes keep writing to the source
+ */
+pub fn copy_database(source: &Path, destination: &Path) -> Result<()> {
+    let source = Connection::open(source, ffi::SQLITE_OPEN_READONLY)?;
+    let destination = Connection::open(
+        destination,
+        ffi::SQLITE_OPEN_READWRITE | ffi::SQLITE_OPEN_CREATE,
+    )?;
+    let main = CString::new("main").unwrap_or_default();
+    let backup =
+        unsafe { ffi::sqlite3_backup_init(destination.0, main.as_ptr(), source.0, main.as_ptr()) };
+    if backup.is_null() {
+        return Err(destination.error());
+    }
+    loop {
+        match unsafe { ffi::sqlite3_backup_step(backup, PAGES_PER_STEP) } {
+            ffi::SQLITE_OK => {}
+            ffi::SQLITE_DONE => break,
+            ffi::SQLITE_BUSY | ffi::SQLITE_LOCKED => thread::sleep(Duration::from_millis(50)),
+            _ => {
+                unsafe { ffi::sqlite3_backup_finish(backup) };
+                return Err(destination.error());
+            }
+        }
+    }
+    if unsafe { ffi::sqlite3_backup_finish(backup) } != ffi::SQLITE_OK {
+        return Err(destination.error());
+    }
+    Ok(())
+}
@@ -1,23 +1,59 @@
 use diesel::{
-    r2d2::{ConnectionManager, Pool, PooledConnection},
+    connection::SimpleConnection,
+    r2d2::{ConnectionManager, CustomizeConnection, Pool, PooledConnection},
     SqliteConnection,
 };
 use diesel_migrations::*;
 
+use crate::error::{Result, YapError};
+
 use super::{
-    models::{NewPlaylist, NewPlaylistSong, NewSong, Playlist, PlaylistSong, Song},
+    models::{
+        NewPlaylist, NewPlaylistSong, NewSchedule, NewSong, Playlist, PlaylistDuration,
+        PlaylistSong, QueueSnapshotSong, Schedule, Setting, Song, SongLoudness, SongProperties,
+    },
     ops::{
         playlist_ops::{delete_playlist, get_playlist_by_name, get_playlists, insert_playlist},
-        playlist_song_ops::{add_songs_to_playlist, delete_playlist_song, get_songs_of_playlist},
-        song_ops::{delete_song, get_song_by_id, get_song_by_name, get_songs, insert_song},
+        playlist_song_ops::{
+            add_songs_to_playlist, append_to_playlist, delete_playlist_song,
+            get_playlist_durations, get_songs_of_playlist,
+        },
+        queue_snapshot_ops::{get_queue_snapshot, replace_queue_snapshot},
+        schedule_ops::{
+            delete_schedule, get_due_schedules, get_schedules, insert_schedule, reschedule,
+        },
+        setting_ops::{compare_and_set_setting, get_setting, set_setting},
+        song_ops::{
+            delete_song, get_song_by_id, get_song_by_name, get_songs, insert_song,
+            set_song_loudness, set_song_offsets, set_song_properties,
+        },
     },
 };
 
 type MysqlitePool = Pool<ConnectionManager<SqliteConnection>>;
 
+/**
+ * Waits for the lock when another connection is writing, like the local player
+ * saving its progress while a command runs, instead of failing right away
+ */
+#[derive(Debug)]
+struct BusyTimeout;
+
+impl CustomizeConnection<SqliteConnection, diesel::r2d2::Error> for BusyTimeout {
+    fn on_acquire(
+        &self,
+        connection: &mut SqliteConnection,
+    ) -> std::result::Result<(), diesel::r2d2::Error> {
+        connection
+            .batch_execute("PRAGMA busy_timeout = 5000;")
+            .map_err(diesel::r2d2::Error::QueryError)
+    }
+}
+
 /**
  * Struct di gestione del database
  */
+#[derive(Clone)]
 pub struct Database {
     //Il pool di connessioni al database
     pool: MysqlitePool,
@@ -29,10 +65,12 @@ impl Database {
      * url: l'url per collegarsi al database
      * max_size: quante connessioni possono avvenire simultaneamente
      */
-    pub fn new(url: String, max_size: u32) -> anyhow::Result<Self> {
+    pub fn new(url: String, max_size: u32) -> Result<Self> {
         let pool = MysqlitePool::builder()
             .max_size(max_size)
-            .build(ConnectionManager::new(url))?;
+            .connection_customizer(Box::new(BusyTimeout))
+            .build(ConnectionManager::new(url))
+            .map_err(YapError::db)?;
 
         Ok(Self { pool })
     }
@@ -40,64 +78,150 @@ impl Database {
     /**
      * Metodo per ottenere una connessione al database
      */
-    fn get_connection(&self) -> PooledConnection<ConnectionManager<diesel::SqliteConnection>> {
-        self.pool.get().unwrap()
+    fn get_connection(
+        &self,
+    ) -> Result<PooledConnection<ConnectionManager<diesel::SqliteConnection>>> {
+        self.pool.get().map_err(YapError::db)
     }
 
     /**
      * Metodo per eseguire le migrazioni se queste non sono ancora state eseguite
      */
-    pub fn run_embedded_migrations(&self) -> bool {
+    pub fn run_embedded_migrations(&self) -> Result<()> {
         const MIGRATIONS: EmbeddedMigrations = embed_migrations!();
-        self.get_connection()
+        self.get_connection()?
             .run_pending_migrations(MIGRATIONS)
-            .is_ok()
+            .map_err(YapError::db)?;
+        Ok(())
     }
 
-    pub fn get_songs(&self) -> Option<Vec<Song>> {
-        get_songs(&mut self.get_connection())
+    pub fn get_songs(&self) -> Result<Vec<Song>> {
+        get_songs(&mut *self.get_connection()?)
     }
 
-    pub fn get_song_by_name(&self, song_name: &str) -> Option<Song> {
-        get_song_by_name(&mut self.get_connection(), song_name)
+    pub fn get_song_by_name(&self, song_name: &str) -> Result<Option<Song>> {
+        get_song_by_name(&mut *self.get_connection()?, song_name)
     }
 
-    pub fn get_song_by_id(&self, song_id: &str) -> Option<Song> {
-        get_song_by_id(&mut self.get_connection(), song_id)
+    pub fn get_song_by_id(&self, song_id: &str) -> Result<Option<Song>> {
+        get_song_by_id(&mut *self.get_connection()?, song_id)
     }
 
-    pub fn insert_song(&self, new_song: NewSong) -> bool {
-        insert_song(&mut self.get_connection(), new_song)
+    pub fn insert_song(&self, new_song: NewSong) -> Result<()> {
+        insert_song(&mut *self.get_connection()?, new_song)
     }
 
-    pub fn delete_song(&self, song_id: &str) -> bool {
-        delete_song(&mut self.get_connection(), song_id)
+    pub fn set_song_loudness(&self, song_id: &str, loudness: SongLoudness) -> Result<()> {
+        set_song_loudness(&mut *self.get_connection()?, song_id, loudness)
     }
 
-    pub fn get_playlists(&self) -> Option<Vec<Playlist>> {
-        get_playlists(&mut self.get_connection())
+    pub fn set_song_properties(&self, song_id: &str, properties: SongProperties) -> Result<()> {
+        set_song_properties(&mut *self.get_connection()?, song_id, properties)
     }
 
-    pub fn insert_playlist(&self, new_playlist: NewPlaylist) -> bool {
-        insert_playlist(&mut self.get_connection(), new_playlist)
+    pub fn set_song_offsets(
+        &self,
+        song_id: &str,
+        start: Option<f64>,
+        end: Option<f64>,
+    ) -> Result<()> {
+        set_song_offsets(&mut *self.get_connection()?, song_id, start, end)
     }
 
-    pub fn delete_playlist(&self, playlist_name: &str) -> bool {
-        delete_playlist(&mut self.get_connection(), playlist_name)
+    pub fn delete_song(&self, song_id: &str) -> Result<bool> {
+        delete_song(&mut *self.get_connection()?, song_id)
     }
 
-    pub fn add_songs_to_playlist(&self, song: NewPlaylistSong) -> bool {
-        add_songs_to_playlist(&mut self.get_connection(), song)
+    pub fn get_playlists(&self) -> Result<Vec<Playlist>> {
+        get_playlists(&mut *self.get_connection()?)
     }
 
-    pub fn get_songs_of_playlist(&self, playlist_name: &str) -> Option<Vec<PlaylistSong>> {
-        get_songs_of_playlist(&mut self.get_connection(), playlist_name)
+    pub fn insert_playlist(&self, new_playlist: NewPlaylist) -> Result<()> {
+        insert_playlist(&mut *self.get_connection()?, new_playlist)
     }
-    pub fn get_playlist_by_name(&self, playlist_name: &str) -> Option<Playlist> {
-        get_playlist_by_name(&mut self.get_connection(), playlist_name)
+
+    pub fn delete_playlist(&self, playlist_name: &str) -> Result<bool> {
+        delete_playlist(&mut *self.get_connection()?, playlist_name)
+    }
+
+    pub fn add_songs_to_playlist(&self, song: NewPlaylistSong) -> Result<()> {
+        add_songs_to_playlist(&mut *self.get_connection()?, song)
+    }
+
+    pub fn append_to_playlist(&self, song: NewPlaylistSong) -> Result<()> {
+        append_to_playlist(&mut *self.get_connection()?, song)
+    }
+
+    pub fn get_songs_of_playlist(&self, playlist_name: &str) -> Result<Vec<PlaylistSong>> {
+        get_songs_of_playlist(&mut *self.get_connection()?, playlist_name)
+    }
+    pub fn get_playlist_by_name(&self, playlist_name: &str) -> Result<Option<Playlist>> {
+        get_playlist_by_name(&mut *self.get_connection()?, playlist_name)
+    }
+
+    pub fn get_playlist_durations(&self) -> Result<Vec<PlaylistDuration>> {
+        get_playlist_durations(&mut *self.get_connection()?)
+    }
+
+    pub fn delete_playlist_song(&self, playlist_name: &str, song_id: &str) -> Result<bool> {
+        delete_playlist_song(&mut *self.get_connection()?, playlist_name, song_id)
+    }
+
+    pub fn get_queue_snapshot(&self) -> Result<Vec<QueueSnapshotSong>> {
+        get_queue_snapshot(&mut *self.get_connection()?)
+    }
+
+    pub fn replace_queue_snapshot(&self, songs: Vec<QueueSnapshotSong>) -> Result<()> {
+        replace_queue_snapshot(&mut *self.get_connection()?, songs)
+    }
+
+    pub fn get_setting(&self, key: &str) -> Result<Option<String>> {
+        Ok(get_setting(&mut *self.get_connection()?, key)?.map(|setting| setting.value))
+    }
+
+    pub fn set_setting(&self, key: &str, value: &str) -> Result<()> {
+        set_setting(
+            &mut *self.get_connection()?,
+            Setting {
+                key: key.to_string(),
+                value: value.to_string(),
+            },
+        )
+    }
+
+    pub fn compare_and_set_setting(
+        &self,
+        key: &str,
+        expected: Option<&str>,
+        value: &str,
+    ) -> Result<bool> {
+        compare_and_set_setting(
+            &mut *self.get_connection()?,
+            expected,
+            Setting {
+                key: key.to_string(),
+                value: value.to_string(),
+            },
+        )
+    }
+
+    pub fn get_schedules(&self) -> Result<Vec<Schedule>> {
+        get_schedules(&mut *self.get_connection()?)
+    }
+
+    pub fn get_due_schedules(&self, now: i64) -> Result<Vec<Schedule>> {
+        get_due_schedules(&mut *self.get_connection()?, now)
+    }
+
+    pub fn insert_schedule(&self, new_schedule: NewSchedule) -> Result<()> {
+        insert_schedule(&mut *self.get_connection()?, new_schedule)
+    }
+
+    pub fn reschedule(&self, schedule_id: i32, next_run: i64) -> Result<()> {
+        reschedule(&mut *self.get_connection()?, schedule_id, next_run)
     }
 
-    pub fn delete_playlist_song(&self, playlist_song: PlaylistSong) -> bool {
-        delete_playlist_song(&mut self.get_connection(), playlist_song)
+    pub fn delete_schedule(&self, schedule_id: i32) -> Result<bool> {
+        delete_schedule(&mut *self.get_connection()?, schedule_id)
     }
 }
@@ -1,5 +1,6 @@
 use crate::db::schema::*;
 use diesel::prelude::*;
+use serde::Serialize;
 // Generated by diesel_ext
 
 #[derive(Queryable, Debug)]
@@ -14,10 +15,21 @@ pub struct NewPlaylist<'a> {
 }
 
 #[derive(Queryable, Debug)]
-#[diesel(primary_key(playlist_id, song_id))]
+#[diesel(primary_key(playlist_id, position))]
 pub struct PlaylistSong {
     pub playlist_id: String,
     pub song_id: String,
+    pub position: i32,
+}
+
+#[derive(Queryable, Debug)]
+pub struct PlaylistDuration {
+    pub playlist_name: String,
+    pub songs: i64,
+    //Songs with a known duration, the only ones counted in duration
+    pub timed_songs: i64,
+    //Seconds
+    pub duration: Option<f64>,
 }
 
 #[derive(Insertable)]
@@ -27,11 +39,85 @@ pub struct NewPlaylistSong<'a> {
     pub song_id: &'a str,
 }
 
+#[derive(Queryable, Insertable, Debug)]
+#[diesel(table_name = queue_snapshot)]
+pub struct QueueSnapshotSong {
+    pub position: i32,
+    pub file: String,
+    //Part of the file that was queued, in seconds
+    pub start_offset: Option<f64>,
+    pub end_offset: Option<f64>,
+}
+
 #[derive(Queryable, Debug)]
+pub struct Schedule {
+    pub id: i32,
+    pub kind: String,
+    pub run_at: i64,
+    pub playlist_name: Option<String>,
+    pub fade: i32,
+    pub daily: bool,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = schedule)]
+pub struct NewSchedule<'a> {
+    pub kind: &'a str,
+    pub run_at: i64,
+    pub playlist_name: Option<&'a str>,
+    pub fade: i32,
+    pub daily: bool,
+}
+
+#[derive(Queryable, Insertable, AsChangeset, Debug)]
+#[diesel(table_name = setting)]
+pub struct Setting {
+    pub key: String,
+    pub value: String,
+}
+
+#[derive(Queryable, Serialize, Debug, Clone)]
 pub struct Song {
     pub id: String,
     pub name: String,
     pub artist: Option<String>,
+    pub source: String,
+    //EBU R128 measurements, missing until the song is analyzed
+    pub loudness: Option<f64>,
+    pub true_peak: Option<f64>,
+    pub gain: Option<f64>,
+    //Probed from the audio file, duration in seconds and bitrate in bits per second
+    pub duration: Option<f64>,
+    pub bitrate: Option<i32>,
+    pub sample_rate: Option<i32>,
+    pub channels: Option<i32>,
+    pub codec: Option<String>,
+    pub file_size: Option<i64>,
+    //Part of the file that gets played, in seconds from its start
+    pub start_offset: Option<f64>,
+    pub end_offset: Option<f64>,
+    //Video a chapter was cut from
+    pub parent_id: Option<String>,
+}
+
+#[derive(AsChangeset)]
+#[diesel(table_name = song)]
+pub struct SongLoudness {
+    pub loudness: f64,
+    pub true_peak: f64,
+    pub gain: f64,
+}
+
+//Missing values leave the stored ones untouched
+#[derive(AsChangeset)]
+#[diesel(table_name = song)]
+pub struct SongProperties {
+    pub duration: Option<f64>,
+    pub bitrate: Option<i32>,
+    pub sample_rate: Option<i32>,
+    pub channels: Option<i32>,
+    pub codec: Option<String>,
+    pub file_size: Option<i64>,
 }
 
 #[derive(Insertable)]
@@ -40,4 +126,6 @@ pub struct NewSong<'a> {
     pub id: &'a str,
     pub name: &'a str,
     pub artist: Option<String>,
+    pub source: &'a str,
+    pub parent_id: Option<&'a str>,
 }
@@ -1,3 +1,6 @@
 pub mod playlist_ops;
 pub mod playlist_song_ops;
+pub mod queue_snapshot_ops;
+pub mod schedule_ops;
+pub mod setting_ops;
 pub mod song_ops;
@@ -1,38 +1,50 @@
-use diesel::{ExpressionMethods, QueryDsl, RunQueryDsl, SqliteConnection};
+use diesel::{ExpressionMethods, OptionalExtension, QueryDsl, RunQueryDsl, SqliteConnection};
 
-use crate::db::{
-    models::{NewPlaylist, Playlist},
-    schema::playlist::dsl::playlist,
+use crate::{
+    db::{
+        models::{NewPlaylist, Playlist},
+        schema::playlist::dsl::playlist,
+    },
+    error::{Result, YapError},
 };
 
-pub fn get_playlists(connnection: &mut SqliteConnection) -> Option<Vec<Playlist>> {
-    playlist.load(connnection).ok()
+pub fn get_playlists(connnection: &mut SqliteConnection) -> Result<Vec<Playlist>> {
+    Ok(playlist.load(connnection)?)
 }
 
-pub fn insert_playlist(connnection: &mut SqliteConnection, new_playlist: NewPlaylist) -> bool {
+pub fn insert_playlist(
+    connnection: &mut SqliteConnection,
+    new_playlist: NewPlaylist,
+) -> Result<()> {
     diesel::insert_into(playlist)
-        .values(new_playlist)
+        .values(&new_playlist)
         .execute(connnection)
-        .is_ok()
+        .map_err(|error| {
+            YapError::on_conflict(
+                error,
+                format!("Playlist {} already exists", new_playlist.name),
+            )
+        })?;
+    Ok(())
 }
 
-pub fn delete_playlist(connection: &mut SqliteConnection, playlist_name: &str) -> bool {
+pub fn delete_playlist(connection: &mut SqliteConnection, playlist_name: &str) -> Result<bool> {
     use crate::db::schema::playlist::dsl::name;
 
-    let _activated_foreign_key = diesel::sql_query("PRAGMA foreign_keys = ON;").execute(connection);
-    diesel::delete(playlist)
+    diesel::sql_query("PRAGMA foreign_keys = ON;").execute(connection)?;
+    let deleted = diesel::delete(playlist)
         .filter(name.eq(playlist_name))
-        .execute(connection)
-        .is_ok()
+        .execute(connection)?;
+    Ok(deleted > 0)
 }
 
 pub fn get_playlist_by_name(
     connnection: &mut SqliteConnection,
     playlist_name: &str,
-) -> Option<Playlist> {
+) -> Result<Option<Playlist>> {
     use crate::db::schema::playlist::dsl::name;
-    playlist
+    Ok(playlist
         .filter(name.eq(playlist_name))
         .first::<Playlist>(connnection)
-        .ok()
+        .optional()?)
 }
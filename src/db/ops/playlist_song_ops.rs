@@ -1,37 +1,100 @@
 use diesel::{ExpressionMethods, QueryDsl, RunQueryDsl, SqliteConnection};
 
-use crate::db::{
-    models::{NewPlaylistSong, PlaylistSong},
-    schema::playlist_song::dsl::playlist_song,
+use crate::{
+    db::{
+        models::{NewPlaylistSong, PlaylistDuration, PlaylistSong},
+        schema::playlist_song::dsl::playlist_song,
+    },
+    error::{Result, YapError},
 };
 
-pub fn add_songs_to_playlist(connection: &mut SqliteConnection, song: NewPlaylistSong) -> bool {
+/**
+ * Adds a song at the end of a playlist, AlreadyExists if it's in the playlist
+ */
+pub fn add_songs_to_playlist(
+    connection: &mut SqliteConnection,
+    song: NewPlaylistSong,
+) -> Result<()> {
+    use crate::db::schema::playlist_song::dsl::{playlist_name as name, song_id};
+
+    let existing = playlist_song
+        .filter(name.eq(song.playlist_name))
+        .filter(song_id.eq(song.song_id))
+        .count()
+        .get_result::<i64>(connection)?;
+    if existing > 0 {
+        return Err(YapError::AlreadyExists(format!(
+            "Song is already in playlist {}",
+            song.playlist_name
+        )));
+    }
+    append_to_playlist(connection, song)
+}
+
+/**
+ * Adds a song at the end of a playlist even if it's already in it, like a queue
+ * holding the same song twice
+ */
+pub fn append_to_playlist(connection: &mut SqliteConnection, song: NewPlaylistSong) -> Result<()> {
+    use crate::db::schema::playlist_song::dsl::{playlist_name as name, position};
+
+    let next_position = playlist_song
+        .filter(name.eq(song.playlist_name))
+        .select(diesel::dsl::max(position))
+        .first::<Option<i32>>(connection)?
+        .map_or(0, |last| last + 1);
+    let playlist_name = song.playlist_name;
     diesel::insert_into(playlist_song)
-        .values(song)
+        .values((song, position.eq(next_position)))
         .execute(connection)
-        .is_ok()
+        .map_err(|error| {
+            YapError::on_conflict(
+                error,
+                format!("Playlist {} changed while adding a song", playlist_name),
+            )
+        })?;
+    Ok(())
 }
 
 pub fn get_songs_of_playlist(
     connection: &mut SqliteConnection,
     playlist_name: &str,
-) -> Option<Vec<PlaylistSong>> {
-    use crate::db::schema::playlist_song::dsl::playlist_name as name;
-    playlist_song
+) -> Result<Vec<PlaylistSong>> {
+    use crate::db::schema::playlist_song::dsl::{playlist_name as name, position};
+    Ok(playlist_song
         .filter(name.eq(playlist_name))
-        .load(connection)
-        .ok()
+        .order(position)
+        .load(connection)?)
+}
+
+/**
+ * Song count and total duration of every playlist that has songs
+ */
+pub fn get_playlist_durations(connection: &mut SqliteConnection) -> Result<Vec<PlaylistDuration>> {
+    use crate::db::schema::{playlist_song::dsl::playlist_name, song};
+    use diesel::dsl::count;
+    Ok(playlist_song
+        .inner_join(song::table)
+        .group_by(playlist_name)
+        .select((
+            playlist_name,
+            count(song::id),
+            count(song::duration),
+            diesel::dsl::sum(song::duration),
+        ))
+        .load(connection)?)
 }
 
 pub fn delete_playlist_song(
     connection: &mut SqliteConnection,
-    playlist_song_to_delete: PlaylistSong,
-) -> bool {
+    playlist_name: &str,
+    song_id: &str,
+) -> Result<bool> {
     use crate::db::schema::playlist_song::dsl::{playlist_name as name, song_id as song};
 
-    diesel::delete(playlist_song)
-        .filter(name.eq(playlist_song_to_delete.playlist_id))
-        .filter(song.eq(playlist_song_to_delete.song_id))
-        .execute(connection)
-        .is_ok()
+    let deleted = diesel::delete(playlist_song)
+        .filter(name.eq(playlist_name))
+        .filter(song.eq(song_id))
+        .execute(connection)?;
+    Ok(deleted > 0)
 }
@@ -0,0 +1,25 @@
+use diesel::prelude::*;
+use diesel::SqliteConnection;
+
+use crate::db::models::QueueSnapshotSong;
+use crate::db::schema::queue_snapshot::dsl::queue_snapshot;
+use crate::error::Result;
+
+pub fn get_queue_snapshot(connection: &mut SqliteConnection) -> Result<Vec<QueueSnapshotSong>> {
+    use crate::db::schema::queue_snapshot::dsl::position;
+    Ok(queue_snapshot.order(position).load(connection)?)
+}
+
+pub fn replace_queue_snapshot(
+    connection: &mut SqliteConnection,
+    songs: Vec<QueueSnapshotSong>,
+) -> Result<()> {
+    connection.transaction::<_, diesel::result::Error, _>(|connection| {
+        diesel::delete(queue_snapshot).execute(connection)?;
+        diesel::insert_into(queue_snapshot)
+            .values(songs)
+            .execute(connection)?;
+        Ok(())
+    })?;
+    Ok(())
+}
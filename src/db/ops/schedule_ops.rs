@@ -0,0 +1,44 @@
+use diesel::prelude::*;
+use diesel::SqliteConnection;
+
+use crate::db::models::{NewSchedule, Schedule};
+use crate::db::schema::schedule::dsl::schedule;
+use crate::error::Result;
+
+pub fn get_schedules(connection: &mut SqliteConnection) -> Result<Vec<Schedule>> {
+    use crate::db::schema::schedule::dsl::run_at;
+    Ok(schedule.order(run_at).load(connection)?)
+}
+
+pub fn get_due_schedules(connection: &mut SqliteConnection, now: i64) -> Result<Vec<Schedule>> {
+    use crate::db::schema::schedule::dsl::run_at;
+    Ok(schedule
+        .filter(run_at.le(now))
+        .order(run_at)
+        .load(connection)?)
+}
+
+pub fn insert_schedule(connection: &mut SqliteConnection, new_schedule: NewSchedule) -> Result<()> {
+    diesel::insert_into(schedule)
+        .values(new_schedule)
+        .execute(connection)?;
+    Ok(())
+}
+
+pub fn reschedule(
+    connection: &mut SqliteConnection,
+    schedule_id: i32,
+    next_run: i64,
+) -> Result<()> {
+    use crate::db::schema::schedule::dsl::{id, run_at};
+    diesel::update(schedule.filter(id.eq(schedule_id)))
+        .set(run_at.eq(next_run))
+        .execute(connection)?;
+    Ok(())
+}
+
+pub fn delete_schedule(connection: &mut SqliteConnection, schedule_id: i32) -> Result<bool> {
+    use crate::db::schema::schedule::dsl::id;
+    let deleted = diesel::delete(schedule.filter(id.eq(schedule_id))).execute(connection)?;
+    Ok(deleted > 0)
+}
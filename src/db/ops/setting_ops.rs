@@ -0,0 +1,54 @@
+use diesel::prelude::*;
+use diesel::result::{DatabaseErrorKind, Error as DieselError};
+use diesel::SqliteConnection;
+
+use crate::db::models::Setting;
+use crate::db::schema::setting::dsl::setting;
+use crate::error::Result;
+
+pub fn get_setting(
+    connection: &mut SqliteConnection,
+    setting_key: &str,
+) -> Result<Option<Setting>> {
+    use crate::db::schema::setting::dsl::key;
+    Ok(setting
+        .filter(key.eq(setting_key))
+        .first::<Setting>(connection)
+        .optional()?)
+}
+
+pub fn set_setting(connection: &mut SqliteConnection, new_setting: Setting) -> Result<()> {
+    diesel::replace_into(setting)
+        .values(new_setting)
+        .execute(connection)?;
+    Ok(())
+}
+
+/**
+ * Changes a setting only if it still has the expected value, a missing expected value
+ * means the setting must not exist yet
+ */
+pub fn compare_and_set_setting(
+    connection: &mut SqliteConnection,
+    expected: Option<&str>,
+    new_setting: Setting,
+) -> Result<bool> {
+    use crate::db::schema::setting::dsl::{key, value};
+    match expected {
+        Some(expected) => Ok(diesel::update(setting)
+            .filter(key.eq(&new_setting.key))
+            .filter(value.eq(expected))
+            .set(value.eq(&new_setting.value))
+            .execute(connection)?
+            == 1),
+        None => match diesel::insert_into(setting)
+            .values(new_setting)
+            .execute(connection)
+        {
+            Ok(_) => Ok(true),
+            //Someone else created it first
+            Err(DieselError::DatabaseError(DatabaseErrorKind::UniqueViolation, _)) => Ok(false),
+            Err(error) => Err(error.into()),
+        },
+    }
+}
@@ -1,38 +1,89 @@
 use diesel::prelude::*;
 use diesel::SqliteConnection;
 
-use crate::db::models::{NewSong, Song};
+use crate::db::models::{NewSong, Song, SongLoudness, SongProperties};
 use crate::db::schema::song::dsl::song;
+use crate::error::{Result, YapError};
 
-pub fn get_songs(connection: &mut SqliteConnection) -> Option<Vec<Song>> {
-    song.load(connection).ok()
+pub fn get_songs(connection: &mut SqliteConnection) -> Result<Vec<Song>> {
+    Ok(song.load(connection)?)
 }
 
-pub fn insert_song(connection: &mut SqliteConnection, new_song: NewSong) -> bool {
+pub fn insert_song(connection: &mut SqliteConnection, new_song: NewSong) -> Result<()> {
     diesel::insert_into(song)
-        .values(new_song)
+        .values(&new_song)
         .execute(connection)
-        .is_ok()
+        .map_err(|error| {
+            YapError::on_conflict(error, format!("Song {} already exists", new_song.id))
+        })?;
+    Ok(())
 }
 
-pub fn get_song_by_name(connection: &mut SqliteConnection, song_name: &str) -> Option<Song> {
+pub fn get_song_by_name(
+    connection: &mut SqliteConnection,
+    song_name: &str,
+) -> Result<Option<Song>> {
     use crate::db::schema::song::dsl::name;
-    song.filter(name.eq(song_name))
+    Ok(song
+        .filter(name.eq(song_name))
         .first::<Song>(connection)
-        .ok()
+        .optional()?)
 }
 
-pub fn get_song_by_id(connection: &mut SqliteConnection, song_id: &str) -> Option<Song> {
+pub fn get_song_by_id(connection: &mut SqliteConnection, song_id: &str) -> Result<Option<Song>> {
     use crate::db::schema::song::dsl::id;
-    song.filter(id.eq(song_id)).first::<Song>(connection).ok()
+    Ok(song
+        .filter(id.eq(song_id))
+        .first::<Song>(connection)
+        .optional()?)
+}
+
+pub fn set_song_loudness(
+    connection: &mut SqliteConnection,
+    song_id: &str,
+    loudness: SongLoudness,
+) -> Result<()> {
+    use crate::db::schema::song::dsl::id;
+    diesel::update(song.filter(id.eq(song_id)))
+        .set(&loudness)
+        .execute(connection)?;
+    Ok(())
 }
 
-pub fn delete_song(connection: &mut SqliteConnection, song_id: &str) -> bool {
+pub fn set_song_properties(
+    connection: &mut SqliteConnection,
+    song_id: &str,
+    properties: SongProperties,
+) -> Result<()> {
     use crate::db::schema::song::dsl::id;
+    diesel::update(song.filter(id.eq(song_id)))
+        .set(&properties)
+        .execute(connection)?;
+    Ok(())
+}
 
-    let _activated_foreign_key = diesel::sql_query("PRAGMA foreign_keys = ON;").execute(connection);
-    diesel::delete(song)
+/**
+ * Sets the part of a song that gets played, None plays from the start or to the end
+ */
+pub fn set_song_offsets(
+    connection: &mut SqliteConnection,
+    song_id: &str,
+    start: Option<f64>,
+    end: Option<f64>,
+) -> Result<()> {
+    use crate::db::schema::song::dsl::{end_offset, id, start_offset};
+    diesel::update(song.filter(id.eq(song_id)))
+        .set((start_offset.eq(start), end_offset.eq(end)))
+        .execute(connection)?;
+    Ok(())
+}
+
+pub fn delete_song(connection: &mut SqliteConnection, song_id: &str) -> Result<bool> {
+    use crate::db::schema::song::dsl::id;
+
+    diesel::sql_query("PRAGMA foreign_keys = ON;").execute(connection)?;
+    let deleted = diesel::delete(song)
         .filter(id.eq(song_id))
-        .execute(connection)
-        .is_ok()
+        .execute(connection)?;
+    Ok(deleted > 0)
 }
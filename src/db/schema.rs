@@ -7,9 +7,37 @@ diesel::table! {
 }
 
 diesel::table! {
-    playlist_song (playlist_name, song_id) {
+    playlist_song (playlist_name, position) {
         playlist_name -> Text,
         song_id -> Text,
+        position -> Integer,
+    }
+}
+
+diesel::table! {
+    queue_snapshot (position) {
+        position -> Integer,
+        file -> Text,
+        start_offset -> Nullable<Double>,
+        end_offset -> Nullable<Double>,
+    }
+}
+
+diesel::table! {
+    schedule (id) {
+        id -> Integer,
+        kind -> Text,
+        run_at -> BigInt,
+        playlist_name -> Nullable<Text>,
+        fade -> Integer,
+        daily -> Bool,
+    }
+}
+
+diesel::table! {
+    setting (key) {
+        key -> Text,
+        value -> Text,
     }
 }
 
@@ -18,10 +46,31 @@ diesel::table! {
         id -> Text,
         name -> Text,
         artist -> Nullable<Text>,
+        source -> Text,
+        loudness -> Nullable<Double>,
+        true_peak -> Nullable<Double>,
+        gain -> Nullable<Double>,
+        duration -> Nullable<Double>,
+        bitrate -> Nullable<Integer>,
+        sample_rate -> Nullable<Integer>,
+        channels -> Nullable<Integer>,
+        codec -> Nullable<Text>,
+        file_size -> Nullable<BigInt>,
+        start_offset -> Nullable<Double>,
+        end_offset -> Nullable<Double>,
+        parent_id -> Nullable<Text>,
     }
 }
 
 diesel::joinable!(playlist_song -> playlist (playlist_name));
 diesel::joinable!(playlist_song -> song (song_id));
+diesel::joinable!(schedule -> playlist (playlist_name));
 
-diesel::allow_tables_to_appear_in_same_query!(playlist, playlist_song, song,);
+diesel::allow_tables_to_appear_in_same_query!(
+    playlist,
+    playlist_song,
+    queue_snapshot,
+    schedule,
+    setting,
+    song,
+);
@@ -0,0 +1,103 @@
+use diesel::result::{DatabaseErrorKind, Error as DieselError};
+use thiserror::Error;
+
+pub type Result<T> = std::result::Result<T, YapError>;
+
+/**
+ * Everything that can go wrong inside yap, each kind exits the cli with its own code
+ */
+#[derive(Debug, Error)]
+pub enum YapError {
+    #[error("{0}")]
+    NotFound(String),
+    #[error("{0}")]
+    AlreadyExists(String),
+    #[error("Database error: {0}")]
+    Db(#[from] DieselError),
+    #[error("Mpd error: {0}")]
+    Mpd(#[from] mpd::error::Error),
+    #[error("Download error: {0}")]
+    Download(String),
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Configuration error: {0}")]
+    Config(String),
+    //The player refused the command, like seeking while stopped
+    #[error("{0}")]
+    Player(String),
+    //ffmpeg couldn't read or write an audio file
+    #[error("Audio error: {0}")]
+    Audio(String),
+    //A value given by the user can't be parsed or is out of range
+    #[error("{0}")]
+    Invalid(String),
+}
+
+impl YapError {
+    /**
+     * Turns a unique constraint violation into AlreadyExists with the given message
+     */
+    pub fn on_conflict(error: DieselError, message: impl Into<String>) -> Self {
+        match error {
+            DieselError::DatabaseError(DatabaseErrorKind::UniqueViolation, _) => {
+                YapError::AlreadyExists(message.into())
+            }
+            error => YapError::Db(error),
+        }
+    }
+
+    /**
+     * Wraps errors from outside diesel, like the connection pool or migrations
+     */
+    pub fn db(error: impl ToString) -> Self {
+        YapError::Db(DieselError::DatabaseError(
+            DatabaseErrorKind::Unknown,
+            Box::new(error.to_string()),
+        ))
+    }
+
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            YapError::NotFound(_) => 2,
+            YapError::AlreadyExists(_) => 3,
+            YapError::Db(_) => 4,
+            YapError::Mpd(_) => 5,
+            YapError::Download(_) => 6,
+            YapError::Io(_) => 7,
+            YapError::Config(_) => 8,
+            YapError::Player(_) => 9,
+            YapError::Audio(_) => 10,
+            YapError::Invalid(_) => 11,
+        }
+    }
+}
+
+impl From<youtube_dl::Error> for YapError {
+    fn from(error: youtube_dl::Error) -> Self {
+        YapError::Download(error.to_string())
+    }
+}
+
+impl From<reqwest::Error> for YapError {
+    fn from(error: reqwest::Error) -> Self {
+        YapError::Download(error.to_string())
+    }
+}
+
+impl From<hound::Error> for YapError {
+    fn from(error: hound::Error) -> Self {
+        match error {
+            hound::Error::IoError(error) => YapError::Io(error),
+            error => YapError::Io(std::io::Error::other(error)),
+        }
+    }
+}
+
+impl From<confy::ConfyError> for YapError {
+    fn from(error: confy::ConfyError) -> Self {
+        match std::error::Error::source(&error) {
+            Some(source) => YapError::Config(format!("{}: {}", error, source)),
+            None => YapError::Config(error.to_string()),
+        }
+    }
+}
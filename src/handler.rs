@@ -1,375 +1,1554 @@
-use anyhow::anyhow;
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    thread,
+    time::Duration,
+};
+
+use chrono::{DateTime, Local, NaiveTime, TimeZone};
+use mpd::status::ReplayGain;
 
 use crate::{
+    audio::{
+        chapters::{chapter_songs, extract, ChapterSong},
+        loudness::{analyze, write_replay_gain, Loudness},
+        properties::probe,
+        silence::{detect_silence, trim_file},
+    },
+    config::TrimSilence,
     db::{
         database::Database,
-        models::{NewPlaylist, NewPlaylistSong, NewSong, PlaylistSong},
+        models::{
+            NewPlaylist, NewPlaylistSong, NewSchedule, NewSong, Playlist, QueueSnapshotSong,
+            Schedule, Song, SongLoudness, SongProperties,
+        },
+    },
+    error::{Result, YapError},
+    library::manifest::{LibraryManifest, ManifestPlaylist, ManifestSong, MANIFEST_VERSION},
+    notifications::notifier::Notifier,
+    player::{
+        backend::{
+            song_id_from_file, PlayRange, Player, QueueEntry, QueueSnapshot, SeekTarget, Status,
+        },
+        events::{PlayerEvent, WatchEvent},
+    },
+    playlists::{
+        format::{write_playlist, PathStyle, PlaylistEntry, PlaylistFormat},
+        matching::best_match,
+        parse::{format_of, parse_playlist, ImportedEntry},
     },
-    mpd::mpd_client::MpdClient,
-    youtube_api::youtube_api::YoutubeAPI,
+    sources::source::{audio_file, SongMetadata, Source, Sources},
+    sync::{
+        sync::{sync_playlists, SyncReport},
+        transcode::Encoding,
+    },
+    utils::utils::{format_duration, probe_duration},
+    youtube_api::youtube_api::video_id,
 };
 
+/**
+ * A schedule that became due, with the result of running it
+ */
+pub struct ScheduleRun {
+    pub schedule: Schedule,
+    pub result: Result<()>,
+}
+
+/**
+ * A downloaded song, with what couldn't be done to it after the download
+ */
+#[derive(Debug)]
+pub struct AddedSong {
+    pub song: Song,
+    //Silence detection or analysis that failed, the song still plays without them
+    pub warnings: Vec<YapError>,
+}
+
+/**
+ * What happened to the entries of an imported playlist
+ */
+pub struct ImportReport {
+    pub playlist_name: String,
+    //Songs of the new playlist, in order
+    pub songs: Vec<Song>,
+    pub downloaded: Vec<AddedSong>,
+    pub unmatched: Vec<ImportedEntry>,
+    pub failed: Vec<(ImportedEntry, YapError)>,
+}
+
+/**
+ * The songs cut from the chapters of a video
+ */
+#[derive(Debug)]
+pub struct ChapterDownload {
+    //Title of the video
+    pub title: String,
+    //Every chapter in order, including the ones already in the library
+    pub songs: Vec<Song>,
+    //Ids of the chapters that were already in the library
+    pub existing: Vec<String>,
+    //Playlist the chapters were added to
+    pub playlist: Option<String>,
+    //Silence detection or analysis of the chapters that failed
+    pub warnings: Vec<YapError>,
+}
+
+/**
+ * A playlist with its song count and total duration
+ */
+pub struct PlaylistSummary {
+    pub name: String,
+    pub songs: usize,
+    pub duration: Duration,
+    //Songs whose duration isn't known yet, left out of duration
+    pub untimed: usize,
+}
+
+/**
+ * What happened to the songs and playlists of an imported library
+ */
+pub struct LibraryImportReport {
+    pub downloaded: Vec<AddedSong>,
+    pub existing: usize,
+    //Songs left out because downloads were disabled or their source can't download them again
+    pub skipped: Vec<ManifestSong>,
+    pub failed: Vec<(ManifestSong, YapError)>,
+    pub playlists_added: Vec<String>,
+    pub playlists_merged: Vec<String>,
+}
+
+/**
+ * Entry point of the library, ties the database, the sources and the player together
+ */
 pub struct Handler {
     database: Database,
-    api: YoutubeAPI,
-    mpd: MpdClient,
+    sources: Sources,
+    player: Box<dyn Player>,
+    notifier: Box<dyn Notifier>,
+    music_directory: PathBuf,
+    trim_silence: TrimSilence,
 }
 
 impl Handler {
-    pub fn new(database: Database, api: YoutubeAPI, mpd: MpdClient) -> Self {
-        database.run_embedded_migrations();
-        Self { database, api, mpd }
+    pub fn new(
+        database: Database,
+        sources: Sources,
+        player: Box<dyn Player>,
+        notifier: Box<dyn Notifier>,
+        music_directory: PathBuf,
+        trim_silence: TrimSilence,
+    ) -> Result<Self> {
+        database.run_embedded_migrations()?;
+        Ok(Self {
+            database,
+            sources,
+            player,
+            notifier,
+            music_directory,
+            trim_silence,
+        })
+    }
+
+    fn song_by_name(&self, song_name: &str) -> Result<Song> {
+        match self.database.get_song_by_name(song_name)? {
+            Some(song) => Ok(song),
+            None => Err(YapError::NotFound(format!(
+                "Song {} doesn't exist",
+                song_name
+            ))),
+        }
+    }
+
+    fn check_playlist_exists(&self, playlist_name: &str) -> Result<()> {
+        match self.database.get_playlist_by_name(playlist_name)? {
+            Some(_) => Ok(()),
+            None => Err(YapError::NotFound(format!(
+                "Playlist {} doesn't exist",
+                playlist_name
+            ))),
+        }
     }
 
     pub async fn add_song_to_registry(
         &self,
-        song_id: &str,
-        song_name: &str,
+        location: &str,
+        song_name: Option<String>,
         song_artist: Option<String>,
-    ) -> anyhow::Result<()> {
-        let inserted = self.database.insert_song(NewSong {
-            id: song_id,
-            name: song_name,
-            artist: song_artist,
-        });
+    ) -> Result<AddedSong> {
+        let source = self.sources.for_location(location)?;
+        let mut metadata = source.resolve(location).await?;
+        let song_name = match song_name.or(metadata.title.clone()) {
+            Some(song_name) => song_name,
+            None => {
+                return Err(YapError::NotFound(format!(
+                    "Couldn't find a name for {}",
+                    location
+                )))
+            }
+        };
+        metadata.title = Some(song_name);
+        metadata.artist = song_artist.or(metadata.artist);
+        self.register_song(source, location, &metadata).await
+    }
 
-        if !inserted {
-            return Err(anyhow!("Couldn't add song to database, skipping..."));
-        }
+    /**
+     * Adds a song to the database and downloads it, the song is removed again if the download fails
+     */
+    async fn register_song(
+        &self,
+        source: &dyn Source,
+        location: &str,
+        metadata: &SongMetadata,
+    ) -> Result<AddedSong> {
+        let name = metadata.title.as_deref().unwrap_or(&metadata.id);
+        self.database.insert_song(NewSong {
+            id: &metadata.id,
+            name,
+            artist: metadata.artist.clone(),
+            source: source.name(),
+            parent_id: None,
+        })?;
 
-        self.api.download_audio(song_id, song_name).await?;
-        println!("Song {} downloaded successfully", song_name);
-        self.mpd.update_db()?;
-        Ok(())
+        if let Err(error) = source.download(location, metadata).await {
+            self.database.delete_song(&metadata.id)?;
+            return Err(error);
+        }
+        let warnings = self.prepare_song(&metadata.id, name);
+        self.player.update_db()?;
+        match self.database.get_song_by_id(&metadata.id)? {
+            Some(song) => Ok(AddedSong { song, warnings }),
+            None => Err(YapError::NotFound(format!(
+                "Song {} doesn't exist",
+                metadata.id
+            ))),
+        }
     }
 
-    pub fn delete_song(&self, song_name: &str) -> anyhow::Result<()> {
-        let song_id = match self.database.get_song_by_name(song_name) {
-            Some(song) => song.id,
-            None => {
-                return Err(anyhow!(format!("Song {} doesn't exist", song_name)));
+    /**
+     * Downloads a video with chapters and cuts it into a song per chapter, the video
+     * itself isn't kept unless it already was in the library
+     */
+    pub async fn download_chapters(
+        &self,
+        location: &str,
+        song_artist: Option<String>,
+        create_playlist: bool,
+    ) -> Result<ChapterDownload> {
+        let source = self.sources.for_location(location)?;
+        let mut metadata = source.resolve(location).await?;
+        if metadata.chapters.is_empty() {
+            return Err(YapError::NotFound(format!("{} has no chapters", location)));
+        }
+        metadata.artist = song_artist.or(metadata.artist);
+        let title = metadata.title.clone().unwrap_or(metadata.id.clone());
+        let chapters = chapter_songs(&metadata.id, &title, &metadata.chapters);
+        let mut existing = Vec::new();
+        let mut missing = Vec::new();
+        for chapter in chapters.iter() {
+            match self.database.get_song_by_id(&chapter.id)? {
+                Some(song) => existing.push(song.id),
+                None => missing.push(chapter.clone()),
             }
+        }
+
+        //The video is only needed when some chapters aren't in the library yet
+        let mix = audio_file(&self.music_directory, &metadata.id);
+        let keep_mix = self.database.get_song_by_id(&metadata.id)?.is_some() && mix.is_file();
+        if !missing.is_empty() && !keep_mix {
+            source.download(location, &metadata).await?;
+        }
+        let added = self.split_chapters(source, &metadata, &missing);
+        if !keep_mix {
+            let _ = fs::remove_file(&mix);
+        }
+        let added = added?;
+        self.player.update_db()?;
+
+        let mut songs = Vec::new();
+        for chapter in chapters.iter() {
+            if let Some(song) = self.database.get_song_by_id(&chapter.id)? {
+                songs.push(song);
+            }
+        }
+        let playlist = match create_playlist {
+            true => {
+                if self.database.get_playlist_by_name(&title)?.is_none() {
+                    self.database
+                        .insert_playlist(NewPlaylist { name: &title })?;
+                }
+                for song in songs.iter() {
+                    match self.database.add_songs_to_playlist(NewPlaylistSong {
+                        playlist_name: &title,
+                        song_id: &song.id,
+                    }) {
+                        Ok(()) | Err(YapError::AlreadyExists(_)) => {}
+                        Err(error) => return Err(error),
+                    }
+                }
+                Some(title.clone())
+            }
+            false => None,
         };
-        self.api.delete_audio(&song_id)?;
-        let deleted = self.database.delete_song(&song_id);
-        if !deleted {
-            return Err(anyhow!(format!(
-                "Couldn't delete song {} from database",
-                &song_name
+        Ok(ChapterDownload {
+            title,
+            songs,
+            existing,
+            playlist,
+            warnings: added.into_iter().flat_map(|added| added.warnings).collect(),
+        })
+    }
+
+    /**
+     * Cuts the given chapters out of a downloaded video and adds them to the library,
+     * when one fails the ones added before it are removed again
+     */
+    fn split_chapters(
+        &self,
+        source: &dyn Source,
+        metadata: &SongMetadata,
+        chapters: &[ChapterSong],
+    ) -> Result<Vec<AddedSong>> {
+        let mut added: Vec<AddedSong> = Vec::new();
+        for chapter in chapters {
+            match self.add_chapter(source, metadata, chapter) {
+                Ok(song) => added.push(song),
+                Err(error) => {
+                    for song in added.iter() {
+                        let _ = self.database.delete_song(&song.song.id);
+                        let _ = fs::remove_file(audio_file(&self.music_directory, &song.song.id));
+                    }
+                    return Err(error);
+                }
+            }
+        }
+        Ok(added)
+    }
+
+    fn add_chapter(
+        &self,
+        source: &dyn Source,
+        metadata: &SongMetadata,
+        chapter: &ChapterSong,
+    ) -> Result<AddedSong> {
+        let file = audio_file(&self.music_directory, &chapter.id);
+        extract(
+            &audio_file(&self.music_directory, &metadata.id),
+            &file,
+            chapter.start,
+            chapter.end,
+            &chapter.name,
+        )?;
+        if let Err(error) = self.database.insert_song(NewSong {
+            id: &chapter.id,
+            name: &chapter.name,
+            artist: metadata.artist.clone(),
+            source: source.name(),
+            parent_id: Some(&metadata.id),
+        }) {
+            let _ = fs::remove_file(&file);
+            return Err(error);
+        }
+        let warnings = self.prepare_song(&chapter.id, &chapter.name);
+        match self.database.get_song_by_id(&chapter.id)? {
+            Some(song) => Ok(AddedSong { song, warnings }),
+            None => Err(YapError::NotFound(format!(
+                "Song {} doesn't exist",
+                chapter.id
+            ))),
+        }
+    }
+
+    /**
+     * Trims and analyzes a new song, returns what failed. The song is still
+     * playable then, and song trim and library analyze can try again
+     */
+    fn prepare_song(&self, song_id: &str, song_name: &str) -> Vec<YapError> {
+        let mut warnings = Vec::new();
+        if self.trim_silence != TrimSilence::Off {
+            if let Err(error) = self.detect_silence(song_id, self.trim_silence) {
+                warnings.push(YapError::Audio(format!(
+                    "Couldn't skip the silence of {}, song trim --detect can try again: {}",
+                    song_name, error
+                )));
+            }
+        }
+        if let Err(error) = self.analyze_song(song_id) {
+            warnings.push(YapError::Audio(format!(
+                "Couldn't analyze {}, library analyze can try again: {}",
+                song_name, error
             )));
         }
-        println!("Song {} deleted successfully", song_name);
-        self.mpd.update_db()?;
-        Ok(())
+        warnings
     }
 
+    /**
+     * Finds the silence at the start and the end of a song and skips it, either with
+     * the song's offsets or by cutting it from the file
+     */
+    fn detect_silence(&self, song_id: &str, mode: TrimSilence) -> Result<PlayRange> {
+        let file = audio_file(&self.music_directory, song_id);
+        let range = detect_silence(&file, probe_duration(&file))?;
+        match mode {
+            TrimSilence::Off => {}
+            TrimSilence::Range => {
+                self.database
+                    .set_song_offsets(song_id, range.start, range.end)?
+            }
+            TrimSilence::Rewrite if range.is_full() => {}
+            TrimSilence::Rewrite => {
+                trim_file(&file, range)?;
+                self.database.set_song_offsets(song_id, None, None)?;
+            }
+        }
+        Ok(range)
+    }
+
+    /**
+     * Sets the part of a song that gets played, offsets that aren't given are kept
+     */
+    pub fn trim_song(
+        &self,
+        song_name: &str,
+        start: Option<Duration>,
+        end: Option<Duration>,
+    ) -> Result<Song> {
+        let song = self.song_by_name(song_name)?;
+        let start = start.map(|start| start.as_secs_f64()).or(song.start_offset);
+        let end = end.map(|end| end.as_secs_f64()).or(song.end_offset);
+        if let (Some(start), Some(end)) = (start, end) {
+            if start >= end {
+                return Err(YapError::Invalid(format!(
+                    "Start {} isn't before the end {}",
+                    format_duration(Duration::from_secs_f64(start)),
+                    format_duration(Duration::from_secs_f64(end))
+                )));
+            }
+        }
+        //The end comes after the start, so it's the one to check when there's both
+        if let (Some(offset), Some(duration)) = (end.or(start), song.duration) {
+            if offset > duration {
+                return Err(YapError::Invalid(format!(
+                    "Offset {} is after the end of the song ({})",
+                    format_duration(Duration::from_secs_f64(offset)),
+                    format_duration(Duration::from_secs_f64(duration))
+                )));
+            }
+        }
+        self.database.set_song_offsets(&song.id, start, end)?;
+        self.song_by_name(song_name)
+    }
+
+    /**
+     * Plays a song whole again
+     */
+    pub fn reset_trim(&self, song_name: &str) -> Result<Song> {
+        let song = self.song_by_name(song_name)?;
+        self.database.set_song_offsets(&song.id, None, None)?;
+        self.song_by_name(song_name)
+    }
+
+    /**
+     * Skips the silence at the start and the end of a song with its offsets
+     */
+    pub fn trim_silence(&self, song_name: &str) -> Result<Song> {
+        let song = self.song_by_name(song_name)?;
+        self.detect_silence(&song.id, TrimSilence::Range)?;
+        self.song_by_name(song_name)
+    }
+
+    /**
+     * Measures the loudness of a song, stores it with the song's audio properties and
+     * writes it to the file as ReplayGain tags
+     */
+    fn analyze_song(&self, song_id: &str) -> Result<Loudness> {
+        let file = audio_file(&self.music_directory, song_id);
+        let loudness = analyze(&file).and_then(|loudness| {
+            write_replay_gain(&file, &loudness)?;
+            Ok(loudness)
+        });
+        //Probed after tagging, which changes the file size
+        let properties = probe(&file)?;
+        self.database.set_song_properties(
+            song_id,
+            SongProperties {
+                duration: properties.duration,
+                bitrate: properties.bitrate,
+                sample_rate: properties.sample_rate,
+                channels: properties.channels,
+                codec: properties.codec,
+                file_size: Some(properties.file_size),
+            },
+        )?;
+        let loudness = loudness?;
+        self.database.set_song_loudness(
+            song_id,
+            SongLoudness {
+                loudness: loudness.integrated,
+                true_peak: loudness.true_peak,
+                gain: loudness.gain,
+            },
+        )?;
+        Ok(loudness)
+    }
+
+    /**
+     * Analyzes the songs without measurements or duration, or every song when all is set
+     */
+    pub fn analyze_library(&self, all: bool) -> Result<Vec<(Song, Result<Loudness>)>> {
+        let mut results = Vec::new();
+        for song in self.database.get_songs()? {
+            if all || song.loudness.is_none() || song.duration.is_none() {
+                let loudness = self.analyze_song(&song.id);
+                results.push((song, loudness));
+            }
+        }
+        if results.iter().any(|(_, loudness)| loudness.is_ok()) {
+            self.player.update_db()?;
+        }
+        Ok(results)
+    }
+
+    pub fn delete_song(&self, song_name: &str) -> Result<Song> {
+        let song = self.song_by_name(song_name)?;
+        self.sources.by_name(&song.source)?.delete(&song.id)?;
+        self.database.delete_song(&song.id)?;
+        self.player.update_db()?;
+        Ok(song)
+    }
+
+    /**
+     * Creates a playlist with the given songs, returns the songs in the order they were added
+     */
     pub fn create_playlist(
         &self,
         playlist_name: &str,
         songs_to_add: Vec<String>,
-    ) -> anyhow::Result<()> {
-        let inserted = self.database.insert_playlist(NewPlaylist {
+    ) -> Result<Vec<Song>> {
+        let mut songs = Vec::new();
+        for song in songs_to_add {
+            songs.push(self.song_by_name(song.trim())?);
+        }
+
+        self.database.insert_playlist(NewPlaylist {
             name: playlist_name,
-        });
+        })?;
 
-        if !inserted {
-            return Err(anyhow!("Couldn't create playlist, skipping..."));
+        for song in songs.iter() {
+            self.database.add_songs_to_playlist(NewPlaylistSong {
+                playlist_name,
+                song_id: &song.id,
+            })?;
         }
 
-        for song in songs_to_add {
-            if let Some(song_in_db) = self.database.get_song_by_name(song.trim()) {
-                self.database.add_songs_to_playlist(NewPlaylistSong {
-                    playlist_name,
-                    song_id: &song_in_db.id,
-                });
+        Ok(songs)
+    }
 
-                println!("Song {} added to playlist", &song);
-            } else {
-                return Err(anyhow!(format!("Song {} doesn't exist", &song)));
-            }
+    pub fn delete_playlist(&self, playlist_name: &str) -> Result<()> {
+        if !self.database.delete_playlist(playlist_name)? {
+            return Err(YapError::NotFound(format!(
+                "Playlist {} doesn't exist",
+                playlist_name
+            )));
         }
-
         Ok(())
     }
 
-    pub fn delete_playlist(&self, playlist_name: &str) -> anyhow::Result<()> {
-        let playlists = match self.database.get_playlists() {
-            Some(playlist) => playlist,
-            None => {
-                return Err(anyhow!("No playlists in database"));
+    pub fn play_playlist(&self, playlist_name: &str) -> Result<()> {
+        self.check_playlist_exists(playlist_name)?;
+        self.snapshot_queue()?;
+        self.player.pause(Some(true))?;
+        self.player.clear_queue()?;
+        let songs = self.database.get_songs_of_playlist(playlist_name)?;
+        for playlist_song in songs {
+            if let Some(song) = self.database.get_song_by_id(&playlist_song.song_id)? {
+                self.player.add_to_queue(&song.id, PlayRange::of(&song))?;
             }
+        }
+        self.player.play()?;
+        Ok(())
+    }
+
+    /**
+     * Songs of a playlist with the paths other players need to find them
+     */
+    fn playlist_entries(
+        &self,
+        playlist_name: &str,
+        style: PathStyle,
+    ) -> Result<Vec<PlaylistEntry>> {
+        self.check_playlist_exists(playlist_name)?;
+        let mut entries = Vec::new();
+        for playlist_song in self.database.get_songs_of_playlist(playlist_name)? {
+            let song = match self.database.get_song_by_id(&playlist_song.song_id)? {
+                Some(song) => song,
+                None => continue,
+            };
+            let file = audio_file(&self.music_directory, &song.id);
+            let path = match style {
+                PathStyle::Absolute => file.clone(),
+                PathStyle::Relative => PathBuf::from(format!("{}.opus", song.id)),
+            };
+            entries.push(PlaylistEntry {
+                path: path.to_string_lossy().to_string(),
+                duration: song.duration.or_else(|| probe_duration(&file)),
+                id: song.id,
+                name: song.name,
+                artist: song.artist,
+            });
+        }
+        Ok(entries)
+    }
+
+    pub fn export_playlist(
+        &self,
+        playlist_name: &str,
+        format: PlaylistFormat,
+        style: PathStyle,
+    ) -> Result<String> {
+        let entries = self.playlist_entries(playlist_name, style)?;
+        write_playlist(format, playlist_name, &entries)
+    }
+
+    /**
+     * Writes every playlist to a directory, the player's playlist directory when none is given,
+     * returns the files written
+     */
+    pub fn export_playlists(
+        &self,
+        directory: Option<&Path>,
+        format: PlaylistFormat,
+        style: PathStyle,
+    ) -> Result<Vec<PathBuf>> {
+        let directory = match directory {
+            Some(directory) => directory.to_path_buf(),
+            None => self.player.playlist_directory()?,
         };
+        fs::create_dir_all(&directory).map_err(YapError::from)?;
+        let mut files = Vec::new();
+        for playlist in self.database.get_playlists()? {
+            let file = directory.join(format!(
+                "{}.{}",
+                playlist.name.replace('/', "_"),
+                format.extension()
+            ));
+            let content = self.export_playlist(&playlist.name, format, style)?;
+            fs::write(&file, content).map_err(YapError::from)?;
+            files.push(file);
+        }
+        Ok(files)
+    }
 
-        if !playlists
-            .iter()
-            .map(|p| p.name.clone())
-            .collect::<Vec<String>>()
-            .contains(&playlist_name.to_string())
-        {
-            return Err(anyhow!(format!("Playlist {} doesn't exist", playlist_name)));
+    /**
+     * Mirrors the songs of the given playlists into a directory, with an m3u per playlist
+     */
+    pub fn sync_playlists(
+        &self,
+        playlist_names: &[String],
+        target: &Path,
+        encoding: &Encoding,
+    ) -> Result<SyncReport> {
+        let mut playlists = Vec::new();
+        for name in playlist_names {
+            playlists.push((
+                name.clone(),
+                self.playlist_entries(name, PathStyle::Absolute)?,
+            ));
         }
+        sync_playlists(&playlists, target, encoding)
+    }
 
-        let deleted = self.database.delete_playlist(playlist_name);
+    /**
+     * Finds the song an imported entry refers to, by youtube id, file name or title
+     */
+    fn match_entry(&self, entry: &ImportedEntry, songs: &[Song]) -> Result<Option<Song>> {
+        let file_stem = Path::new(&entry.location)
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string());
+        for id in [video_id(&entry.location), file_stem].into_iter().flatten() {
+            if let Some(song) = self.database.get_song_by_id(&id)? {
+                return Ok(Some(song));
+            }
+        }
+        Ok(best_match(entry, songs).cloned())
+    }
 
-        if !deleted {
-            return Err(anyhow!(format!(
-                "Couldn't delete playlist {}",
+    /**
+     * Creates a playlist from a m3u, xspf, pls or json file, named after the file
+     * unless a name is given, entries with a youtube url can be downloaded when missing
+     */
+    pub async fn import_playlist(
+        &self,
+        file: &Path,
+        format: Option<PlaylistFormat>,
+        playlist_name: Option<String>,
+        download: bool,
+    ) -> Result<ImportReport> {
+        let format = match format {
+            Some(format) => format,
+            None => format_of(file)?,
+        };
+        let content = fs::read_to_string(file).map_err(YapError::from)?;
+        let imported = parse_playlist(format, &content)?;
+        let playlist_name = match playlist_name.or(imported.name) {
+            Some(name) => name,
+            None => file
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().to_string())
+                .unwrap_or_default(),
+        };
+        if self
+            .database
+            .get_playlist_by_name(&playlist_name)?
+            .is_some()
+        {
+            return Err(YapError::AlreadyExists(format!(
+                "Playlist {} already exists",
                 playlist_name
             )));
+        }
+
+        let library = self.database.get_songs()?;
+        let mut report = ImportReport {
+            playlist_name,
+            songs: Vec::new(),
+            downloaded: Vec::new(),
+            unmatched: Vec::new(),
+            failed: Vec::new(),
         };
+        for entry in imported.entries {
+            if let Some(song) = self.match_entry(&entry, &library)? {
+                report.songs.push(song);
+            } else if download && video_id(&entry.location).is_some() {
+                match self
+                    .add_song_to_registry(
+                        &entry.location,
+                        entry.title.clone(),
+                        entry.artist.clone(),
+                    )
+                    .await
+                {
+                    Ok(added) => {
+                        report.songs.push(added.song.clone());
+                        report.downloaded.push(added);
+                    }
+                    Err(error) => report.failed.push((entry, error)),
+                }
+            } else {
+                report.unmatched.push(entry);
+            }
+        }
 
-        println!("Playlist {} deleted successfully", playlist_name);
-        Ok(())
+        self.database.insert_playlist(NewPlaylist {
+            name: &report.playlist_name,
+        })?;
+        for song in report.songs.iter() {
+            match self.database.add_songs_to_playlist(NewPlaylistSong {
+                playlist_name: &report.playlist_name,
+                song_id: &song.id,
+            }) {
+                //A song listed twice is only added once
+                Ok(()) | Err(YapError::AlreadyExists(_)) => {}
+                Err(error) => return Err(error),
+            }
+        }
+        Ok(report)
     }
 
-    pub fn play_playlist(&self, playlist_name: &str) -> anyhow::Result<()> {
-        self.mpd.pause(Some(true))?;
-        self.mpd.clear_queue()?;
-        if self.database.get_playlist_by_name(playlist_name).is_none() {
-            return Err(anyhow!(format!("Playlist {} doesn't exist", playlist_name)));
+    /**
+     * Describes the songs and playlists of the library, without their audio
+     */
+    pub fn export_library(&self) -> Result<LibraryManifest> {
+        let songs = self
+            .database
+            .get_songs()?
+            .into_iter()
+            .map(|song| ManifestSong {
+                id: song.id,
+                name: song.name,
+                artist: song.artist,
+                source: song.source,
+                loudness: song.loudness,
+                true_peak: song.true_peak,
+                gain: song.gain,
+                start_offset: song.start_offset,
+                end_offset: song.end_offset,
+                parent_id: song.parent_id,
+            })
+            .collect();
+        let mut playlists = Vec::new();
+        for playlist in self.database.get_playlists()? {
+            playlists.push(ManifestPlaylist {
+                songs: self
+                    .database
+                    .get_songs_of_playlist(&playlist.name)?
+                    .into_iter()
+                    .map(|song| song.song_id)
+                    .collect(),
+                name: playlist.name,
+            });
         }
-        let songs = match self.database.get_songs_of_playlist(playlist_name) {
-            Some(songs) => songs,
-            None => {
-                return Err(anyhow!("Playlist doesn't contain any songs"));
-            }
-        };
-        for song in songs {
-            self.mpd.add_to_queue(&song.song_id)?;
+        Ok(LibraryManifest {
+            version: MANIFEST_VERSION,
+            exported_at: Local::now().to_rfc3339(),
+            songs,
+            playlists,
+        })
+    }
+
+    /**
+     * Applies the trim and tags of an exported song to its download, the measurements
+     * of the new file win over the exported ones
+     */
+    fn restore_manifest_song(&self, song: &ManifestSong, downloaded: Song) -> Result<Song> {
+        let trimmed = song.start_offset.is_some() || song.end_offset.is_some();
+        if !trimmed && (downloaded.loudness.is_some() || song.loudness.is_none()) {
+            return Ok(downloaded);
+        }
+        if trimmed {
+            self.database
+                .set_song_offsets(&song.id, song.start_offset, song.end_offset)?;
+        }
+        if let (None, Some(loudness), Some(true_peak), Some(gain)) = (
+            downloaded.loudness,
+            song.loudness,
+            song.true_peak,
+            song.gain,
+        ) {
+            self.database.set_song_loudness(
+                &song.id,
+                SongLoudness {
+                    loudness,
+                    true_peak,
+                    gain,
+                },
+            )?;
+        }
+        match self.database.get_song_by_id(&song.id)? {
+            Some(song) => Ok(song),
+            None => Err(YapError::NotFound(format!(
+                "Song {} doesn't exist",
+                song.id
+            ))),
         }
-        self.mpd.play()?;
-        Ok(())
     }
 
-    pub fn get_playlists(&self) -> anyhow::Result<()> {
-        match self.database.get_playlists() {
-            Some(playlists) => {
-                for playlist in playlists {
-                    println!("{}", playlist.name)
+    /**
+     * Adds the songs and playlists of an exported library, downloading the songs
+     * that are missing again from their source
+     */
+    pub async fn import_library(
+        &self,
+        manifest: LibraryManifest,
+        download: bool,
+    ) -> Result<LibraryImportReport> {
+        let mut report = LibraryImportReport {
+            downloaded: Vec::new(),
+            existing: 0,
+            skipped: Vec::new(),
+            failed: Vec::new(),
+            playlists_added: Vec::new(),
+            playlists_merged: Vec::new(),
+        };
+        for song in manifest.songs {
+            if self.database.get_song_by_id(&song.id)?.is_some() {
+                report.existing += 1;
+                continue;
+            }
+            let source = self.sources.by_name(&song.source).ok();
+            let location = source.and_then(|source| source.redownload_location(&song.id));
+            let (source, location) = match (source, location) {
+                (Some(source), Some(location)) if download => (source, location),
+                _ => {
+                    report.skipped.push(song);
+                    continue;
                 }
+            };
+            let metadata = SongMetadata {
+                id: song.id.clone(),
+                title: Some(song.name.clone()),
+                artist: song.artist.clone(),
+                chapters: Vec::new(),
+            };
+            let downloaded = match self.register_song(source, &location, &metadata).await {
+                Ok(added) => self
+                    .restore_manifest_song(&song, added.song)
+                    .map(|restored| AddedSong {
+                        song: restored,
+                        warnings: added.warnings,
+                    }),
+                Err(error) => Err(error),
+            };
+            match downloaded {
+                Ok(downloaded) => report.downloaded.push(downloaded),
+                Err(error) => report.failed.push((song, error)),
             }
-            None => {}
-        };
-        Ok(())
-    }
+        }
 
-    pub fn get_songs(&self) -> anyhow::Result<()> {
-        match self.database.get_songs() {
-            Some(songs) => {
-                for song in songs {
-                    println!("{} - {}", song.name, {
-                        if let Some(artist) = song.artist {
-                            artist
-                        } else {
-                            "Unknown".to_string()
-                        }
-                    })
+        for playlist in manifest.playlists {
+            let exists = self
+                .database
+                .get_playlist_by_name(&playlist.name)?
+                .is_some();
+            if !exists {
+                self.database.insert_playlist(NewPlaylist {
+                    name: &playlist.name,
+                })?;
+            }
+            let mut added = false;
+            for song_id in playlist.songs.iter() {
+                if self.database.get_song_by_id(song_id)?.is_none() {
+                    continue;
+                }
+                match self.database.add_songs_to_playlist(NewPlaylistSong {
+                    playlist_name: &playlist.name,
+                    song_id,
+                }) {
+                    Ok(()) => added = true,
+                    Err(YapError::AlreadyExists(_)) => {}
+                    Err(error) => return Err(error),
                 }
             }
-            None => {}
-        };
-        Ok(())
+            if !exists {
+                report.playlists_added.push(playlist.name);
+            } else if added {
+                report.playlists_merged.push(playlist.name);
+            }
+        }
+        Ok(report)
     }
 
-    pub fn play_song(&self, song_name: &str) -> anyhow::Result<()> {
-        let song_id = match self.database.get_song_by_name(song_name) {
-            Some(song) => song.id,
-            None => {
-                return Err(anyhow!(format!("Song {} doesn't exist", song_name)));
-            }
-        };
-        self.mpd.pause(Some(true))?;
-        self.mpd.clear_queue()?;
-        self.mpd.add_to_queue(&song_id)?;
-        self.mpd.play()?;
-        Ok(())
+    pub fn get_playlists(&self) -> Result<Vec<Playlist>> {
+        self.database.get_playlists()
+    }
+
+    /**
+     * Every playlist with its song count and total duration
+     */
+    pub fn get_playlist_summaries(&self) -> Result<Vec<PlaylistSummary>> {
+        let durations = self.database.get_playlist_durations()?;
+        Ok(self
+            .database
+            .get_playlists()?
+            .into_iter()
+            .map(|playlist| {
+                match durations
+                    .iter()
+                    .find(|duration| duration.playlist_name == playlist.name)
+                {
+                    Some(duration) => PlaylistSummary {
+                        name: playlist.name,
+                        songs: duration.songs as usize,
+                        duration: Duration::from_secs_f64(duration.duration.unwrap_or(0.)),
+                        untimed: (duration.songs - duration.timed_songs) as usize,
+                    },
+                    None => PlaylistSummary {
+                        name: playlist.name,
+                        songs: 0,
+                        duration: Duration::ZERO,
+                        untimed: 0,
+                    },
+                }
+            })
+            .collect())
     }
 
-    pub fn play(&self) -> anyhow::Result<()> {
-        self.mpd.play()?;
+    pub fn get_songs(&self) -> Result<Vec<Song>> {
+        self.database.get_songs()
+    }
+
+    pub fn play_song(&self, song_name: &str) -> Result<()> {
+        let song = self.song_by_name(song_name)?;
+        self.snapshot_queue()?;
+        self.player.pause(Some(true))?;
+        self.player.clear_queue()?;
+        self.player.add_to_queue(&song.id, PlayRange::of(&song))?;
+        self.player.play()?;
         Ok(())
     }
 
-    pub fn pause(&self) -> anyhow::Result<()> {
-        self.mpd.pause(None)?;
+    pub fn play(&self) -> Result<()> {
+        self.player.play()?;
         Ok(())
     }
 
-    pub fn shuffle(&self) -> anyhow::Result<()> {
-        self.mpd.shuffle(None)?;
+    /**
+     * Toggles pause, returns whether playback is now paused
+     */
+    pub fn pause(&self) -> Result<bool> {
+        self.player.pause(None)
+    }
+
+    /**
+     * Toggles random playback, returns whether it is now enabled
+     */
+    pub fn shuffle(&self) -> Result<bool> {
+        self.player.shuffle(None)
+    }
+
+    pub fn clear_queue(&self) -> Result<()> {
+        self.snapshot_queue()?;
+        self.player.clear_queue()?;
         Ok(())
     }
 
-    pub fn clear_queue(&self) -> anyhow::Result<()> {
-        self.mpd.clear_queue()?;
-        println!("Queue cleared");
+    pub fn next(&self) -> Result<()> {
+        self.player.next()?;
+        self.player.pause(Some(true))?;
+        self.player.pause(Some(false))?;
         Ok(())
     }
 
-    pub fn next(&self) -> anyhow::Result<()> {
-        self.mpd.next()?;
-        self.mpd.pause(Some(true))?;
-        self.mpd.pause(Some(false))?;
-        println!("Skipping to next song in queue");
+    pub fn previous(&self) -> Result<()> {
+        self.player.previous()?;
+        self.player.pause(Some(true))?;
+        self.player.pause(Some(false))?;
         Ok(())
     }
 
-    pub fn previous(&self) -> anyhow::Result<()> {
-        self.mpd.previous()?;
-        self.mpd.pause(Some(true))?;
-        self.mpd.pause(Some(false))?;
-        println!("Going back to previous song in queue");
+    pub fn insert_into_playlist(&self, playlist_name: &str, song_name: &str) -> Result<Song> {
+        self.check_playlist_exists(playlist_name)?;
+        let song = self.song_by_name(song_name)?;
+        self.database.add_songs_to_playlist(NewPlaylistSong {
+            playlist_name,
+            song_id: &song.id,
+        })?;
+        Ok(song)
+    }
+
+    pub fn remove_song_from_playlist(&self, playlist_name: &str, song_name: &str) -> Result<()> {
+        let song = self.song_by_name(song_name)?;
+        self.check_playlist_exists(playlist_name)?;
+        if !self
+            .database
+            .delete_playlist_song(playlist_name, &song.id)?
+        {
+            return Err(YapError::NotFound(format!(
+                "Song {} is not in playlist {}",
+                song_name, playlist_name
+            )));
+        }
         Ok(())
     }
 
-    pub fn insert_into_playlist(&self, playlist_name: &str, song_name: &str) -> anyhow::Result<()> {
-        if self.database.get_playlist_by_name(playlist_name).is_none() {
-            return Err(anyhow!(format!("Playlist {} doesn't exist", playlist_name)));
-        };
+    /**
+     * Gets the player status and the song that is playing
+     */
+    pub fn current(&self) -> Result<(Status, Song)> {
+        match self.status()? {
+            (status, Some(song)) => Ok((status, song)),
+            _ => Err(YapError::NotFound("No song currently playing".to_string())),
+        }
+    }
 
-        let song = match self.database.get_song_by_name(song_name) {
-            Some(song) => song,
-            None => {
-                return Err(anyhow!(format!("Song {} doesn't exist", song_name)));
-            }
-        };
+    /**
+     * Toggles repeat, returns whether it is now enabled
+     */
+    pub fn repeat(&self) -> Result<bool> {
+        self.player.repeat()
+    }
 
-        let inserted = self.database.add_songs_to_playlist(NewPlaylistSong {
-            playlist_name,
-            song_id: &song.id,
-        });
+    /**
+     * Seeks in the current song, returns the new position
+     */
+    pub fn seek(&self, target: SeekTarget) -> Result<Duration> {
+        let position = self.player.seek(target)?;
+        self.player.pause(Some(true))?;
+        self.player.pause(Some(false))?;
+        Ok(position)
+    }
 
-        if !inserted {
-            return Err(anyhow!(
-                "Couldn't insert song into playlist, song is already present"
-            ));
+    /**
+     * Gets the player status and the song that is playing, if it is in the database
+     */
+    pub fn status(&self) -> Result<(Status, Option<Song>)> {
+        let mut status = self.player.status()?;
+        let song_info = match &status.file {
+            Some(file) => self.database.get_song_by_id(&song_id_from_file(file))?,
+            None => None,
         };
+        if status.duration.is_none() {
+            status.duration = song_info
+                .as_ref()
+                .and_then(|song| song.duration)
+                .map(Duration::from_secs_f64);
+        }
+        Ok((status, song_info))
+    }
 
-        println!("Song {} successfully added to {}", song_name, playlist_name);
-        Ok(())
+    /**
+     * Gets the songs in the queue, resolved against the database
+     */
+    pub fn queue(&self) -> Result<Vec<QueueEntry>> {
+        let mut queue = self.player.queue()?;
+        for entry in queue.iter_mut() {
+            entry.song = self.database.get_song_by_id(&entry.song_id())?;
+        }
+        Ok(queue)
     }
 
-    pub fn remove_song_from_playlist(
-        &self,
-        playlist_name: &str,
-        song_name: &str,
-    ) -> anyhow::Result<()> {
-        let song = match self.database.get_song_by_name(song_name) {
-            Some(song) => song,
-            None => {
-                return Err(anyhow!(format!("Song {} doesn't exist", song_name)));
-            }
-        };
-        if self.database.get_playlist_by_name(playlist_name).is_none() {
-            return Err(anyhow!(format!("Playlist {} doesn't exist", playlist_name)));
+    pub fn add_to_queue(&self, song_name: &str, next: bool) -> Result<Song> {
+        let song = self.song_by_name(song_name)?;
+        if next {
+            self.player.insert_next(&song.id, PlayRange::of(&song))?;
+        } else {
+            self.player.add_to_queue(&song.id, PlayRange::of(&song))?;
         }
-        let removed = self.database.delete_playlist_song(PlaylistSong {
-            playlist_id: playlist_name.to_string(),
-            song_id: song.id,
-        });
-        if !removed {
-            return Err(anyhow!("Couldn't remove song from playlist"));
+        Ok(song)
+    }
+
+    /**
+     * Removes a song from the queue, returns how many times it was removed
+     */
+    pub fn remove_from_queue(&self, song_name: &str, all: bool) -> Result<usize> {
+        let song = self.song_by_name(song_name)?;
+        let removed = self.player.remove_from_queue(&song.id, all)?;
+        if removed == 0 {
+            return Err(YapError::NotFound(format!(
+                "Song {} is not in the queue",
+                song_name
+            )));
         }
+        Ok(removed)
+    }
+
+    pub fn remove_position_from_queue(&self, position: u32) -> Result<()> {
+        self.player.remove_position(position)
+    }
+
+    pub fn remove_range_from_queue(&self, start: u32, end: u32) -> Result<()> {
+        self.player.remove_range(start, end)
+    }
+
+    /**
+     * Removes repeated songs from the queue, returns how many were removed
+     */
+    pub fn dedupe_queue(&self) -> Result<usize> {
+        self.player.dedupe_queue()
+    }
+
+    pub fn shuffle_queue(&self) -> Result<()> {
+        self.player.shuffle_queue()
+    }
+
+    /**
+     * Saves the queue, the current song and its elapsed time so a clear can be undone
+     */
+    fn snapshot_queue(&self) -> Result<()> {
+        let snapshot = self.player.snapshot()?;
+        if snapshot.files.is_empty() {
+            return Ok(());
+        }
+        let songs = snapshot
+            .files
+            .into_iter()
+            .enumerate()
+            .map(|(position, file)| {
+                let range = snapshot.ranges.get(position).copied().unwrap_or_default();
+                QueueSnapshotSong {
+                    position: position as i32,
+                    file,
+                    start_offset: range.start,
+                    end_offset: range.end,
+                }
+            })
+            .collect();
+        self.database.replace_queue_snapshot(songs)?;
+        self.database.set_setting(
+            "snapshot_current",
+            &snapshot
+                .current
+                .map(|current| current.to_string())
+                .unwrap_or_default(),
+        )?;
+        self.database
+            .set_setting("snapshot_elapsed", &snapshot.elapsed.as_secs().to_string())?;
+        self.database
+            .set_setting("snapshot_paused", &snapshot.paused.to_string())?;
         Ok(())
     }
 
-    pub fn current(&self) -> anyhow::Result<()> {
-        let current_song = self.mpd.current()?;
-        let current_time = self.mpd.current_time()?;
-        let current_song_name = match current_song.split(".").next() {
-            Some(name) => name,
-            None => {
-                return Err(anyhow!("Error in file name"));
-            }
+    pub fn restore_queue(&self) -> Result<()> {
+        let songs = self.database.get_queue_snapshot()?;
+        if songs.is_empty() {
+            return Err(YapError::NotFound("No queue to restore".to_string()));
+        }
+        let snapshot = QueueSnapshot {
+            ranges: songs
+                .iter()
+                .map(|song| PlayRange {
+                    start: song.start_offset,
+                    end: song.end_offset,
+                })
+                .collect(),
+            files: songs.into_iter().map(|song| song.file).collect(),
+            current: self
+                .database
+                .get_setting("snapshot_current")?
+                .and_then(|current| current.parse().ok()),
+            elapsed: Duration::from_secs(
+                self.database
+                    .get_setting("snapshot_elapsed")?
+                    .and_then(|elapsed| elapsed.parse().ok())
+                    .unwrap_or(0),
+            ),
+            paused: self.database.get_setting("snapshot_paused")? == Some("true".to_string()),
         };
-        let song_info = match self.database.get_song_by_id(&current_song_name) {
-            Some(song) => song,
-            None => {
-                return Err(anyhow!("No song currently playing"));
+        //The live queue becomes the snapshot, so restoring again undoes the restore
+        self.snapshot_queue()?;
+        self.player.restore(&snapshot)
+    }
+
+    /**
+     * Saves the queue as a new playlist in its order, songs queued twice included.
+     * Returns the entries whose file isn't in the library
+     */
+    pub fn save_queue(&self, playlist_name: &str) -> Result<Vec<QueueEntry>> {
+        if self.database.get_playlist_by_name(playlist_name)?.is_some() {
+            return Err(YapError::AlreadyExists(format!(
+                "Playlist {} already exists",
+                playlist_name
+            )));
+        }
+        let queue = self.queue()?;
+        if queue.is_empty() {
+            return Err(YapError::NotFound("Queue is empty".to_string()));
+        }
+
+        self.database.insert_playlist(NewPlaylist {
+            name: playlist_name,
+        })?;
+
+        let mut skipped = Vec::new();
+        for entry in queue {
+            match &entry.song {
+                Some(song) => self.database.append_to_playlist(NewPlaylistSong {
+                    playlist_name,
+                    song_id: &song.id,
+                })?,
+                None => skipped.push(entry),
             }
-        };
+        }
+
+        Ok(skipped)
+    }
+
+    pub fn move_in_queue(&self, from: u32, to: u32) -> Result<()> {
+        self.player.move_in_queue(from, to)
+    }
+
+    pub fn swap_in_queue(&self, first: u32, second: u32) -> Result<()> {
+        self.player.swap_in_queue(first, second)
+    }
+
+    pub fn play_position(&self, position: u32) -> Result<()> {
+        self.player.play_position(position)
+    }
 
-        println!(
-            "Current song: {}\nArtist: {}\n{}",
-            song_info.name,
-            {
-                if let Some(song_artist) = song_info.artist {
-                    song_artist
+    /**
+     * Toggles consume mode, returns whether it is now enabled
+     */
+    pub fn consume(&self) -> Result<bool> {
+        self.player.consume()
+    }
+
+    /**
+     * Toggles single mode, returns whether it is now enabled
+     */
+    pub fn single(&self) -> Result<bool> {
+        self.player.single()
+    }
+
+    /**
+     * Sets the volume to N, +N or -N, returns the new volume
+     */
+    pub fn volume(&self, volume: Option<String>) -> Result<i8> {
+        let current = self.player.volume()?;
+        let new_volume = match volume {
+            None => current,
+            Some(volume) => {
+                let parse = |value: &str| {
+                    value
+                        .parse::<i8>()
+                        .map_err(|_| YapError::Invalid(format!("Invalid volume {}", volume)))
+                };
+                if let Some(step) = volume.strip_prefix('+') {
+                    current.saturating_add(parse(step)?)
+                } else if let Some(step) = volume.strip_prefix('-') {
+                    current.saturating_sub(parse(step)?)
                 } else {
-                    "Unknown".to_string()
+                    parse(&volume)?
                 }
-            },
-            current_time
-        );
+            }
+        }
+        .clamp(0, 100);
+        if new_volume != current {
+            self.player.set_volume(new_volume)?;
+        }
+        Ok(new_volume)
+    }
 
-        Ok(())
+    /**
+     * Toggles mute, returns whether the player is now muted
+     */
+    pub fn mute(&self) -> Result<bool> {
+        let current = self.player.volume()?;
+        if current == 0 {
+            let previous = self
+                .database
+                .get_setting("volume_before_mute")?
+                .and_then(|volume| volume.parse::<i8>().ok())
+                .unwrap_or(100);
+            self.player.set_volume(previous)?;
+            Ok(false)
+        } else {
+            self.database
+                .set_setting("volume_before_mute", &current.to_string())?;
+            self.player.set_volume(0)?;
+            Ok(true)
+        }
     }
 
-    pub fn repeat(&self) -> anyhow::Result<()> {
-        self.mpd.repeat()?;
-        Ok(())
+    pub fn crossfade(&self, seconds: u64) -> Result<()> {
+        self.player.crossfade(seconds)
     }
 
-    pub fn seek(&self, percentage: u8) -> anyhow::Result<()> {
-        self.mpd.seek(percentage)?;
-        self.mpd.pause(Some(true))?;
-        self.mpd.pause(Some(false))?;
-        Ok(())
+    /**
+     * Changes the mixramp settings that are given, returns the threshold and delay now in use
+     */
+    pub fn mixramp(&self, db: Option<f32>, delay: Option<f64>) -> Result<(f32, Option<Duration>)> {
+        self.player.mixramp(db, delay)
+    }
+
+    pub fn replay_gain(&self, mode: ReplayGain) -> Result<()> {
+        self.player.replay_gain(mode)
+    }
+
+    /**
+     * Saves a sleep timer, returns when playback will stop
+     */
+    pub fn sleep(&self, delay: Duration, fade: u32) -> Result<DateTime<Local>> {
+        let delay = chrono::Duration::from_std(delay).map_err(|_| {
+            YapError::Invalid(format!("Delay {} is too long", format_duration(delay)))
+        })?;
+        let run_at = Local::now() + delay;
+        self.database.insert_schedule(NewSchedule {
+            kind: "sleep",
+            run_at: run_at.timestamp(),
+            playlist_name: None,
+            fade: fade as i32,
+            daily: false,
+        })?;
+        Ok(run_at)
+    }
+
+    /**
+     * Saves an alarm for the next time the clock shows HH:MM, returns when it will start
+     */
+    pub fn alarm(
+        &self,
+        time: &str,
+        playlist_name: &str,
+        fade: u32,
+        daily: bool,
+    ) -> Result<DateTime<Local>> {
+        let time = NaiveTime::parse_from_str(time, "%H:%M")
+            .map_err(|_| YapError::Invalid(format!("Invalid time {}, expected HH:MM", time)))?;
+        self.check_playlist_exists(playlist_name)?;
+        let run_at = next_occurrence(time, Local::now())?;
+        self.database.insert_schedule(NewSchedule {
+            kind: "alarm",
+            run_at: run_at.timestamp(),
+            playlist_name: Some(playlist_name),
+            fade: fade as i32,
+            daily,
+        })?;
+        Ok(run_at)
+    }
+
+    pub fn get_schedules(&self) -> Result<Vec<Schedule>> {
+        self.database.get_schedules()
     }
 
-    pub fn status(&self) -> anyhow::Result<()> {
-        let status = self.mpd.status()?;
-        println!(
-            "Pause: {}\tRandom: {}\tRepeat: {}",
-            status.is_paused, status.random, status.repeat
-        );
+    pub fn cancel_schedule(&self, schedule_id: i32) -> Result<()> {
+        if !self.database.delete_schedule(schedule_id)? {
+            return Err(YapError::NotFound(format!(
+                "Schedule {} doesn't exist",
+                schedule_id
+            )));
+        }
         Ok(())
     }
 
-    pub fn queue(&self) -> anyhow::Result<()> {
-        let queue = self.mpd.queue()?;
-        for song in queue {
-            let db_song = self.database.get_song_by_id(&song);
-            if let Some(song_info) = db_song {
-                println!(
-                    "{} - {}",
-                    song_info.name,
-                    if let Some(artist) = song_info.artist {
-                        artist
-                    } else {
-                        "Unknown".to_string()
+    /**
+     * Runs every schedule due at the given time, then removes it or moves it to the next day
+     */
+    pub fn run_due_schedules(&self, now: DateTime<Local>) -> Result<Vec<ScheduleRun>> {
+        let schedules = self.database.get_due_schedules(now.timestamp())?;
+        let mut runs = Vec::new();
+        for schedule in schedules {
+            let result = self.run_schedule(&schedule);
+            if schedule.daily {
+                let time = match Local.timestamp_opt(schedule.run_at, 0).single() {
+                    Some(run_at) => run_at.time(),
+                    None => {
+                        return Err(YapError::Invalid(format!(
+                            "Invalid time for schedule {}",
+                            schedule.id
+                        )))
                     }
-                )
+                };
+                self.database
+                    .reschedule(schedule.id, next_occurrence(time, now)?.timestamp())?;
+            } else {
+                self.database.delete_schedule(schedule.id)?;
             }
+            runs.push(ScheduleRun { schedule, result });
         }
-        Ok(())
+        Ok(runs)
     }
 
-    pub fn add_to_queue(&self, song_name: &str) -> anyhow::Result<()> {
-        let song_info = self.database.get_song_by_name(song_name);
-        if let Some(song) = song_info {
-            self.mpd.add_to_queue(&song.id)?;
-            println!("Song {} added to queue", song_name);
-        } else {
-            return Err(anyhow!(format!("Song {} doesn't exist", song_name)));
+    fn run_schedule(&self, schedule: &Schedule) -> Result<()> {
+        let volume = self.player.volume().ok();
+        match (schedule.kind.as_str(), &schedule.playlist_name) {
+            ("sleep", _) => {
+                if let Some(volume) = volume {
+                    self.fade_volume(volume, 0, schedule.fade as u32)?;
+                }
+                self.player.pause(Some(true))?;
+                if let Some(volume) = volume {
+                    self.player.set_volume(volume)?;
+                }
+            }
+            ("alarm", Some(playlist_name)) => {
+                if volume.is_some() {
+                    self.player.set_volume(0)?;
+                }
+                self.play_playlist(playlist_name)?;
+                if let Some(volume) = volume {
+                    let target = if volume == 0 { 100 } else { volume };
+                    self.fade_volume(0, target, schedule.fade as u32)?;
+                }
+            }
+            _ => {
+                return Err(YapError::Invalid(format!(
+                    "Invalid schedule {}",
+                    schedule.id
+                )))
+            }
         }
         Ok(())
     }
 
-    pub fn remove_from_queue(&self, song_name: &str) -> anyhow::Result<()> {
-        let song_info = self.database.get_song_by_name(song_name);
-        if let Some(song) = song_info {
-            self.mpd.remove_from_queue(&song.id)?;
-            println!("Song {} removed from queue", song_name);
-        } else {
-            return Err(anyhow!(format!("Song {} doesn't exist", song_name)));
+    /**
+     * Moves the volume from one value to another one step per second
+     */
+    fn fade_volume(&self, from: i8, to: i8, seconds: u32) -> Result<()> {
+        for step in 1..=seconds {
+            let volume = from as i32 + (to as i32 - from as i32) * step as i32 / seconds as i32;
+            self.player.set_volume(volume as i8)?;
+            thread::sleep(Duration::from_secs(1));
         }
+        self.player.set_volume(to)?;
         Ok(())
     }
 
-    pub fn shuffle_queue(&self) -> anyhow::Result<()> {
-        self.mpd.shuffle_queue()?;
-        println!("Queue shuffled");
-        Ok(())
+    pub fn run_player(&self) -> Result<()> {
+        self.player.run()
     }
+
+    /**
+     * Passes every player event to the callback until it or the player fails, never returns otherwise
+     */
+    pub fn watch(&self, mut on_event: impl FnMut(WatchEvent) -> Result<()>) -> Result<()> {
+        let mut events = self.player.events()?;
+        loop {
+            for event in events.next_events()? {
+                let song = events
+                    .current_song_id()
+                    .and_then(|song_id| self.database.get_song_by_id(&song_id).ok().flatten());
+                if let (PlayerEvent::SongChanged { .. }, Some(song)) = (&event, &song) {
+                    if self.notifier.is_enabled() {
+                        //A notification daemon going away shouldn't stop the stream of events
+                        let _ = self.notifier.song_changed(song);
+                    }
+                }
+                on_event(WatchEvent { event, song })?;
+            }
+        }
+    }
+}
+
+/**
+ * Gets the first time after the given instant when the clock shows the given time
+ */
+fn next_occurrence(time: NaiveTime, after: DateTime<Local>) -> Result<DateTime<Local>> {
+    let mut date = after.date_naive();
+    loop {
+        if let Some(candidate) = Local.from_local_datetime(&date.and_time(time)).earliest() {
+            if candidate > after {
+                return Ok(candidate);
+            }
+        }
+        date = match date.succ_opt() {
+            Some(date) => date,
+            None => {
+                return Err(YapError::Invalid(format!(
+                    "No date after {} for {}",
+                    after, time
+                )))
+            }
+        };
+    }
+}
+
+/**
+ * Values that can be used in the templates of current and status
+ */
+pub fn status_values(status: &Status, song: Option<Song>) -> HashMap<&'static str, String> {
+    let (id, title, artist) = match song {
+        Some(song) => (
+            song.id,
+            song.name,
+            song.artist.unwrap_or("Unknown".to_string()),
+        ),
+        None => Default::default(),
+    };
+    let percentage = match (status.elapsed, status.duration) {
+        (Some(elapsed), Some(duration)) if !duration.is_zero() => {
+            (elapsed.as_secs_f64() / duration.as_secs_f64() * 100.) as u32
+        }
+        _ => 0,
+    };
+    let optional = |value: Option<String>| value.unwrap_or_default();
+
+    HashMap::from([
+        ("id", id),
+        ("title", title),
+        ("artist", artist),
+        ("file", optional(status.file.clone())),
+        ("state", status.state.clone()),
+        ("elapsed", optional(status.elapsed.map(format_duration))),
+        ("duration", optional(status.duration.map(format_duration))),
+        ("percentage", percentage.to_string()),
+        ("position", optional(status.position.map(|p| p.to_string()))),
+        ("length", status.queue_length.to_string()),
+        ("bitrate", optional(status.bitrate.map(|b| b.to_string()))),
+        ("format", optional(status.audio_format.clone())),
+        (
+            "volume",
+            if status.volume < 0 {
+                "n/a".to_string()
+            } else {
+                format!("{}%", status.volume)
+            },
+        ),
+        ("repeat", status.repeat.to_string()),
+        ("random", status.random.to_string()),
+        ("single", status.single.to_string()),
+        ("consume", status.consume.to_string()),
+        ("paused", status.is_paused.to_string()),
+        ("crossfade", status.crossfade.to_string()),
+        ("replay_gain", status.replay_gain.clone()),
+    ])
 }
@@ -0,0 +1,23 @@
+/*!
+ * yap keeps a library of songs downloaded from youtube and other sources,
+ * organises them in playlists and plays them through mpd or in-process.
+ *
+ * [`handler::Handler`] is the entry point, built from a [`db::database::Database`],
+ * the [`sources::source::Sources`] songs can be downloaded from and a
+ * [`player::backend::Player`].
+ */
+pub mod audio;
+pub mod backup;
+pub mod config;
+pub mod db;
+pub mod error;
+pub mod handler;
+pub mod library;
+pub mod mpd;
+pub mod notifications;
+pub mod player;
+pub mod playlists;
+pub mod sources;
+pub mod sync;
+pub mod utils;
+pub mod youtube_api;
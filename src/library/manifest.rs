@@ -0,0 +1,74 @@
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Result, YapError};
+
+//Bumped when the document changes in a way older versions can't read
+pub const MANIFEST_VERSION: u32 = 2;
+
+/**
+ * A library without its audio, songs are downloaded again when it's imported
+ */
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LibraryManifest {
+    pub version: u32,
+    pub exported_at: String,
+    pub songs: Vec<ManifestSong>,
+    pub playlists: Vec<ManifestPlaylist>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ManifestSong {
+    pub id: String,
+    pub name: String,
+    pub artist: Option<String>,
+    pub source: String,
+    //The fields below are optional, older manifests don't have them
+    //ReplayGain tags, kept when the downloaded audio can't be analyzed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub loudness: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub true_peak: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gain: Option<f64>,
+    //Part of the file that gets played, in seconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub start_offset: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub end_offset: Option<f64>,
+    //Video a chapter was cut from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent_id: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ManifestPlaylist {
+    pub name: String,
+    //Song ids in playlist order
+    pub songs: Vec<String>,
+}
+
+impl LibraryManifest {
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string_pretty(self)
+            .map_err(|error| YapError::Io(std::io::Error::other(error)))
+    }
+
+    pub fn from_json(json: &str) -> Result<Self> {
+        let manifest: Self = serde_json::from_str(json).map_err(|error| {
+            YapError::Io(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("Not a yap library: {}", error),
+            ))
+        })?;
+        if manifest.version > MANIFEST_VERSION {
+            return Err(YapError::Io(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!(
+                    "Library version {} is newer than this yap supports",
+                    manifest.version
+                ),
+            )));
+        }
+        Ok(manifest)
+    }
+}
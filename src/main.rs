@@ -1,86 +1,613 @@
 mod args;
-mod config;
-mod db;
-mod handler;
-mod mpd;
-mod utils;
-mod youtube_api;
 
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process, thread,
+    time::Duration,
+};
+
+use chrono::{DateTime, Local, TimeZone};
 use clap::Parser;
-use config::Config;
-use mpd::mpd_client::MpdClient;
+use mpd::status::ReplayGain;
+use yap::{
+    audio::loudness::Loudness,
+    backup::backup::{create_backup, restore_backup, BackupManifest, RestoreMode, RestoreReport},
+    config::{config_path, Backend, Config},
+    db::{
+        database::Database,
+        models::{Schedule, Song},
+    },
+    error::YapError,
+    handler::{
+        status_values, AddedSong, ChapterDownload, Handler, ImportReport, LibraryImportReport,
+        PlaylistSummary,
+    },
+    library::manifest::{LibraryManifest, ManifestSong},
+    mpd::mpd_client::MpdClient,
+    notifications::notifier::DesktopNotifier,
+    player::{
+        backend::{Player, QueueEntry, Status},
+        local_player::LocalPlayer,
+    },
+    playlists::{
+        format::{PathStyle, PlaylistFormat},
+        parse::ImportedEntry,
+    },
+    sources::{
+        http_source::HttpSource, local_source::LocalSource, source::Sources,
+        ytdlp_source::YtDlpSource,
+    },
+    sync::{sync::SyncReport, transcode::Encoding},
+    utils::{template::render, utils::format_duration},
+    youtube_api::youtube_api::YoutubeAPI,
+};
 
 use crate::args::*;
-use crate::db::database::Database;
-use crate::handler::Handler;
-use crate::youtube_api::youtube_api::YoutubeAPI;
+
+macro_rules! check {
+    ($expr:expr) => {
+        check!($expr, |_| {})
+    };
+    ($expr:expr, $on_success:expr) => {
+        match $expr {
+            Ok(value) => ($on_success)(value),
+            Err(err) => {
+                //Each kind of error exits with its own code so scripts can tell them apart
+                let err: anyhow::Error = err.into();
+                println!("{:?}", err);
+                process::exit(exit_code(&err));
+            }
+        }
+    };
+}
+
+/**
+ * Exit code for an error returned by the handler, 1 when it isn't a YapError
+ */
+fn exit_code(error: &anyhow::Error) -> i32 {
+    error
+        .downcast_ref::<YapError>()
+        .map_or(1, |error| error.exit_code())
+}
 
 #[tokio::main]
 async fn main() {
-    let config: Config = match confy::load("yap", "yap.config") {
-        Ok(config) => config,
-        Err(error) => {
-            println!("{}", error);
-            return;
+    let args = App::parse();
+    let config_path = check!(config_path(args.config), |path| path);
+    let profile = args.profile;
+    //Config commands must work even when the configuration can't build a handler
+    let handler = || -> Handler {
+        check!(build_handler(&config_path, profile.as_deref()), |handler| {
+            handler
+        })
+    };
+
+    match args.command {
+        Command::Download(args) => parse_download_options(handler(), args).await,
+        Command::Playlist(args) => parse_playlist_options(handler(), args).await,
+        Command::Play(args) => parse_play_options(handler(), args),
+        Command::Song(args) => parse_song_options(handler(), args),
+        Command::Mpd(args) => parse_mpd_options(handler(), args),
+        Command::Watch {} => check!(handler().watch(|event| {
+            println!(
+                "{}",
+                serde_json::to_string(&event).map_err(std::io::Error::from)?
+            );
+            Ok(())
+        })),
+        Command::Sleep(args) => check!(
+            handler().sleep(args.delay, args.fade),
+            |run_at: DateTime<Local>| {
+                println!("Playback will stop at {}", run_at.format("%H:%M:%S"))
+            }
+        ),
+        Command::Alarm(args) => check!(
+            handler().alarm(&args.time, &args.playlist, args.fade, args.daily),
+            |run_at: DateTime<Local>| println!(
+                "Playlist {} will start at {}",
+                args.playlist,
+                run_at.format("%Y-%m-%d %H:%M")
+            )
+        ),
+        Command::Schedule(args) => parse_schedule_options(handler(), args),
+        Command::Player(args) => match args {
+            PlayerOptions::Run {} => check!(handler().run_player()),
+        },
+        Command::Config(args) => parse_config_options(&config_path, profile.as_deref(), args),
+        Command::Profile(args) => parse_profile_options(&config_path, profile.as_deref(), args),
+        Command::Backup(args) => parse_backup_options(&config_path, profile.as_deref(), args),
+        Command::Library(args) => parse_library_options(handler(), args).await,
+        Command::Sync(args) => sync(handler(), args),
+    }
+}
+
+/**
+ * Loads the configuration of the selected profile, with its paths expanded and created
+ */
+fn load_config(config_path: &Path, profile: Option<&str>) -> Result<Config, YapError> {
+    let mut config = Config::load(config_path)?;
+    config.select_profile(profile)?;
+    config.expand_paths()?;
+    config.create_directories()?;
+    Ok(config)
+}
+
+/**
+ * Builds the handler from the configuration file
+ */
+fn build_handler(config_path: &Path, profile: Option<&str>) -> Result<Handler, YapError> {
+    let config = load_config(config_path, profile)?;
+    let database = Database::new(
+        format!("sqlite://{}", config.database.database_path.display()),
+        10,
+    )?;
+    let sources = Sources::new(vec![
+        Box::new(YoutubeAPI::new(
+            config.general.music_directory.clone(),
+            config.general.miniature_directory.clone(),
+            config.general.download_miniature,
+        )),
+        Box::new(HttpSource::new(config.general.music_directory.clone())),
+        Box::new(YtDlpSource::new(config.general.music_directory.clone())),
+        Box::new(LocalSource::new(config.general.music_directory.clone())),
+    ]);
+    let notifier = Box::new(DesktopNotifier::new(
+        config.notifications.clone(),
+        config.general.miniature_directory.clone(),
+    ));
+    let player: Box<dyn Player> = match config.player.backend {
+        Backend::Mpd => Box::new(MpdClient::new(config.mpd.address.clone())),
+        Backend::Local => Box::new(LocalPlayer::new(
+            database.clone(),
+            config.general.music_directory.clone(),
+            config.player.clone(),
+        )),
+    };
+    Handler::new(
+        database,
+        sources,
+        player,
+        notifier,
+        config.general.music_directory,
+        config.general.trim_silence,
+    )
+}
+
+fn parse_config_options(config_path: &Path, profile: Option<&str>, options: ConfigOptions) {
+    match options {
+        ConfigOptions::Path {} => println!("{}", config_path.display()),
+        ConfigOptions::Show {} => check!(
+            Config::load(config_path).and_then(|config| config.to_toml()),
+            |config: String| print!("{}", config)
+        ),
+        ConfigOptions::Get(args) => check!(
+            Config::load(config_path).and_then(|config| config.get(&args.key)),
+            |value: toml::Value| match value {
+                toml::Value::String(value) => println!("{}", value),
+                toml::Value::Table(_) => print!("{}", value),
+                value => println!("{}", value),
+            }
+        ),
+        ConfigOptions::Set(args) => check!(
+            Config::load(config_path).and_then(|mut config| {
+                config.set(&args.key, &args.value)?;
+                config.store(config_path)
+            }),
+            |_| println!("{} set to {}", args.key, args.value)
+        ),
+        ConfigOptions::Edit {} => check!(edit_config(config_path)),
+        ConfigOptions::Validate {} => check!(
+            Config::load(config_path).and_then(|mut config| {
+                config.select_profile(profile)?;
+                config.validate()
+            }),
+            |_| println!("Configuration is valid")
+        ),
+    }
+}
+
+fn parse_backup_options(config_path: &Path, profile: Option<&str>, options: BackupOptions) {
+    let config = check!(load_config(config_path, profile), |config| config);
+    match options {
+        BackupOptions::Create(args) => check!(
+            create_backup(
+                &config,
+                config_path,
+                &args.archive,
+                args.audio,
+                args.miniatures
+            ),
+            |manifest: BackupManifest| println!(
+                "Backed up {} songs and {} playlists to {}",
+                manifest.songs,
+                manifest.playlists,
+                args.archive.display()
+            )
+        ),
+        BackupOptions::Restore(args) => {
+            let mode = match args.mode {
+                RestoreModeArg::Merge => RestoreMode::Merge,
+                RestoreModeArg::Replace => RestoreMode::Replace,
+            };
+            check!(
+                restore_backup(
+                    &config,
+                    config_path,
+                    &args.archive,
+                    mode,
+                    args.dry_run,
+                    profile
+                ),
+                |report: RestoreReport| print_restore_report(&report, args.dry_run)
+            )
         }
+    }
+}
+
+fn sync(handler: Handler, options: SyncOptions) {
+    let encoding = Encoding {
+        codec: options.codec,
+        bitrate: options.bitrate,
     };
-    let mpd = MpdClient::new();
-    let database = match Database::new(format!("sqlite://{}", config.database.database_path), 10) {
-        Ok(database) => database,
-        Err(error) => {
-            println!("{}", error);
-            return;
+    check!(
+        handler.sync_playlists(&options.playlists, &options.to, &encoding),
+        |report: SyncReport| {
+            for name in report.written.iter() {
+                println!("Copied {}", name)
+            }
+            for name in report.removed.iter() {
+                println!("Removed {}", name)
+            }
+            for name in report.missing.iter() {
+                println!("Song {} has no audio file, skipping...", name)
+            }
+            for (name, error) in report.failed.iter() {
+                println!("Couldn't copy {}: {}", name, error)
+            }
+            println!(
+                "Synced {} playlists to {}, {} songs copied and {} unchanged",
+                report.playlists.len(),
+                options.to.display(),
+                report.written.len(),
+                report.unchanged
+            )
+        }
+    )
+}
+
+async fn parse_library_options(handler: Handler, options: LibraryOptions) {
+    match options {
+        LibraryOptions::Export(args) => {
+            let manifest = check!(handler.export_library(), |manifest| manifest);
+            let content = match args.json {
+                true => check!(manifest.to_json(), |json: String| json + "\n"),
+                false => describe_library(&manifest),
+            };
+            match args.output {
+                Some(output) => check!(fs::write(&output, content), |_| println!(
+                    "Library exported to {}",
+                    output.display()
+                )),
+                None => print!("{}", content),
+            }
+        }
+        LibraryOptions::Analyze(args) => check!(handler.analyze_library(args.all), print_analysis),
+        LibraryOptions::Import(args) => {
+            let manifest = check!(
+                fs::read_to_string(&args.file)
+                    .map_err(YapError::from)
+                    .and_then(|json| LibraryManifest::from_json(&json)),
+                |manifest| manifest
+            );
+            check!(
+                handler.import_library(manifest, !args.no_download).await,
+                |report: LibraryImportReport| {
+                    for added in report.downloaded.iter() {
+                        print_added(added)
+                    }
+                    for song in report.skipped.iter() {
+                        println!("Song {} not downloaded, skipping...", describe_song(song))
+                    }
+                    for (song, error) in report.failed.iter() {
+                        println!("Couldn't download {}: {}", describe_song(song), error)
+                    }
+                    for playlist in report.playlists_added.iter() {
+                        println!("Playlist {} added", playlist)
+                    }
+                    for playlist in report.playlists_merged.iter() {
+                        println!("Songs added to playlist {}", playlist)
+                    }
+                    println!(
+                        "{} songs downloaded, {} already in the library",
+                        report.downloaded.len(),
+                        report.existing
+                    )
+                }
+            )
+        }
+    }
+}
+
+fn print_analysis(results: Vec<(Song, yap::error::Result<Loudness>)>) {
+    let analyzed = results.iter().filter(|(_, result)| result.is_ok()).count();
+    for (song, result) in results {
+        match result {
+            Ok(loudness) => println!(
+                "{}: {:.1} LUFS, peak {:.1} dBTP, gain {:+.1} dB",
+                song.name, loudness.integrated, loudness.true_peak, loudness.gain
+            ),
+            Err(error) => println!("Couldn't analyze {}: {}", song.name, error),
         }
+    }
+    println!("{} songs analyzed", analyzed)
+}
+
+fn describe_song(song: &ManifestSong) -> String {
+    match &song.artist {
+        Some(artist) => format!("{} - {} ({})", artist, song.name, song.id),
+        None => format!("{} ({})", song.name, song.id),
+    }
+}
+
+/**
+ * Human readable listing of an exported library
+ */
+fn describe_library(manifest: &LibraryManifest) -> String {
+    let mut description = format!("Songs ({}):\n", manifest.songs.len());
+    for song in manifest.songs.iter() {
+        description += &format!("  {} [{}]\n", describe_song(song), song.source);
+    }
+    description += &format!("Playlists ({}):\n", manifest.playlists.len());
+    for playlist in manifest.playlists.iter() {
+        description += &format!("  {} ({} songs)\n", playlist.name, playlist.songs.len());
+    }
+    description
+}
+
+fn print_restore_report(report: &RestoreReport, dry_run: bool) {
+    let (add, remove, copy, replace) = match dry_run {
+        true => ("Would add", "Would remove", "Would copy", "Would replace"),
+        false => ("Added", "Removed", "Copied", "Replaced"),
     };
-    database.run_embedded_migrations();
-    let api = YoutubeAPI::new(
-        config.general.music_directory.clone(),
-        config.general.miniature_directory.clone(),
-        config.general.download_miniature,
-    );
-    let handler = Handler::new(database, api, mpd);
-    let args = App::parse();
+    println!("Backup of {}", report.manifest.created_at);
+    for song in report.songs_added.iter() {
+        println!("{} song {}", add, song)
+    }
+    for song in report.songs_removed.iter() {
+        println!("{} song {}", remove, song)
+    }
+    for playlist in report.playlists_added.iter() {
+        println!("{} playlist {}", add, playlist)
+    }
+    for playlist in report.playlists_merged.iter() {
+        println!("{} songs to playlist {}", add, playlist)
+    }
+    println!("{} {} files", copy, report.files_copied);
+    if report.config_replaced {
+        println!("{} the configuration file", replace)
+    }
+}
 
-    match args.command {
-        Command::Download(args) => parse_download_options(handler, args).await,
-        Command::Playlist(args) => parse_playlist_options(handler, args),
-        Command::Play(args) => parse_play_options(handler, args),
-        Command::Song(args) => parse_song_options(handler, args),
-        Command::Mpd(args) => parse_mpd_options(handler, args),
+fn parse_profile_options(config_path: &Path, profile: Option<&str>, options: ProfileOptions) {
+    match options {
+        ProfileOptions::List {} => check!(Config::load(config_path), |config: Config| {
+            let active = config.active_profile(profile);
+            let mark = |name: Option<&str>| if name == active { "*" } else { " " };
+            println!("{} default", mark(None));
+            for name in config.profiles.keys() {
+                println!("{} {}", mark(Some(name)), name);
+            }
+        }),
+        ProfileOptions::Use(args) => check!(
+            Config::load(config_path).and_then(|mut config| {
+                config.profile = match args.name.as_str() {
+                    "default" => None,
+                    name if config.profiles.contains_key(name) => Some(name.to_string()),
+                    name => {
+                        return Err(YapError::NotFound(format!(
+                            "Profile {} doesn't exist",
+                            name
+                        )))
+                    }
+                };
+                config.store(config_path)
+            }),
+            |_| println!("Using profile {}", args.name)
+        ),
     }
 }
 
-fn parse_playlist_options(handler: Handler, options: PlaylistOptions) {
+/**
+ * Opens the configuration file in the user's editor, then checks it still parses
+ */
+fn edit_config(config_path: &Path) -> Result<(), YapError> {
+    Config::load(config_path)?;
+    let editor = std::env::var("VISUAL")
+        .or(std::env::var("EDITOR"))
+        .unwrap_or("vi".to_string());
+    let status = process::Command::new("sh")
+        .arg("-c")
+        .arg(format!("{} \"$1\"", editor))
+        .arg("sh")
+        .arg(config_path)
+        .status()?;
+    if !status.success() {
+        return Err(YapError::Config(format!(
+            "{} exited with {}",
+            editor, status
+        )));
+    }
+    Config::load(config_path)?;
+    Ok(())
+}
+
+async fn parse_playlist_options(handler: Handler, options: PlaylistOptions) {
     match options {
-        PlaylistOptions::Create(args) => {
-            check!(handler.create_playlist(
+        PlaylistOptions::Create(args) => check!(
+            handler.create_playlist(
                 &args.name,
                 args.songs
                     .split(",")
                     .map(|s| s.to_string())
                     .collect::<Vec<String>>(),
-            ))
-        }
-        PlaylistOptions::List {} => {
-            check!(handler.get_playlists());
-        }
-        PlaylistOptions::Delete(args) => check!(handler.delete_playlist(&args.name)),
-        PlaylistOptions::Insert(args) => {
-            check!(handler.insert_into_playlist(&args.playlist_name, &args.song_name))
-        }
+            ),
+            |songs: Vec<Song>| {
+                for song in songs {
+                    println!("Song {} added to playlist", song.name);
+                }
+            }
+        ),
+        PlaylistOptions::List {} => check!(handler.get_playlist_summaries(), |playlists: Vec<
+            PlaylistSummary,
+        >| {
+            for playlist in playlists {
+                let untimed = match playlist.untimed {
+                    0 => String::new(),
+                    untimed => format!(", {} without a duration", untimed),
+                };
+                println!(
+                    "{} ({} songs, {}{})",
+                    playlist.name,
+                    playlist.songs,
+                    format_duration(playlist.duration),
+                    untimed
+                )
+            }
+        }),
+        PlaylistOptions::Delete(args) => check!(handler.delete_playlist(&args.name), |_| {
+            println!("Playlist {} deleted successfully", args.name)
+        }),
+        PlaylistOptions::Insert(args) => check!(
+            handler.insert_into_playlist(&args.playlist_name, &args.song_name),
+            |_| println!(
+                "Song {} successfully added to {}",
+                args.song_name, args.playlist_name
+            )
+        ),
         PlaylistOptions::Remove(args) => {
             check!(handler.remove_song_from_playlist(&args.playlist_name, &args.song_name))
         }
+        PlaylistOptions::Export(args) => export_playlists(handler, args),
+        PlaylistOptions::Import(args) => import_playlist(handler, args).await,
     };
 }
+
+async fn import_playlist(handler: Handler, options: PlaylistImportOptions) {
+    check!(
+        handler
+            .import_playlist(
+                &options.file,
+                options.format,
+                options.name,
+                options.download
+            )
+            .await,
+        |report: ImportReport| {
+            for added in report.downloaded.iter() {
+                print_added(added)
+            }
+            for entry in report.unmatched.iter() {
+                println!("No song matches {}, skipping...", describe_entry(entry))
+            }
+            for (entry, error) in report.failed.iter() {
+                println!("Couldn't download {}: {}", describe_entry(entry), error)
+            }
+            println!(
+                "Playlist {} imported with {} songs",
+                report.playlist_name,
+                report.songs.len()
+            )
+        }
+    )
+}
+
+fn describe_entry(entry: &ImportedEntry) -> String {
+    match (&entry.artist, &entry.title) {
+        (Some(artist), Some(title)) => format!("{} - {} ({})", artist, title, entry.location),
+        (None, Some(title)) => format!("{} ({})", title, entry.location),
+        _ => entry.location.clone(),
+    }
+}
+
+fn export_playlists(handler: Handler, options: PlaylistExportOptions) {
+    //MPD only loads m3u files with paths relative to its music directory
+    let to_mpd = options.all && options.directory.is_none();
+    let style = match (options.absolute, options.relative) {
+        (true, _) => PathStyle::Absolute,
+        (_, true) => PathStyle::Relative,
+        _ if to_mpd => PathStyle::Relative,
+        _ => PathStyle::Absolute,
+    };
+    let format = options.format.unwrap_or(if to_mpd {
+        PlaylistFormat::M3u
+    } else {
+        PlaylistFormat::M3u8
+    });
+    match options.name {
+        Some(name) => check!(
+            handler.export_playlist(&name, format, style),
+            |playlist: String| match &options.output {
+                Some(output) => check!(fs::write(output, playlist), |_| println!(
+                    "Playlist {} exported to {}",
+                    name,
+                    output.display()
+                )),
+                None => print!("{}", playlist),
+            }
+        ),
+        None => check!(
+            handler.export_playlists(options.directory.as_deref(), format, style),
+            |files: Vec<PathBuf>| {
+                for file in files {
+                    println!("Exported {}", file.display())
+                }
+            }
+        ),
+    }
+}
 async fn parse_download_options(handler: Handler, options: DownloadOptions) {
+    if options.split_chapters {
+        return check!(
+            handler
+                .download_chapters(&options.id, options.artist, options.playlist)
+                .await,
+            |download: ChapterDownload| {
+                for song in download.songs.iter() {
+                    if !download.existing.contains(&song.id) {
+                        println!("Song {} downloaded successfully", song.name)
+                    }
+                }
+                for warning in download.warnings.iter() {
+                    println!("{}", warning)
+                }
+                if let Some(playlist) = download.playlist {
+                    println!("Chapters added to playlist {}", playlist)
+                }
+                println!(
+                    "{} split into {} songs, {} already in the library",
+                    download.title,
+                    download.songs.len(),
+                    download.existing.len()
+                )
+            }
+        );
+    }
     check!(
         handler
-            .add_song_to_registry(&options.id, &options.name, options.artist)
-            .await
+            .add_song_to_registry(&options.id, options.name, options.artist)
+            .await,
+        |added: AddedSong| print_added(&added)
     )
 }
 
+fn print_added(added: &AddedSong) {
+    println!("Song {} downloaded successfully", added.song.name);
+    for warning in added.warnings.iter() {
+        println!("{}", warning)
+    }
+}
+
 fn parse_play_options(handler: Handler, options: PlayOptions) {
     match options {
         PlayOptions::Playlist(args) => check!(handler.play_playlist(&args.name)),
@@ -90,26 +617,282 @@ fn parse_play_options(handler: Handler, options: PlayOptions) {
 
 fn parse_song_options(handler: Handler, options: SongOptions) {
     match options {
-        SongOptions::List {} => check!(handler.get_songs()),
-        SongOptions::Delete(args) => check!(handler.delete_song(&args.name)),
+        SongOptions::List {} => check!(handler.get_songs(), |songs: Vec<Song>| {
+            for song in songs {
+                println!(
+                    "{} - {}",
+                    song.name,
+                    song.artist.unwrap_or("Unknown".to_string())
+                )
+            }
+        }),
+        SongOptions::Delete(args) => check!(handler.delete_song(&args.name), |_| println!(
+            "Song {} deleted successfully",
+            args.name
+        )),
+        SongOptions::Trim(args) => {
+            let song = if args.reset {
+                handler.reset_trim(&args.name)
+            } else if args.detect {
+                handler.trim_silence(&args.name)
+            } else {
+                handler.trim_song(&args.name, args.start, args.end)
+            };
+            check!(song, |song: Song| println!(
+                "Song {} plays {}",
+                song.name,
+                describe_trim(&song)
+            ))
+        }
+    }
+}
+
+fn describe_trim(song: &Song) -> String {
+    let time = |seconds: f64| format_duration(Duration::from_secs_f64(seconds));
+    match (song.start_offset, song.end_offset) {
+        (None, None) => "whole".to_string(),
+        (Some(start), None) => format!("from {}", time(start)),
+        (None, Some(end)) => format!("until {}", time(end)),
+        (Some(start), Some(end)) => format!("from {} to {}", time(start), time(end)),
+    }
+}
+
+fn parse_schedule_options(handler: Handler, options: ScheduleOptions) {
+    match options {
+        ScheduleOptions::List {} => check!(handler.get_schedules(), |schedules: Vec<Schedule>| {
+            for schedule in schedules {
+                let run_at = match Local.timestamp_opt(schedule.run_at, 0).single() {
+                    Some(run_at) => run_at.format("%Y-%m-%d %H:%M:%S").to_string(),
+                    None => schedule.run_at.to_string(),
+                };
+                println!(
+                    "{}. {} at {}{}{}",
+                    schedule.id,
+                    schedule.kind,
+                    run_at,
+                    schedule
+                        .playlist_name
+                        .map(|name| format!(" playing {}", name))
+                        .unwrap_or_default(),
+                    if schedule.daily { " (daily)" } else { "" }
+                );
+            }
+        }),
+        ScheduleOptions::Cancel(args) => check!(handler.cancel_schedule(args.id), |_| println!(
+            "Schedule {} cancelled",
+            args.id
+        )),
+        ScheduleOptions::Run {} => run_schedules(handler),
     }
 }
 
+/**
+ * Runs the saved sleep timers and alarms as they become due, never returns
+ */
+fn run_schedules(handler: Handler) {
+    loop {
+        match handler.run_due_schedules(Local::now()) {
+            Ok(runs) => {
+                for run in runs {
+                    match (run.result, run.schedule.playlist_name) {
+                        (Err(err), _) => println!("{:?}", err),
+                        (Ok(_), Some(playlist_name)) => {
+                            println!("Alarm started playlist {}", playlist_name)
+                        }
+                        (Ok(_), None) => println!("Sleep timer expired, playback paused"),
+                    }
+                }
+            }
+            Err(err) => {
+                println!("{:?}", err);
+                return;
+            }
+        }
+        thread::sleep(Duration::from_secs(1));
+    }
+}
+
+fn print_toggle(name: &str) -> impl Fn(bool) + '_ {
+    move |state| println!("{}: {}", name, state)
+}
+
 pub fn parse_mpd_options(handler: Handler, options: MpdOptions) {
     match options {
         MpdOptions::Play {} => check!(handler.play()),
-        MpdOptions::Pause {} => check!(handler.pause()),
-        MpdOptions::Shuffle {} => check!(handler.shuffle()),
-        MpdOptions::Clear {} => check!(handler.clear_queue()),
-        MpdOptions::Next {} => check!(handler.next()),
-        MpdOptions::Current {} => check!(handler.current()),
-        MpdOptions::Repeat {} => check!(handler.repeat()),
-        MpdOptions::Previous {} => check!(handler.previous()),
-        MpdOptions::Seek(options) => check!(handler.seek(options.percentage)),
-        MpdOptions::Status {} => check!(handler.status()),
-        MpdOptions::Queue {} => check!(handler.queue()),
-        MpdOptions::QueueAdd(options) => check!(handler.add_to_queue(&options.song_name)),
-        MpdOptions::QueueRemove(options) => check!(handler.remove_from_queue(&options.song_name)),
-        MpdOptions::QueueShuffle {} => check!(handler.shuffle_queue()),
+        MpdOptions::Pause {} => check!(handler.pause(), print_toggle("Pause")),
+        MpdOptions::Shuffle {} => check!(handler.shuffle(), print_toggle("Shuffle")),
+        MpdOptions::Clear {} => check!(handler.clear_queue(), |_| println!("Queue cleared")),
+        MpdOptions::Next {} => check!(handler.next(), |_| println!(
+            "Skipping to next song in queue"
+        )),
+        MpdOptions::Current(options) => check!(handler.current(), |(status, song)| {
+            let template = options.template.unwrap_or(
+                "Current song: {title}\nArtist: {artist}\n{elapsed}/{duration} ({percentage}%)"
+                    .to_string(),
+            );
+            println!("{}", render(&template, &status_values(&status, Some(song))));
+        }),
+        MpdOptions::Repeat {} => check!(handler.repeat(), print_toggle("Repeat")),
+        MpdOptions::Previous {} => check!(handler.previous(), |_| println!(
+            "Going back to previous song in queue"
+        )),
+        MpdOptions::Seek(options) => check!(handler.seek(options.position), |position| println!(
+            "Seeked to {}",
+            format_duration(position)
+        )),
+        MpdOptions::Status(options) => check!(handler.status(), |(status, song): (Status, _)| {
+            let template = options.template.unwrap_or({
+                let mut template = concat!(
+                    "State: {state}\tRandom: {random}\tRepeat: {repeat}\t",
+                    "Single: {single}\tConsume: {consume}\n",
+                    "Volume: {volume}\tCrossfade: {crossfade}s\tReplay gain: {replay_gain}"
+                )
+                .to_string();
+                if status.file.is_some() {
+                    template.push_str("\nSong: {position}/{length}");
+                    if status.duration.is_some() {
+                        template.push_str("\t{elapsed}/{duration}");
+                    }
+                    if status.bitrate.is_some() {
+                        template.push_str("\t{bitrate} kbps");
+                    }
+                    if status.audio_format.is_some() {
+                        template.push_str("\t{format}");
+                    }
+                }
+                template
+            });
+            println!("{}", render(&template, &status_values(&status, song)));
+        }),
+        MpdOptions::Queue {} => check!(handler.queue(), |queue: Vec<QueueEntry>| {
+            for entry in queue {
+                let duration = match entry.duration {
+                    Some(duration) => format!(" [{}]", format_duration(duration)),
+                    None => String::new(),
+                };
+                match entry.song {
+                    Some(song_info) => println!(
+                        "{}. {} - {}{}",
+                        entry.position,
+                        song_info.name,
+                        song_info.artist.unwrap_or("Unknown".to_string()),
+                        duration
+                    ),
+                    None => println!("{}. {}{}", entry.position, entry.file, duration),
+                }
+            }
+        }),
+        MpdOptions::QueueAdd(options) => check!(
+            handler.add_to_queue(&options.song_name, options.next),
+            |_| if options.next {
+                println!("Song {} will play next", options.song_name)
+            } else {
+                println!("Song {} added to queue", options.song_name)
+            }
+        ),
+        MpdOptions::QueueRemove(options) => {
+            if let Some(song_name) = options.song_name {
+                check!(
+                    handler.remove_from_queue(&song_name, options.all),
+                    |removed| {
+                        println!("Song {} removed from queue {} time(s)", song_name, removed)
+                    }
+                )
+            } else if let Some(position) = options.position {
+                check!(handler.remove_position_from_queue(position), |_| println!(
+                    "Song at position {} removed from queue",
+                    position
+                ))
+            } else if let Some(range) = options.range {
+                check!(
+                    parse_range(&range).and_then(|(start, end)| handler
+                        .remove_range_from_queue(start, end)
+                        .map(|_| (start, end))),
+                    |(start, end)| println!(
+                        "Songs from position {} to {} removed from queue",
+                        start, end
+                    )
+                )
+            }
+        }
+        MpdOptions::QueueDedupe {} => check!(handler.dedupe_queue(), |removed| println!(
+            "Removed {} duplicate(s) from queue",
+            removed
+        )),
+        MpdOptions::QueueSave(options) => check!(
+            handler.save_queue(&options.playlist_name),
+            |skipped: Vec<QueueEntry>| {
+                for entry in skipped {
+                    println!("File {} is not in the database, skipping...", entry.file)
+                }
+                println!("Queue saved as playlist {}", options.playlist_name)
+            }
+        ),
+        MpdOptions::QueueRestore {} => {
+            check!(handler.restore_queue(), |_| println!("Queue restored"))
+        }
+        MpdOptions::QueueShuffle {} => {
+            check!(handler.shuffle_queue(), |_| println!("Queue shuffled"))
+        }
+        MpdOptions::QueueMove(options) => check!(
+            handler.move_in_queue(options.from, options.to),
+            |_| println!(
+                "Song moved from position {} to {}",
+                options.from, options.to
+            )
+        ),
+        MpdOptions::QueueSwap(options) => check!(
+            handler.swap_in_queue(options.first, options.second),
+            |_| println!(
+                "Songs at positions {} and {} swapped",
+                options.first, options.second
+            )
+        ),
+        MpdOptions::QueuePlay(options) => check!(handler.play_position(options.position)),
+        MpdOptions::Consume {} => check!(handler.consume(), print_toggle("Consume")),
+        MpdOptions::Single {} => check!(handler.single(), print_toggle("Single")),
+        MpdOptions::Volume(options) => check!(handler.volume(options.volume), |volume| println!(
+            "Volume: {}%",
+            volume
+        )),
+        MpdOptions::Mute {} => check!(handler.mute(), print_toggle("Mute")),
+        MpdOptions::Crossfade(options) => check!(handler.crossfade(options.seconds), |_| {
+            println!("Crossfade: {}s", options.seconds)
+        }),
+        MpdOptions::Mixramp(options) => check!(
+            handler.mixramp(options.db, options.delay),
+            |(db, delay): (f32, Option<Duration>)| println!(
+                "Mixramp: {}dB\tDelay: {}",
+                db,
+                match delay {
+                    Some(delay) => format!("{}s", delay.as_secs_f64()),
+                    None => "off".to_string(),
+                }
+            )
+        ),
+        MpdOptions::ReplayGain(options) => {
+            let mode = match options.mode {
+                ReplayGainMode::Off => ReplayGain::Off,
+                ReplayGainMode::Track => ReplayGain::Track,
+                ReplayGainMode::Album => ReplayGain::Album,
+                ReplayGainMode::Auto => ReplayGain::Auto,
+            };
+            check!(handler.replay_gain(mode), |_| println!(
+                "Replay gain: {}",
+                mode
+            ))
+        }
+    }
+}
+
+/**
+ * Parses a range of queue positions written as start-end
+ */
+fn parse_range(range: &str) -> yap::error::Result<(u32, u32)> {
+    let invalid = || YapError::Invalid(format!("Invalid range {}, expected start-end", range));
+    let (start, end) = range.split_once('-').ok_or_else(invalid)?;
+    match (start.trim().parse::<u32>(), end.trim().parse::<u32>()) {
+        (Ok(start), Ok(end)) => Ok((start, end)),
+        _ => Err(invalid()),
     }
 }
@@ -1 +1,2 @@
 pub mod mpd_client;
+pub mod mpd_events;
@@ -1,45 +1,114 @@
-use std::time::Duration;
+use std::{
+    env, fs,
+    io::{BufRead, BufReader, Write},
+    net::TcpStream,
+    path::PathBuf,
+    time::Duration,
+};
 
-use anyhow::anyhow;
-use mpd::{Client, Song, State};
+use mpd::{song::Id, status::ReplayGain, Client, Song, State};
 
-pub struct Status {
-    pub repeat: bool,
-    pub random: bool,
-    pub is_paused: bool,
-}
+use super::mpd_events::EventStream;
+use crate::error::{Result, YapError};
+use crate::player::{
+    backend::{
+        song_id_from_file, PlayRange, Player, QueueEntry, QueueSnapshot, SeekTarget, Status,
+    },
+    events::EventSource,
+};
 
-pub struct MpdClient {}
+pub struct MpdClient {
+    address: String,
+}
 
 impl MpdClient {
-    pub fn new() -> Self {
-        Self {}
+    pub fn new(address: String) -> Self {
+        Self { address }
+    }
+
+    /**
+     * The music directory MPD serves songs from, mpd only tells local clients
+     * so this falls back to reading its configuration file
+     */
+    pub fn music_directory(&self) -> Result<PathBuf> {
+        let mut conn = Client::connect(self.address.as_str())?;
+        let error = match conn.music_directory() {
+            Ok(directory) => return Ok(PathBuf::from(directory)),
+            Err(error) => error,
+        };
+        mpd_conf_setting("music_directory").ok_or(YapError::Mpd(error))
+    }
+}
+
+/**
+ * Reads a path setting from the first mpd.conf found where mpd looks for it
+ */
+fn mpd_conf_setting(name: &str) -> Option<PathBuf> {
+    let home = env::var_os("HOME").map(PathBuf::from).unwrap_or_default();
+    let config_home = env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .unwrap_or(home.join(".config"));
+    let content = [
+        config_home.join("mpd/mpd.conf"),
+        home.join(".mpdconf"),
+        home.join(".mpd/mpd.conf"),
+        PathBuf::from("/etc/mpd.conf"),
+    ]
+    .iter()
+    .find_map(|file| fs::read_to_string(file).ok())?;
+    let value = content.lines().find_map(|line| {
+        let (key, value) = line.trim().split_once(char::is_whitespace)?;
+        (key == name).then(|| value.trim().trim_matches('"').to_string())
+    })?;
+    Some(match value.strip_prefix('~') {
+        Some(rest) => PathBuf::from(format!("{}{}", home.display(), rest)),
+        None => PathBuf::from(value),
+    })
+}
+
+impl Player for MpdClient {
+    fn events(&self) -> Result<Box<dyn EventSource>> {
+        Ok(Box::new(EventStream::new(&self.address)?))
     }
 
-    pub fn update_db(&self) -> anyhow::Result<()> {
-        let mut conn = Client::connect("127.0.0.1:6600")?;
+    fn update_db(&self) -> Result<()> {
+        let mut conn = Client::connect(self.address.as_str())?;
         conn.update()?;
         Ok(())
     }
 
-    pub fn add_to_queue(&self, song_id: &str) -> anyhow::Result<()> {
-        let mut conn = Client::connect("127.0.0.1:6600")?;
+    fn add_to_queue(&self, song_id: &str, range: PlayRange) -> Result<()> {
+        let mut conn = Client::connect(self.address.as_str())?;
         let song = Song {
             file: format!("{}.opus", song_id).to_string(),
             ..Default::default()
         };
-        conn.push(song)?;
-        Ok(())
+        let id = conn.push(song)?;
+        set_range(&mut conn, id, range)
+    }
+
+    fn insert_next(&self, song_id: &str, range: PlayRange) -> Result<()> {
+        let mut conn = Client::connect(self.address.as_str())?;
+        let position = match conn.status()?.song {
+            Some(current) => current.pos as usize + 1,
+            None => 0,
+        };
+        let song = Song {
+            file: format!("{}.opus", song_id).to_string(),
+            ..Default::default()
+        };
+        let id = conn.insert(song, position)?;
+        set_range(&mut conn, Id(id as u32), range)
     }
 
-    pub fn play(&self) -> anyhow::Result<()> {
-        let mut conn = Client::connect("127.0.0.1:6600")?;
+    fn play(&self) -> Result<()> {
+        let mut conn = Client::connect(self.address.as_str())?;
         conn.play()?;
         Ok(())
     }
 
-    pub fn pause(&self, state: Option<bool>) -> anyhow::Result<()> {
-        let mut conn = Client::connect("127.0.0.1:6600")?;
+    fn pause(&self, state: Option<bool>) -> Result<bool> {
+        let mut conn = Client::connect(self.address.as_str())?;
         let new_state = match conn.status()?.state {
             mpd::State::Stop => false,
             mpd::State::Play => true,
@@ -49,139 +118,386 @@ impl MpdClient {
             conn.pause(state)?;
         } else {
             conn.pause(new_state)?;
-            println!("Pause: {}", conn.status()?.state == State::Pause);
         }
 
-        Ok(())
+        Ok(conn.status()?.state != State::Play)
     }
 
-    pub fn shuffle(&self, state: Option<bool>) -> anyhow::Result<()> {
-        let mut conn = Client::connect("127.0.0.1:6600")?;
+    fn shuffle(&self, state: Option<bool>) -> Result<bool> {
+        let mut conn = Client::connect(self.address.as_str())?;
         let new_state = !conn.status()?.random;
         if let Some(state) = state {
             conn.random(state)?;
         } else {
             conn.random(new_state)?;
         }
-        println!("Shuffle: {}", conn.status()?.random);
-        Ok(())
+        Ok(conn.status()?.random)
     }
 
-    pub fn clear_queue(&self) -> anyhow::Result<()> {
-        let mut conn = Client::connect("127.0.0.1:6600")?;
+    fn clear_queue(&self) -> Result<()> {
+        let mut conn = Client::connect(self.address.as_str())?;
         conn.clear()?;
         Ok(())
     }
 
-    pub fn next(&self) -> anyhow::Result<()> {
-        let mut conn = Client::connect("127.0.0.1:6600")?;
+    fn next(&self) -> Result<()> {
+        let mut conn = Client::connect(self.address.as_str())?;
         conn.next()?;
         Ok(())
     }
 
-    pub fn previous(&self) -> anyhow::Result<()> {
-        let mut conn = Client::connect("127.0.0.1:6600")?;
+    fn previous(&self) -> Result<()> {
+        let mut conn = Client::connect(self.address.as_str())?;
         conn.prev()?;
         Ok(())
     }
 
-    pub fn current(&self) -> anyhow::Result<String> {
-        let mut conn = Client::connect("127.0.0.1:6600")?;
-        if let Some(song) = conn.currentsong()? {
-            Ok(song.file)
-        } else {
-            Err(anyhow!("No song currently playing"))
-        }
-    }
-
-    pub fn current_time(&self) -> anyhow::Result<String> {
-        let mut conn = Client::connect("127.0.0.1:6600")?;
-        if let Some(timestamps) = conn.status()?.time {
-            let perc = timestamps.0.as_secs() as f64 / timestamps.1.as_secs() as f64 * 100.;
-            let current_minute = timestamps.0.as_secs() / 60;
-            let current_second = timestamps.0.as_secs() - current_minute * 60;
-            let song_minutes = timestamps.1.as_secs() / 60;
-            let song_seconds = timestamps.1.as_secs() - song_minutes * 60;
-            Ok(format!(
-                "{}:{}/{}:{} ({}%)",
-                current_minute, current_second, song_minutes, song_seconds, perc as i32
-            ))
-        } else {
-            Err(anyhow!("No song currently playing"))
-        }
-    }
-
-    pub fn repeat(&self) -> anyhow::Result<()> {
-        let mut conn = Client::connect("127.0.0.1:6600")?;
+    fn repeat(&self) -> Result<bool> {
+        let mut conn = Client::connect(self.address.as_str())?;
         let repeat = !conn.status()?.repeat;
         conn.repeat(repeat)?;
-        println!("Repeat: {}", repeat);
-        Ok(())
+        Ok(repeat)
     }
 
-    pub fn seek(&self, perc: u8) -> anyhow::Result<()> {
-        let mut conn = Client::connect("127.0.0.1:6600")?;
-        if let Some(current_song) = conn.currentsong()? {
-            let current_song_in_queue = conn
-                .queue()?
-                .into_iter()
-                .filter(|song| song.file == current_song.file)
-                .collect::<Vec<Song>>();
-            if current_song_in_queue.len() != 1 {
-                return Err(anyhow!("No song currently playling"));
-            }
-            let current_song_in_queue = &current_song_in_queue[0];
-            let queue_place = current_song_in_queue.place.unwrap();
-            let seconds_to_skip_to = conn.status()?.time.unwrap().1.as_secs() * perc as u64 / 100;
-            conn.seek(queue_place.id, Duration::from_secs(seconds_to_skip_to))?;
-
-            println!("Seeked to {}%", perc);
+    fn seek(&self, target: SeekTarget) -> Result<Duration> {
+        let mut conn = Client::connect(self.address.as_str())?;
+        let status = conn.status()?;
+        if status.state == State::Stop || status.song.is_none() {
+            return Err(YapError::Player(
+                "Player is stopped, nothing to seek".to_string(),
+            ));
         }
-        Ok(())
+        let duration = match status.duration.or(status.time.map(|time| time.1)) {
+            Some(duration) => duration,
+            None => {
+                return Err(YapError::Player(
+                    "Current song has no known duration".to_string(),
+                ))
+            }
+        };
+        let position = target.resolve(status.elapsed.unwrap_or_default(), duration)?;
+        conn.rewind(position)?;
+        Ok(position)
     }
 
-    pub fn status(&self) -> anyhow::Result<Status> {
-        let mut conn = Client::connect("127.0.0.1:6600")?;
+    fn status(&self) -> Result<Status> {
+        let mut conn = Client::connect(self.address.as_str())?;
         let status = conn.status()?;
+        let file = match status.song {
+            Some(_) => conn.currentsong()?.map(|song| song.file),
+            None => None,
+        };
         let out = Status {
+            state: match status.state {
+                State::Play => "play",
+                State::Pause => "pause",
+                State::Stop => "stop",
+            }
+            .to_string(),
             random: status.random,
             repeat: status.repeat,
-            is_paused: match status.state {
-                State::Play => false,
-                _ => true,
-            },
+            single: status.single,
+            consume: status.consume,
+            is_paused: !matches!(status.state, State::Play),
+            volume: status.volume,
+            crossfade: status.crossfade.map(|c| c.as_secs()).unwrap_or(0),
+            replay_gain: status
+                .replaygain
+                .map(|mode| mode.to_string())
+                .unwrap_or("off".to_string()),
+            file,
+            position: status.song.map(|place| place.pos + 1),
+            queue_length: status.queue_len,
+            elapsed: status.elapsed.or(status.time.map(|time| time.0)),
+            duration: status.duration.or(status.time.map(|time| time.1)),
+            bitrate: status.bitrate,
+            audio_format: status
+                .audio
+                .map(|audio| format!("{}:{}:{}", audio.rate, audio.bits, audio.chans)),
         };
         Ok(out)
     }
 
-    pub fn queue(&self) -> anyhow::Result<Vec<String>> {
-        let mut conn = Client::connect("127.0.0.1:6600")?;
-        let songs = conn.queue()?.into_iter().collect::<Vec<Song>>();
-        let mut filenames = Vec::new();
-        for song in songs {
-            let index = song.file.chars().position(|c| c == '.').unwrap();
-            filenames.push(song.file[..index].to_string());
-        }
-        Ok(filenames)
+    fn queue(&self) -> Result<Vec<QueueEntry>> {
+        let mut conn = Client::connect(self.address.as_str())?;
+        let entries = conn
+            .queue()?
+            .into_iter()
+            .filter_map(|song| {
+                song.place.map(|place| QueueEntry {
+                    position: place.pos + 1,
+                    id: place.id.0,
+                    file: song.file,
+                    duration: song.duration,
+                    song: None,
+                })
+            })
+            .collect();
+        Ok(entries)
     }
 
-    pub fn remove_from_queue(&self, song_id: &str) -> anyhow::Result<()> {
-        let mut conn = Client::connect("127.0.0.1:6600")?;
-        let song_in_queue_pos = conn
+    fn remove_from_queue(&self, song_id: &str, all: bool) -> Result<usize> {
+        let mut conn = Client::connect(self.address.as_str())?;
+        let mut ids = conn
             .queue()?
             .into_iter()
-            .filter(|song| song.file == format!("{}.opus", song_id))
-            .collect::<Vec<Song>>();
-        if song_in_queue_pos.len() > 0 {
-            let position = song_in_queue_pos[0].place.unwrap().id;
-            conn.delete(position)?;
+            .filter(|song| song_id_from_file(&song.file) == song_id)
+            .filter_map(|song| song.place.map(|place| place.id))
+            .collect::<Vec<Id>>();
+        if !all {
+            ids.truncate(1);
+        }
+        for id in &ids {
+            conn.delete(*id)?;
+        }
+        Ok(ids.len())
+    }
+
+    fn snapshot(&self) -> Result<QueueSnapshot> {
+        let mut conn = Client::connect(self.address.as_str())?;
+        let status = conn.status()?;
+        let (files, ranges) = queue_ranges(&self.address)?.into_iter().unzip();
+        Ok(QueueSnapshot {
+            files,
+            ranges,
+            current: status.song.map(|place| place.pos),
+            elapsed: status.elapsed.unwrap_or_default(),
+            paused: !matches!(status.state, State::Play),
+        })
+    }
+
+    fn restore(&self, snapshot: &QueueSnapshot) -> Result<()> {
+        let mut conn = Client::connect(self.address.as_str())?;
+        conn.pause(true)?;
+        conn.clear()?;
+        for (index, file) in snapshot.files.iter().enumerate() {
+            let id = conn.push(Song {
+                file: file.clone(),
+                ..Default::default()
+            })?;
+            let range = snapshot.ranges.get(index).copied().unwrap_or_default();
+            set_range(&mut conn, id, range)?;
+        }
+        if let Some(current) = snapshot.current {
+            conn.seek(current, snapshot.elapsed)?;
+            if snapshot.paused {
+                conn.pause(true)?;
+            }
         }
         Ok(())
     }
 
-    pub fn shuffle_queue(&self) -> anyhow::Result<()> {
-        let mut conn = Client::connect("127.0.0.1:6600")?;
+    fn remove_position(&self, position: u32) -> Result<()> {
+        let mut conn = Client::connect(self.address.as_str())?;
+        let position = queue_index(&mut conn, position)?;
+        conn.delete(position)?;
+        Ok(())
+    }
+
+    fn remove_range(&self, start: u32, end: u32) -> Result<()> {
+        let mut conn = Client::connect(self.address.as_str())?;
+        let start = queue_index(&mut conn, start)?;
+        let end = queue_index(&mut conn, end)?;
+        if start > end {
+            return Err(YapError::Player("Range start is after its end".to_string()));
+        }
+        conn.delete(start..end + 1)?;
+        Ok(())
+    }
+
+    /**
+     * Removes every repeated song from the queue, keeping its first occurrence
+     */
+    fn dedupe_queue(&self) -> Result<usize> {
+        let mut conn = Client::connect(self.address.as_str())?;
+        let mut seen = Vec::new();
+        let mut duplicates = Vec::new();
+        for song in conn.queue()? {
+            if seen.contains(&song.file) {
+                if let Some(place) = song.place {
+                    duplicates.push(place.id);
+                }
+            } else {
+                seen.push(song.file);
+            }
+        }
+        for id in &duplicates {
+            conn.delete(*id)?;
+        }
+        Ok(duplicates.len())
+    }
+
+    fn shuffle_queue(&self) -> Result<()> {
+        let mut conn = Client::connect(self.address.as_str())?;
         conn.shuffle(..)?;
         Ok(())
     }
+
+    fn volume(&self) -> Result<i8> {
+        let mut conn = Client::connect(self.address.as_str())?;
+        let volume = conn.status()?.volume;
+        if volume < 0 {
+            return Err(YapError::Player(
+                "Volume control is not available".to_string(),
+            ));
+        }
+        Ok(volume)
+    }
+
+    fn set_volume(&self, volume: i8) -> Result<()> {
+        let mut conn = Client::connect(self.address.as_str())?;
+        conn.volume(volume.clamp(0, 100))?;
+        Ok(())
+    }
+
+    fn crossfade(&self, seconds: u64) -> Result<()> {
+        let mut conn = Client::connect(self.address.as_str())?;
+        conn.crossfade(seconds as i64)?;
+        Ok(())
+    }
+
+    fn mixramp(&self, db: Option<f32>, delay: Option<f64>) -> Result<(f32, Option<Duration>)> {
+        let mut conn = Client::connect(self.address.as_str())?;
+        if let Some(db) = db {
+            conn.mixrampdb(db)?;
+        }
+        if let Some(delay) = delay {
+            conn.mixrampdelay(delay)?;
+        }
+        let status = conn.status()?;
+        Ok((status.mixrampdb, status.mixrampdelay))
+    }
+
+    fn replay_gain(&self, mode: ReplayGain) -> Result<()> {
+        let mut conn = Client::connect(self.address.as_str())?;
+        conn.replaygain(mode)?;
+        Ok(())
+    }
+
+    fn playlist_directory(&self) -> Result<PathBuf> {
+        mpd_conf_setting("playlist_directory").ok_or(YapError::Config(
+            "Couldn't find playlist_directory in mpd.conf".to_string(),
+        ))
+    }
+
+    fn move_in_queue(&self, from: u32, to: u32) -> Result<()> {
+        let mut conn = Client::connect(self.address.as_str())?;
+        let from = queue_index(&mut conn, from)?;
+        let to = queue_index(&mut conn, to)?;
+        conn.shift(from, to as usize)?;
+        Ok(())
+    }
+
+    fn swap_in_queue(&self, first: u32, second: u32) -> Result<()> {
+        let mut conn = Client::connect(self.address.as_str())?;
+        let first = queue_index(&mut conn, first)?;
+        let second = queue_index(&mut conn, second)?;
+        conn.swap(first, second)?;
+        Ok(())
+    }
+
+    fn play_position(&self, position: u32) -> Result<()> {
+        let mut conn = Client::connect(self.address.as_str())?;
+        let position = queue_index(&mut conn, position)?;
+        conn.switch(position)?;
+        Ok(())
+    }
+
+    fn consume(&self) -> Result<bool> {
+        let mut conn = Client::connect(self.address.as_str())?;
+        let consume = !conn.status()?.consume;
+        conn.consume(consume)?;
+        Ok(consume)
+    }
+
+    fn single(&self) -> Result<bool> {
+        let mut conn = Client::connect(self.address.as_str())?;
+        let single = !conn.status()?.single;
+        conn.single(single)?;
+        Ok(single)
+    }
+}
+
+/**
+ * Converts a 1-based queue position given by the user into mpd's 0-based index
+ */
+fn queue_index(conn: &mut Client, position: u32) -> Result<u32> {
+    let queue_len = conn.status()?.queue_len;
+    if position == 0 || position > queue_len {
+        return Err(YapError::NotFound(format!(
+            "Position {} is out of the queue (1-{})",
+            position, queue_len
+        )));
+    }
+    Ok(position - 1)
+}
+
+/**
+ * Limits a queued song to its range, mpd is only given whole seconds
+ */
+/**
+ * File and range of each queued song. The mpd crate reads Range as whole seconds
+ * and drops the fractional values mpd sends, so playlistinfo is read directly
+ */
+fn queue_ranges(address: &str) -> Result<Vec<(String, PlayRange)>> {
+    let mut stream = TcpStream::connect(address)?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    if !line.starts_with("OK MPD") {
+        return Err(YapError::Player(format!(
+            "Unexpected greeting from mpd: {}",
+            line.trim_end()
+        )));
+    }
+    stream.write_all(b"playlistinfo\n")?;
+    let mut songs: Vec<(String, PlayRange)> = Vec::new();
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            return Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof).into());
+        }
+        let line = line.trim_end();
+        if line == "OK" {
+            return Ok(songs);
+        }
+        if let Some(error) = line.strip_prefix("ACK ") {
+            return Err(YapError::Player(error.to_string()));
+        }
+        match line.split_once(": ") {
+            Some(("file", file)) => songs.push((file.to_string(), PlayRange::default())),
+            Some(("Range", range)) => {
+                if let Some((_, last)) = songs.last_mut() {
+                    *last = parse_range(range);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/**
+ * Parses a range written by mpd as start-end in seconds, the end is missing when
+ * the song plays to its end
+ */
+fn parse_range(range: &str) -> PlayRange {
+    let (start, end) = range.split_once('-').unwrap_or((range, ""));
+    PlayRange {
+        start: start.parse::<f64>().ok().filter(|start| *start > 0.),
+        end: end.parse::<f64>().ok(),
+    }
+}
+
+fn set_range(conn: &mut Client, id: Id, range: PlayRange) -> Result<()> {
+    if range.is_full() {
+        return Ok(());
+    }
+    conn.range(
+        id,
+        mpd::song::Range(
+            Duration::from_secs_f64(range.start.unwrap_or(0.)),
+            range.end.map(Duration::from_secs_f64),
+        ),
+    )?;
+    Ok(())
 }
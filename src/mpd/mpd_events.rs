@@ -0,0 +1,109 @@
+use mpd::{idle::Subsystem, Client, Idle, State, Status};
+
+use crate::error::Result;
+use crate::player::{
+    backend::song_id_from_file,
+    events::{EventSource, PlayerEvent},
+};
+
+/**
+ * Keeps an idle connection open to mpd and turns the changed subsystems
+ * into typed events by comparing the status before and after each change
+ */
+pub struct EventStream {
+    conn: Client,
+    last_status: Status,
+    last_song_id: Option<String>,
+}
+
+impl EventStream {
+    pub fn new(address: &str) -> Result<Self> {
+        let mut conn = Client::connect(address)?;
+        let last_status = conn.status()?;
+        let last_song_id = current_song_id(&mut conn)?;
+        Ok(Self {
+            conn,
+            last_status,
+            last_song_id,
+        })
+    }
+}
+
+impl EventSource for EventStream {
+    fn current_song_id(&self) -> Option<String> {
+        self.last_song_id.clone()
+    }
+
+    /**
+     * Blocks until mpd reports a change, then returns the events it caused
+     */
+    fn next_events(&mut self) -> Result<Vec<PlayerEvent>> {
+        let subsystems = self.conn.wait(&[
+            Subsystem::Player,
+            Subsystem::Queue,
+            Subsystem::Options,
+            Subsystem::Database,
+        ])?;
+        let status = self.conn.status()?;
+        let song_id = current_song_id(&mut self.conn)?;
+        let events = subsystem_events(
+            &subsystems,
+            (&self.last_status, &self.last_song_id),
+            (&status, &song_id),
+        );
+
+        self.last_status = status;
+        self.last_song_id = song_id;
+        Ok(events)
+    }
+}
+
+/**
+ * Events caused by the changed subsystems, given the status before and after the change
+ */
+pub fn subsystem_events(
+    subsystems: &[Subsystem],
+    (last_status, last_song_id): (&Status, &Option<String>),
+    (status, song_id): (&Status, &Option<String>),
+) -> Vec<PlayerEvent> {
+    let mut events = Vec::new();
+
+    for subsystem in subsystems {
+        match subsystem {
+            Subsystem::Player => {
+                if song_id != last_song_id {
+                    events.push(PlayerEvent::SongChanged {
+                        song_id: song_id.clone(),
+                    });
+                }
+                if is_paused(status) != is_paused(last_status) {
+                    events.push(PlayerEvent::Paused {
+                        paused: is_paused(status),
+                    });
+                }
+            }
+            Subsystem::Queue => events.push(PlayerEvent::QueueChanged {
+                length: status.queue_len,
+            }),
+            Subsystem::Options => events.push(PlayerEvent::OptionsChanged {
+                repeat: status.repeat,
+                random: status.random,
+                single: status.single,
+                consume: status.consume,
+            }),
+            Subsystem::Database => events.push(PlayerEvent::DatabaseUpdated),
+            _ => {}
+        }
+    }
+    events
+}
+
+fn is_paused(status: &Status) -> bool {
+    !matches!(status.state, State::Play)
+}
+
+fn current_song_id(conn: &mut Client) -> Result<Option<String>> {
+    Ok(conn
+        .currentsong()?
+        .map(|song| song_id_from_file(&song.file)))
+}
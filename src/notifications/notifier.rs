@@ -0,0 +1,64 @@
+use std::path::PathBuf;
+
+use notify_rust::{Notification, Timeout};
+
+use crate::{
+    config::Notifications,
+    db::models::Song,
+    error::{Result, YapError},
+};
+
+/**
+ * Tells the user about what the player does, while yap watch runs
+ */
+pub trait Notifier {
+    fn is_enabled(&self) -> bool;
+
+    /**
+     * Notifies the song that just started playing
+     */
+    fn song_changed(&self, song: &Song) -> Result<()>;
+}
+
+/**
+ * Sends freedesktop notifications through the session bus
+ */
+pub struct DesktopNotifier {
+    config: Notifications,
+    miniature_directory: PathBuf,
+}
+
+impl DesktopNotifier {
+    pub fn new(config: Notifications, miniature_directory: PathBuf) -> Self {
+        Self {
+            config,
+            miniature_directory,
+        }
+    }
+}
+
+impl Notifier for DesktopNotifier {
+    fn is_enabled(&self) -> bool {
+        self.config.enabled
+    }
+
+    //Shows the miniature of the song if one was downloaded
+    fn song_changed(&self, song: &Song) -> Result<()> {
+        let mut notification = Notification::new();
+        notification
+            .appname("yap")
+            .summary(&song.name)
+            .body(song.artist.as_deref().unwrap_or("Unknown"))
+            .timeout(Timeout::Milliseconds(self.config.timeout));
+
+        let miniature = self.miniature_directory.join(format!("{}.jpg", song.name));
+        if self.config.show_miniature && miniature.exists() {
+            notification.image_path(&miniature.to_string_lossy());
+        }
+
+        notification
+            .show()
+            .map_err(|error| YapError::Io(std::io::Error::other(error)))?;
+        Ok(())
+    }
+}
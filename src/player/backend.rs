@@ -0,0 +1,211 @@
+use std::{
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::Duration,
+};
+
+use mpd::status::ReplayGain;
+
+use super::events::EventSource;
+use crate::{
+    db::models,
+    error::{Result, YapError},
+    utils::utils::{format_duration, parse_duration},
+};
+
+/**
+ * Operations every playback backend has to provide to the handler
+ */
+pub trait Player {
+    fn events(&self) -> Result<Box<dyn EventSource>>;
+    fn update_db(&self) -> Result<()>;
+    fn add_to_queue(&self, song_id: &str, range: PlayRange) -> Result<()>;
+    fn insert_next(&self, song_id: &str, range: PlayRange) -> Result<()>;
+    fn play(&self) -> Result<()>;
+    fn pause(&self, state: Option<bool>) -> Result<bool>;
+    fn shuffle(&self, state: Option<bool>) -> Result<bool>;
+    fn clear_queue(&self) -> Result<()>;
+    fn next(&self) -> Result<()>;
+    fn previous(&self) -> Result<()>;
+    fn repeat(&self) -> Result<bool>;
+    fn seek(&self, target: SeekTarget) -> Result<Duration>;
+    fn status(&self) -> Result<Status>;
+    fn queue(&self) -> Result<Vec<QueueEntry>>;
+    fn remove_from_queue(&self, song_id: &str, all: bool) -> Result<usize>;
+    fn snapshot(&self) -> Result<QueueSnapshot>;
+    fn restore(&self, snapshot: &QueueSnapshot) -> Result<()>;
+    fn remove_position(&self, position: u32) -> Result<()>;
+    fn remove_range(&self, start: u32, end: u32) -> Result<()>;
+    fn dedupe_queue(&self) -> Result<usize>;
+    fn shuffle_queue(&self) -> Result<()>;
+    fn volume(&self) -> Result<i8>;
+    fn set_volume(&self, volume: i8) -> Result<()>;
+    fn crossfade(&self, seconds: u64) -> Result<()>;
+    fn mixramp(&self, db: Option<f32>, delay: Option<f64>) -> Result<(f32, Option<Duration>)>;
+    fn replay_gain(&self, mode: ReplayGain) -> Result<()>;
+    fn move_in_queue(&self, from: u32, to: u32) -> Result<()>;
+    fn swap_in_queue(&self, first: u32, second: u32) -> Result<()>;
+    fn play_position(&self, position: u32) -> Result<()>;
+    fn consume(&self) -> Result<bool>;
+    fn single(&self) -> Result<bool>;
+
+    /**
+     * Plays the queue until stopped, only needed by backends running in-process
+     */
+    fn run(&self) -> Result<()> {
+        Err(YapError::Player(
+            "This player doesn't need to be run by yap".to_string(),
+        ))
+    }
+
+    /**
+     * Directory the player loads saved playlists from, if it has one
+     */
+    fn playlist_directory(&self) -> Result<PathBuf> {
+        Err(YapError::Player(
+            "This player has no playlist directory".to_string(),
+        ))
+    }
+}
+
+pub struct Status {
+    pub state: String,
+    pub repeat: bool,
+    pub random: bool,
+    pub single: bool,
+    pub consume: bool,
+    pub is_paused: bool,
+    pub volume: i8,
+    pub crossfade: u64,
+    pub replay_gain: String,
+    //File of the current song and its 1-based position in the queue
+    pub file: Option<String>,
+    pub position: Option<u32>,
+    pub queue_length: u32,
+    pub elapsed: Option<Duration>,
+    pub duration: Option<Duration>,
+    pub bitrate: Option<u32>,
+    //Audio format as samplerate:bits:channels
+    pub audio_format: Option<String>,
+}
+
+/**
+ * A song in the queue, with the matching song of the database if there is one
+ */
+#[derive(Debug)]
+pub struct QueueEntry {
+    //1-based position in the queue
+    pub position: u32,
+    //Id assigned by the player, stable while the song stays in the queue
+    pub id: u32,
+    pub file: String,
+    pub duration: Option<Duration>,
+    pub song: Option<models::Song>,
+}
+
+impl QueueEntry {
+    pub fn song_id(&self) -> String {
+        song_id_from_file(&self.file)
+    }
+}
+
+/**
+ * Part of a song to play, in seconds from the start of its file
+ */
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct PlayRange {
+    pub start: Option<f64>,
+    pub end: Option<f64>,
+}
+
+impl PlayRange {
+    pub fn of(song: &models::Song) -> Self {
+        Self {
+            start: song.start_offset,
+            end: song.end_offset,
+        }
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.start.is_none() && self.end.is_none()
+    }
+}
+
+/**
+ * State of the queue saved before it gets cleared
+ */
+pub struct QueueSnapshot {
+    pub files: Vec<String>,
+    //Range of each file, songs are restored whole when it's empty
+    pub ranges: Vec<PlayRange>,
+    //0-based position of the song that was playing
+    pub current: Option<u32>,
+    pub elapsed: Duration,
+    pub paused: bool,
+}
+
+/**
+ * Position to seek to, parsed from 1:23, +10s, -30s or 50%
+ */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SeekTarget {
+    Absolute(Duration),
+    Forward(Duration),
+    Backward(Duration),
+    Percentage(u8),
+}
+
+impl FromStr for SeekTarget {
+    type Err = String;
+
+    fn from_str(value: &str) -> std::result::Result<Self, Self::Err> {
+        let value = value.trim();
+        if let Some(perc) = value.strip_suffix('%') {
+            return match perc.parse::<u8>() {
+                Ok(perc) if perc <= 100 => Ok(SeekTarget::Percentage(perc)),
+                _ => Err(format!("Invalid percentage {}", value)),
+            };
+        }
+        let parse = |time: &str| parse_duration(time).map_err(|err| err.to_string());
+        if let Some(offset) = value.strip_prefix('+') {
+            Ok(SeekTarget::Forward(parse(offset)?))
+        } else if let Some(offset) = value.strip_prefix('-') {
+            Ok(SeekTarget::Backward(parse(offset)?))
+        } else {
+            Ok(SeekTarget::Absolute(parse(value)?))
+        }
+    }
+}
+
+impl SeekTarget {
+    /**
+     * Gets the position in the song to seek to, given where playback is now
+     */
+    pub fn resolve(&self, elapsed: Duration, duration: Duration) -> Result<Duration> {
+        Ok(match *self {
+            SeekTarget::Absolute(position) => {
+                if position > duration {
+                    return Err(YapError::Player(format!(
+                        "Position {} is after the end of the song ({})",
+                        format_duration(position),
+                        format_duration(duration)
+                    )));
+                }
+                position
+            }
+            SeekTarget::Percentage(perc) => duration.mul_f64(perc as f64 / 100.),
+            SeekTarget::Forward(offset) => (elapsed + offset).min(duration),
+            SeekTarget::Backward(offset) => elapsed.saturating_sub(offset),
+        })
+    }
+}
+
+/**
+ * Gets the id of a song from the name of its file in the music directory
+ */
+pub fn song_id_from_file(file: &str) -> String {
+    Path::new(file)
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or(file.to_string())
+}
@@ -0,0 +1,43 @@
+use serde::Serialize;
+
+use crate::db::models::Song;
+use crate::error::Result;
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum PlayerEvent {
+    SongChanged {
+        song_id: Option<String>,
+    },
+    Paused {
+        paused: bool,
+    },
+    QueueChanged {
+        length: u32,
+    },
+    OptionsChanged {
+        repeat: bool,
+        random: bool,
+        single: bool,
+        consume: bool,
+    },
+    DatabaseUpdated,
+}
+
+/**
+ * Event enriched with the metadata of the song currently loaded in the player
+ */
+#[derive(Serialize, Debug)]
+pub struct WatchEvent {
+    #[serde(flatten)]
+    pub event: PlayerEvent,
+    pub song: Option<Song>,
+}
+
+/**
+ * Source of player events, blocking until something changes
+ */
+pub trait EventSource {
+    fn next_events(&mut self) -> Result<Vec<PlayerEvent>>;
+    fn current_song_id(&self) -> Option<String>;
+}
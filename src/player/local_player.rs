@@ -0,0 +1,785 @@
+use std::{
+    io::Read,
+    path::PathBuf,
+    process::{Child, Command, Stdio},
+    thread,
+    time::Duration,
+};
+
+use mpd::status::ReplayGain;
+use rand::{seq::SliceRandom, Rng};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    backend::{
+        song_id_from_file, PlayRange, Player, QueueEntry, QueueSnapshot, SeekTarget, Status,
+    },
+    events::{EventSource, PlayerEvent},
+    sink::{open_sink, CHANNELS, SAMPLE_RATE},
+};
+use crate::{
+    config,
+    db::database::Database,
+    error::{Result, YapError},
+    utils::utils::probe_duration,
+};
+
+//Key of the setting holding the state shared by every yap process
+const STATE_KEY: &str = "local_player_state";
+//Samples decoded between two checks of the shared state, 100ms of audio
+const CHUNK_SAMPLES: usize = (SAMPLE_RATE as usize / 10) * CHANNELS as usize;
+
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+struct LocalEntry {
+    id: u32,
+    file: String,
+    duration: Option<f64>,
+    //Part of the file to play, in seconds
+    #[serde(default)]
+    start: Option<f64>,
+    #[serde(default)]
+    end: Option<f64>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+enum PlaybackState {
+    Play,
+    Pause,
+    Stop,
+}
+
+impl PlaybackState {
+    fn as_str(&self) -> &'static str {
+        match self {
+            PlaybackState::Play => "play",
+            PlaybackState::Pause => "pause",
+            PlaybackState::Stop => "stop",
+        }
+    }
+}
+
+/**
+ * Queue and playback state, saved in the database so that commands
+ * can drive the process running the player
+ */
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+struct LocalState {
+    //Increased when the song, its position or the playback state change,
+    //the player restarts decoding when it does
+    version: u64,
+    queue: Vec<LocalEntry>,
+    next_id: u32,
+    current: Option<usize>,
+    state: PlaybackState,
+    elapsed_ms: u64,
+    volume: i8,
+    repeat: bool,
+    random: bool,
+    single: bool,
+    consume: bool,
+}
+
+impl Default for LocalState {
+    fn default() -> Self {
+        Self {
+            version: 0,
+            queue: Vec::new(),
+            next_id: 1,
+            current: None,
+            state: PlaybackState::Stop,
+            elapsed_ms: 0,
+            volume: 100,
+            repeat: false,
+            random: false,
+            single: false,
+            consume: false,
+        }
+    }
+}
+
+impl LocalState {
+    fn push(&mut self, file: String, duration: Option<f64>, range: PlayRange, position: usize) {
+        self.queue.insert(
+            position,
+            LocalEntry {
+                id: self.next_id,
+                file,
+                duration,
+                start: range.start,
+                end: range.end,
+            },
+        );
+        self.next_id += 1;
+        if let Some(current) = self.current {
+            if position <= current {
+                self.current = Some(current + 1);
+            }
+        }
+    }
+
+    fn remove(&mut self, index: usize) {
+        self.queue.remove(index);
+        self.current = match self.current {
+            Some(current) if current == index => {
+                self.elapsed_ms = 0;
+                if index < self.queue.len() {
+                    Some(index)
+                } else {
+                    self.state = PlaybackState::Stop;
+                    None
+                }
+            }
+            Some(current) if current > index => Some(current - 1),
+            current => current,
+        };
+    }
+
+    /**
+     * Moves to the following song, following the repeat, random, single and consume modes
+     */
+    fn advance(&mut self, manual: bool) {
+        let current = match self.current {
+            Some(current) => current,
+            None => return,
+        };
+        self.elapsed_ms = 0;
+        if self.single && !manual {
+            if !self.repeat {
+                self.state = PlaybackState::Stop;
+            }
+            return;
+        }
+        let next = if self.consume {
+            self.queue.remove(current);
+            current
+        } else {
+            current + 1
+        };
+        let next = if self.random && self.queue.len() > 1 {
+            rand::thread_rng().gen_range(0..self.queue.len())
+        } else {
+            next
+        };
+        if next < self.queue.len() {
+            self.current = Some(next);
+        } else if self.repeat && !self.queue.is_empty() {
+            self.current = Some(0);
+        } else {
+            self.current = None;
+            self.state = PlaybackState::Stop;
+        }
+    }
+
+    fn current_entry(&self) -> Option<&LocalEntry> {
+        self.current.and_then(|current| self.queue.get(current))
+    }
+
+    //What the decoder depends on, volume and modes are applied without restarting it
+    fn playback(&self) -> (Option<LocalEntry>, PlaybackState, u64) {
+        (self.current_entry().cloned(), self.state, self.elapsed_ms)
+    }
+
+    fn index(&self, position: u32) -> Result<usize> {
+        if position == 0 || position as usize > self.queue.len() {
+            return Err(YapError::NotFound(format!(
+                "Position {} is out of the queue (1-{})",
+                position,
+                self.queue.len()
+            )));
+        }
+        Ok(position as usize - 1)
+    }
+}
+
+/**
+ * Player decoding files with an ffmpeg process and writing the samples to a sink
+ */
+#[derive(Clone)]
+pub struct LocalPlayer {
+    database: Database,
+    music_directory: PathBuf,
+    config: config::Player,
+}
+
+impl LocalPlayer {
+    pub fn new(database: Database, music_directory: PathBuf, config: config::Player) -> Self {
+        Self {
+            database,
+            music_directory,
+            config,
+        }
+    }
+
+    fn load(&self) -> Result<(Option<String>, LocalState)> {
+        match self.database.get_setting(STATE_KEY)? {
+            Some(raw) => {
+                let state = serde_json::from_str(&raw).map_err(YapError::db)?;
+                Ok((Some(raw), state))
+            }
+            None => Ok((None, LocalState::default())),
+        }
+    }
+
+    /**
+     * Applies a change to the shared state, retrying if another process changed it meanwhile
+     */
+    fn update<T>(&self, change: impl Fn(&mut LocalState) -> Result<T>) -> Result<T> {
+        loop {
+            let (raw, mut state) = self.load()?;
+            let playback = state.playback();
+            let result = change(&mut state)?;
+            if state.playback() != playback {
+                state.version += 1;
+            }
+            if self.database.compare_and_set_setting(
+                STATE_KEY,
+                raw.as_deref(),
+                &serde_json::to_string(&state).map_err(YapError::db)?,
+            )? {
+                return Ok(result);
+            }
+        }
+    }
+
+    fn path(&self, file: &str) -> PathBuf {
+        self.music_directory.join(file)
+    }
+
+    //Stored when the song was downloaded, probed for songs downloaded before durations were
+    fn duration(&self, file: &str) -> Option<f64> {
+        let stored = self
+            .database
+            .get_song_by_id(&song_id_from_file(file))
+            .ok()
+            .flatten()
+            .and_then(|song| song.duration);
+        stored.or_else(|| probe_duration(&self.path(file)))
+    }
+
+    fn decode(&self, file: &str, start: Duration) -> Result<Child> {
+        Ok(Command::new("ffmpeg")
+            .args(["-v", "quiet", "-ss", &start.as_secs_f64().to_string(), "-i"])
+            .arg(self.path(file))
+            .args(["-f", "s16le", "-ac", &CHANNELS.to_string()])
+            .args(["-ar", &SAMPLE_RATE.to_string(), "-"])
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .spawn()?)
+    }
+}
+
+/**
+ * Reads a full chunk from the decoder, returns fewer samples only at the end of the song
+ */
+fn read_chunk(decoder: &mut Child, samples: &mut Vec<i16>) -> Result<()> {
+    let stdout = match decoder.stdout.as_mut() {
+        Some(stdout) => stdout,
+        None => return Err(YapError::Player("Couldn't read from decoder".to_string())),
+    };
+    let mut buffer = vec![0u8; CHUNK_SAMPLES * 2];
+    let mut read = 0;
+    while read < buffer.len() {
+        match stdout.read(&mut buffer[read..])? {
+            0 => break,
+            bytes => read += bytes,
+        }
+    }
+    samples.clear();
+    samples.extend(
+        buffer[..read - read % 2]
+            .chunks_exact(2)
+            .map(|bytes| i16::from_le_bytes([bytes[0], bytes[1]])),
+    );
+    Ok(())
+}
+
+fn stop_decoder(decoder: &mut Option<(u64, Child)>) {
+    if let Some((_, mut child)) = decoder.take() {
+        let _ = child.kill();
+        let _ = child.wait();
+    }
+}
+
+impl Player for LocalPlayer {
+    fn events(&self) -> Result<Box<dyn EventSource>> {
+        let (_, last) = self.load()?;
+        Ok(Box::new(LocalEvents {
+            player: self.clone(),
+            last,
+        }))
+    }
+
+    fn update_db(&self) -> Result<()> {
+        Ok(())
+    }
+
+    fn add_to_queue(&self, song_id: &str, range: PlayRange) -> Result<()> {
+        let file = format!("{}.opus", song_id);
+        let duration = self.duration(&file);
+        self.update(|state| {
+            let position = state.queue.len();
+            state.push(file.clone(), duration, range, position);
+            Ok(())
+        })
+    }
+
+    fn insert_next(&self, song_id: &str, range: PlayRange) -> Result<()> {
+        let file = format!("{}.opus", song_id);
+        let duration = self.duration(&file);
+        self.update(|state| {
+            let position = state.current.map_or(0, |current| current + 1);
+            state.push(file.clone(), duration, range, position);
+            Ok(())
+        })
+    }
+
+    fn play(&self) -> Result<()> {
+        self.update(|state| {
+            if state.queue.is_empty() {
+                return Ok(());
+            }
+            if state.current.is_none() {
+                state.current = Some(0);
+                state.elapsed_ms = 0;
+            }
+            state.state = PlaybackState::Play;
+            Ok(())
+        })
+    }
+
+    fn pause(&self, pause: Option<bool>) -> Result<bool> {
+        self.update(|state| {
+            if state.state == PlaybackState::Stop {
+                return Ok(true);
+            }
+            let paused = pause.unwrap_or(state.state == PlaybackState::Play);
+            state.state = match paused {
+                true => PlaybackState::Pause,
+                false => PlaybackState::Play,
+            };
+            Ok(paused)
+        })
+    }
+
+    fn shuffle(&self, random: Option<bool>) -> Result<bool> {
+        self.update(|state| {
+            state.random = random.unwrap_or(!state.random);
+            Ok(state.random)
+        })
+    }
+
+    fn clear_queue(&self) -> Result<()> {
+        self.update(|state| {
+            state.queue.clear();
+            state.current = None;
+            state.elapsed_ms = 0;
+            state.state = PlaybackState::Stop;
+            Ok(())
+        })
+    }
+
+    fn next(&self) -> Result<()> {
+        self.update(|state| {
+            state.advance(true);
+            Ok(())
+        })
+    }
+
+    fn previous(&self) -> Result<()> {
+        self.update(|state| {
+            if let Some(current) = state.current {
+                state.current = Some(current.saturating_sub(1));
+                state.elapsed_ms = 0;
+            }
+            Ok(())
+        })
+    }
+
+    fn repeat(&self) -> Result<bool> {
+        self.update(|state| {
+            state.repeat = !state.repeat;
+            Ok(state.repeat)
+        })
+    }
+
+    fn seek(&self, target: SeekTarget) -> Result<Duration> {
+        self.update(|state| {
+            if state.state == PlaybackState::Stop {
+                return Err(YapError::Player(
+                    "Player is stopped, nothing to seek".to_string(),
+                ));
+            }
+            let duration = match state.current_entry().and_then(|entry| entry.duration) {
+                Some(duration) => Duration::from_secs_f64(duration),
+                None => {
+                    return Err(YapError::Player(
+                        "Current song has no known duration".to_string(),
+                    ))
+                }
+            };
+            let position = target.resolve(Duration::from_millis(state.elapsed_ms), duration)?;
+            state.elapsed_ms = position.as_millis() as u64;
+            Ok(position)
+        })
+    }
+
+    fn status(&self) -> Result<Status> {
+        let (_, state) = self.load()?;
+        let entry = state.current_entry();
+        Ok(Status {
+            state: state.state.as_str().to_string(),
+            repeat: state.repeat,
+            random: state.random,
+            single: state.single,
+            consume: state.consume,
+            is_paused: state.state != PlaybackState::Play,
+            volume: state.volume,
+            crossfade: 0,
+            replay_gain: "off".to_string(),
+            file: entry.map(|entry| entry.file.clone()),
+            position: state.current.map(|current| current as u32 + 1),
+            queue_length: state.queue.len() as u32,
+            elapsed: entry.map(|_| Duration::from_millis(state.elapsed_ms)),
+            duration: entry
+                .and_then(|entry| entry.duration)
+                .map(Duration::from_secs_f64),
+            bitrate: None,
+            audio_format: entry.map(|_| format!("{}:16:{}", SAMPLE_RATE, CHANNELS)),
+        })
+    }
+
+    fn queue(&self) -> Result<Vec<QueueEntry>> {
+        let (_, state) = self.load()?;
+        Ok(state
+            .queue
+            .into_iter()
+            .enumerate()
+            .map(|(index, entry)| QueueEntry {
+                position: index as u32 + 1,
+                id: entry.id,
+                file: entry.file,
+                duration: entry.duration.map(Duration::from_secs_f64),
+                song: None,
+            })
+            .collect())
+    }
+
+    fn remove_from_queue(&self, song_id: &str, all: bool) -> Result<usize> {
+        self.update(|state| {
+            let mut removed = 0;
+            while let Some(index) = state
+                .queue
+                .iter()
+                .position(|entry| song_id_from_file(&entry.file) == song_id)
+            {
+                state.remove(index);
+                removed += 1;
+                if !all {
+                    break;
+                }
+            }
+            Ok(removed)
+        })
+    }
+
+    fn snapshot(&self) -> Result<QueueSnapshot> {
+        let (_, state) = self.load()?;
+        Ok(QueueSnapshot {
+            files: state.queue.iter().map(|entry| entry.file.clone()).collect(),
+            ranges: state
+                .queue
+                .iter()
+                .map(|entry| PlayRange {
+                    start: entry.start,
+                    end: entry.end,
+                })
+                .collect(),
+            current: state.current.map(|current| current as u32),
+            elapsed: Duration::from_millis(state.elapsed_ms),
+            paused: state.state != PlaybackState::Play,
+        })
+    }
+
+    fn restore(&self, snapshot: &QueueSnapshot) -> Result<()> {
+        let durations = snapshot
+            .files
+            .iter()
+            .map(|file| self.duration(file))
+            .collect::<Vec<Option<f64>>>();
+        self.update(|state| {
+            state.queue.clear();
+            state.current = None;
+            for (index, (file, duration)) in snapshot.files.iter().zip(&durations).enumerate() {
+                let position = state.queue.len();
+                let range = snapshot.ranges.get(index).copied().unwrap_or_default();
+                state.push(file.clone(), *duration, range, position);
+            }
+            state.current = snapshot
+                .current
+                .map(|current| current as usize)
+                .filter(|current| *current < state.queue.len());
+            state.elapsed_ms = snapshot.elapsed.as_millis() as u64;
+            state.state = match (state.current, snapshot.paused) {
+                (None, _) => PlaybackState::Stop,
+                (Some(_), true) => PlaybackState::Pause,
+                (Some(_), false) => PlaybackState::Play,
+            };
+            Ok(())
+        })
+    }
+
+    fn remove_position(&self, position: u32) -> Result<()> {
+        self.update(|state| {
+            let index = state.index(position)?;
+            state.remove(index);
+            Ok(())
+        })
+    }
+
+    fn remove_range(&self, start: u32, end: u32) -> Result<()> {
+        self.update(|state| {
+            let start = state.index(start)?;
+            let end = state.index(end)?;
+            if start > end {
+                return Err(YapError::Player("Range start is after its end".to_string()));
+            }
+            for index in (start..=end).rev() {
+                state.remove(index);
+            }
+            Ok(())
+        })
+    }
+
+    fn dedupe_queue(&self) -> Result<usize> {
+        self.update(|state| {
+            let mut seen = Vec::new();
+            let mut removed = 0;
+            let mut index = 0;
+            while index < state.queue.len() {
+                if seen.contains(&state.queue[index].file) {
+                    state.remove(index);
+                    removed += 1;
+                } else {
+                    seen.push(state.queue[index].file.clone());
+                    index += 1;
+                }
+            }
+            Ok(removed)
+        })
+    }
+
+    fn shuffle_queue(&self) -> Result<()> {
+        self.update(|state| {
+            let current = state.current_entry().map(|entry| entry.id);
+            state.queue.shuffle(&mut rand::thread_rng());
+            state.current = current.and_then(|id| state.queue.iter().position(|e| e.id == id));
+            Ok(())
+        })
+    }
+
+    fn volume(&self) -> Result<i8> {
+        Ok(self.load()?.1.volume)
+    }
+
+    fn set_volume(&self, volume: i8) -> Result<()> {
+        self.update(|state| {
+            state.volume = volume.clamp(0, 100);
+            Ok(())
+        })
+    }
+
+    fn crossfade(&self, _seconds: u64) -> Result<()> {
+        Err(YapError::Player(
+            "Crossfade is not supported by the local player".to_string(),
+        ))
+    }
+
+    fn mixramp(&self, _db: Option<f32>, _delay: Option<f64>) -> Result<(f32, Option<Duration>)> {
+        Err(YapError::Player(
+            "Mixramp is not supported by the local player".to_string(),
+        ))
+    }
+
+    fn replay_gain(&self, _mode: ReplayGain) -> Result<()> {
+        Err(YapError::Player(
+            "Replay gain is not supported by the local player".to_string(),
+        ))
+    }
+
+    fn move_in_queue(&self, from: u32, to: u32) -> Result<()> {
+        self.update(|state| {
+            let from = state.index(from)?;
+            let to = state.index(to)?;
+            let current = state.current_entry().map(|entry| entry.id);
+            let entry = state.queue.remove(from);
+            state.queue.insert(to, entry);
+            state.current = current.and_then(|id| state.queue.iter().position(|e| e.id == id));
+            Ok(())
+        })
+    }
+
+    fn swap_in_queue(&self, first: u32, second: u32) -> Result<()> {
+        self.update(|state| {
+            let first = state.index(first)?;
+            let second = state.index(second)?;
+            let current = state.current_entry().map(|entry| entry.id);
+            state.queue.swap(first, second);
+            state.current = current.and_then(|id| state.queue.iter().position(|e| e.id == id));
+            Ok(())
+        })
+    }
+
+    fn play_position(&self, position: u32) -> Result<()> {
+        self.update(|state| {
+            state.current = Some(state.index(position)?);
+            state.elapsed_ms = 0;
+            state.state = PlaybackState::Play;
+            Ok(())
+        })
+    }
+
+    fn consume(&self) -> Result<bool> {
+        self.update(|state| {
+            state.consume = !state.consume;
+            Ok(state.consume)
+        })
+    }
+
+    fn single(&self) -> Result<bool> {
+        self.update(|state| {
+            state.single = !state.single;
+            Ok(state.single)
+        })
+    }
+
+    /**
+     * Plays the shared queue, reacting to the changes made by other yap commands
+     */
+    fn run(&self) -> Result<()> {
+        let mut sink = open_sink(&self.config)?;
+        let mut decoder: Option<(u64, Child)> = None;
+        let mut samples = Vec::with_capacity(CHUNK_SAMPLES);
+        loop {
+            let (mut raw, mut state) = self.load()?;
+            let (file, start, end) = match (state.state, state.current_entry()) {
+                (PlaybackState::Play, Some(entry)) => (entry.file.clone(), entry.start, entry.end),
+                _ => {
+                    stop_decoder(&mut decoder);
+                    thread::sleep(Duration::from_millis(100));
+                    continue;
+                }
+            };
+
+            //Trimmed songs start at their offset, even when seeking before it
+            if let Some(start) = start {
+                state.elapsed_ms = state.elapsed_ms.max((start * 1000.) as u64);
+            }
+            if decoder.as_ref().map(|(version, _)| *version) != Some(state.version) {
+                stop_decoder(&mut decoder);
+                let start = Duration::from_millis(state.elapsed_ms);
+                decoder = Some((state.version, self.decode(&file, start)?));
+            }
+            if let Some((_, child)) = decoder.as_mut() {
+                read_chunk(child, &mut samples)?;
+            }
+
+            let past_end = end.is_some_and(|end| state.elapsed_ms as f64 >= end * 1000.);
+            if samples.is_empty() || past_end {
+                stop_decoder(&mut decoder);
+                let version = state.version;
+                self.update(|state| {
+                    if state.version == version {
+                        state.advance(false);
+                    }
+                    Ok(())
+                })?;
+                continue;
+            }
+
+            for sample in samples.iter_mut() {
+                *sample = (*sample as i32 * state.volume as i32 / 100) as i16;
+            }
+            sink.write(&samples)?;
+
+            //Progress doesn't change the version, so the decoder keeps going
+            let played = samples.len() as u64 * 1000 / (SAMPLE_RATE as u64 * CHANNELS as u64);
+            let (version, elapsed_ms) = (state.version, state.elapsed_ms + played);
+            state.elapsed_ms = elapsed_ms;
+            //A command saved meanwhile is kept and the progress applied on top of it,
+            //unless it changed the playback and decoding restarts from its position
+            while !self.database.compare_and_set_setting(
+                STATE_KEY,
+                raw.as_deref(),
+                &serde_json::to_string(&state).map_err(YapError::db)?,
+            )? {
+                (raw, state) = self.load()?;
+                if state.version != version {
+                    break;
+                }
+                state.elapsed_ms = elapsed_ms;
+            }
+        }
+    }
+}
+
+/**
+ * Polls the shared state of the local player and reports what changed
+ */
+struct LocalEvents {
+    player: LocalPlayer,
+    last: LocalState,
+}
+
+impl EventSource for LocalEvents {
+    fn current_song_id(&self) -> Option<String> {
+        self.last
+            .current_entry()
+            .map(|entry| song_id_from_file(&entry.file))
+    }
+
+    fn next_events(&mut self) -> Result<Vec<PlayerEvent>> {
+        loop {
+            let (_, state) = self.player.load()?;
+            let mut events = Vec::new();
+            let file = |state: &LocalState| state.current_entry().map(|entry| entry.id);
+            if file(&state) != file(&self.last) {
+                events.push(PlayerEvent::SongChanged {
+                    song_id: state
+                        .current_entry()
+                        .map(|entry| song_id_from_file(&entry.file)),
+                });
+            }
+            if (state.state == PlaybackState::Play) != (self.last.state == PlaybackState::Play) {
+                events.push(PlayerEvent::Paused {
+                    paused: state.state != PlaybackState::Play,
+                });
+            }
+            if state.queue != self.last.queue {
+                events.push(PlayerEvent::QueueChanged {
+                    length: state.queue.len() as u32,
+                });
+            }
+            if (state.repeat, state.random, state.single, state.consume)
+                != (
+                    self.last.repeat,
+                    self.last.random,
+                    self.last.single,
+                    self.last.consume,
+                )
+            {
+                events.push(PlayerEvent::OptionsChanged {
+                    repeat: state.repeat,
+                    random: state.random,
+                    single: state.single,
+                    consume: state.consume,
+                });
+            }
+            self.last = state;
+            if !events.is_empty() {
+                return Ok(events);
+            }
+            thread::sleep(Duration::from_millis(250));
+        }
+    }
+}
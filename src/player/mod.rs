@@ -0,0 +1,4 @@
+pub mod backend;
+pub mod events;
+pub mod local_player;
+pub mod sink;
@@ -0,0 +1,122 @@
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+    process::{Child, ChildStdin, Command, Stdio},
+    thread,
+    time::Duration,
+};
+
+use hound::{SampleFormat, WavSpec, WavWriter};
+
+use crate::config::{Player, SinkKind};
+use crate::error::{Result, YapError};
+
+pub const SAMPLE_RATE: u32 = 48000;
+pub const CHANNELS: u16 = 2;
+
+/**
+ * Output for the signed 16 bit interleaved samples decoded by the local player
+ */
+pub trait Sink {
+    fn write(&mut self, samples: &[i16]) -> Result<()>;
+}
+
+pub fn open_sink(config: &Player) -> Result<Box<dyn Sink>> {
+    Ok(match config.sink {
+        SinkKind::Command => Box::new(CommandSink::new(&config.sink_command)?),
+        SinkKind::Null => Box::new(NullSink {}),
+        SinkKind::Wav => Box::new(WavSink::new(&config.wav_path)?),
+    })
+}
+
+/**
+ * Pipes the samples into an external program, like aplay or pw-play
+ */
+pub struct CommandSink {
+    child: Child,
+    stdin: ChildStdin,
+}
+
+impl CommandSink {
+    pub fn new(command: &str) -> Result<Self> {
+        let mut child = Command::new("sh")
+            .arg("-c")
+            .arg(command)
+            .stdin(Stdio::piped())
+            .spawn()?;
+        let stdin = match child.stdin.take() {
+            Some(stdin) => stdin,
+            None => {
+                return Err(YapError::Player(
+                    "Couldn't open sink command input".to_string(),
+                ))
+            }
+        };
+        Ok(Self { child, stdin })
+    }
+}
+
+impl Sink for CommandSink {
+    fn write(&mut self, samples: &[i16]) -> Result<()> {
+        let bytes = samples
+            .iter()
+            .flat_map(|sample| sample.to_le_bytes())
+            .collect::<Vec<u8>>();
+        self.stdin.write_all(&bytes)?;
+        Ok(())
+    }
+}
+
+impl Drop for CommandSink {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/**
+ * Discards the samples, waiting as long as they would take to play
+ */
+pub struct NullSink {}
+
+impl Sink for NullSink {
+    fn write(&mut self, samples: &[i16]) -> Result<()> {
+        let frames = samples.len() as u64 / CHANNELS as u64;
+        thread::sleep(Duration::from_micros(
+            frames * 1_000_000 / SAMPLE_RATE as u64,
+        ));
+        Ok(())
+    }
+}
+
+/**
+ * Writes the samples to a wav file as fast as they are decoded
+ */
+pub struct WavSink {
+    writer: WavWriter<BufWriter<File>>,
+}
+
+impl WavSink {
+    pub fn new(path: &Path) -> Result<Self> {
+        let spec = WavSpec {
+            channels: CHANNELS,
+            sample_rate: SAMPLE_RATE,
+            bits_per_sample: 16,
+            sample_format: SampleFormat::Int,
+        };
+        Ok(Self {
+            writer: WavWriter::create(path, spec)?,
+        })
+    }
+}
+
+impl Sink for WavSink {
+    fn write(&mut self, samples: &[i16]) -> Result<()> {
+        for sample in samples {
+            self.writer.write_sample(*sample)?;
+        }
+        self.writer.flush()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,189 @@
+use std::str::FromStr;
+
+use serde::Serialize;
+
+use crate::error::{Result, YapError};
+
+/**
+ * File formats playlists can be exported to
+ */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PlaylistFormat {
+    M3u,
+    M3u8,
+    Xspf,
+    Pls,
+    Json,
+}
+
+impl PlaylistFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            PlaylistFormat::M3u => "m3u",
+            PlaylistFormat::M3u8 => "m3u8",
+            PlaylistFormat::Xspf => "xspf",
+            PlaylistFormat::Pls => "pls",
+            PlaylistFormat::Json => "json",
+        }
+    }
+}
+
+impl FromStr for PlaylistFormat {
+    type Err = String;
+
+    fn from_str(value: &str) -> std::result::Result<Self, Self::Err> {
+        match value.to_lowercase().as_str() {
+            "m3u" => Ok(PlaylistFormat::M3u),
+            "m3u8" => Ok(PlaylistFormat::M3u8),
+            "xspf" => Ok(PlaylistFormat::Xspf),
+            "pls" => Ok(PlaylistFormat::Pls),
+            "json" => Ok(PlaylistFormat::Json),
+            _ => Err(format!(
+                "Unknown playlist format {}, use m3u, m3u8, xspf, pls or json",
+                value
+            )),
+        }
+    }
+}
+
+/**
+ * How song files are written in exported playlists
+ */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PathStyle {
+    Absolute,
+    //Relative to the music directory, which is what mpd expects
+    Relative,
+}
+
+/**
+ * A song of an exported playlist
+ */
+#[derive(Debug, Clone, Serialize)]
+pub struct PlaylistEntry {
+    pub id: String,
+    pub name: String,
+    pub artist: Option<String>,
+    pub path: String,
+    //Seconds
+    pub duration: Option<f64>,
+}
+
+impl PlaylistEntry {
+    pub(crate) fn display_title(&self) -> String {
+        match &self.artist {
+            Some(artist) => format!("{} - {}", artist, self.name),
+            None => self.name.clone(),
+        }
+    }
+
+    fn seconds(&self) -> i64 {
+        self.duration.map_or(-1, |duration| duration.round() as i64)
+    }
+}
+
+#[derive(Serialize)]
+struct JsonPlaylist<'a> {
+    name: &'a str,
+    songs: &'a [PlaylistEntry],
+}
+
+/**
+ * Writes a playlist in the given format
+ */
+pub fn write_playlist(
+    format: PlaylistFormat,
+    name: &str,
+    entries: &[PlaylistEntry],
+) -> Result<String> {
+    Ok(match format {
+        PlaylistFormat::M3u | PlaylistFormat::M3u8 => write_m3u(name, entries),
+        PlaylistFormat::Xspf => write_xspf(name, entries),
+        PlaylistFormat::Pls => write_pls(entries),
+        PlaylistFormat::Json => {
+            let playlist = JsonPlaylist {
+                name,
+                songs: entries,
+            };
+            serde_json::to_string_pretty(&playlist)
+                .map_err(|error| YapError::Io(std::io::Error::other(error)))?
+                + "\n"
+        }
+    })
+}
+
+fn write_m3u(name: &str, entries: &[PlaylistEntry]) -> String {
+    let mut output = format!("#EXTM3U\n#PLAYLIST:{}\n", name);
+    for entry in entries {
+        output += &format!(
+            "#EXTINF:{},{}\n{}\n",
+            entry.seconds(),
+            entry.display_title(),
+            entry.path
+        );
+    }
+    output
+}
+
+fn write_pls(entries: &[PlaylistEntry]) -> String {
+    let mut output = "[playlist]\n".to_string();
+    for (index, entry) in entries.iter().enumerate() {
+        let number = index + 1;
+        output += &format!("File{}={}\n", number, entry.path);
+        output += &format!("Title{}={}\n", number, entry.display_title());
+        output += &format!("Length{}={}\n", number, entry.seconds());
+    }
+    output += &format!("NumberOfEntries={}\nVersion=2\n", entries.len());
+    output
+}
+
+fn write_xspf(name: &str, entries: &[PlaylistEntry]) -> String {
+    let mut output = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n".to_string();
+    output += "<playlist version=\"1\" xmlns=\"http://xspf.org/ns/0/\">\n";
+    output += &format!("  <title>{}</title>\n  <trackList>\n", escape_xml(name));
+    for entry in entries {
+        let location = if entry.path.starts_with('/') {
+            format!("file://{}", encode_uri(&entry.path))
+        } else {
+            encode_uri(&entry.path)
+        };
+        output += "    <track>\n";
+        output += &format!("      <location>{}</location>\n", escape_xml(&location));
+        output += &format!("      <title>{}</title>\n", escape_xml(&entry.name));
+        if let Some(artist) = &entry.artist {
+            output += &format!("      <creator>{}</creator>\n", escape_xml(artist));
+        }
+        if let Some(duration) = entry.duration {
+            output += &format!(
+                "      <duration>{}</duration>\n",
+                (duration * 1000.).round() as u64
+            );
+        }
+        output += "    </track>\n";
+    }
+    output += "  </trackList>\n</playlist>\n";
+    output
+}
+
+fn escape_xml(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+/**
+ * Percent encodes a path for use in a URI, keeping the slashes
+ */
+fn encode_uri(path: &str) -> String {
+    path.bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => {
+                (byte as char).to_string()
+            }
+            byte => format!("%{:02X}", byte),
+        })
+        .collect()
+}
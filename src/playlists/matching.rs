@@ -0,0 +1,69 @@
+use std::{collections::HashSet, path::Path};
+
+use super::parse::ImportedEntry;
+use crate::db::models::Song;
+
+//How similar titles have to be for a fuzzy match, from 0 to 1
+const TITLE_THRESHOLD: f64 = 0.75;
+
+/**
+ * Lowercase words of a title, ignoring punctuation
+ */
+fn words(value: &str) -> HashSet<String> {
+    value
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| word.to_lowercase())
+        .collect()
+}
+
+/**
+ * Share of words two titles have in common
+ */
+pub fn similarity(first: &str, second: &str) -> f64 {
+    let first = words(first);
+    let second = words(second);
+    if first.is_empty() || second.is_empty() {
+        return 0.;
+    }
+    first.intersection(&second).count() as f64 / first.union(&second).count() as f64
+}
+
+/**
+ * Title to compare an entry with, the file name when the playlist has none
+ */
+fn entry_title(entry: &ImportedEntry) -> String {
+    match &entry.title {
+        Some(title) => title.clone(),
+        None => Path::new(&entry.location)
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_default(),
+    }
+}
+
+/**
+ * The song that looks most like the entry by title and artist, if any is close enough
+ */
+pub fn best_match<'a>(entry: &ImportedEntry, songs: &'a [Song]) -> Option<&'a Song> {
+    let title = entry_title(entry);
+    songs
+        .iter()
+        .filter_map(|song| {
+            let mut score = similarity(&title, &song.name);
+            //Some players write "Artist - Title" without splitting it
+            if let Some(artist) = &song.artist {
+                score = score.max(similarity(&title, &format!("{} {}", artist, song.name)));
+            }
+            if score < TITLE_THRESHOLD {
+                return None;
+            }
+            match (&entry.artist, &song.artist) {
+                (Some(wanted), Some(artist)) if similarity(wanted, artist) == 0. => None,
+                (Some(wanted), Some(artist)) => Some((score + similarity(wanted, artist), song)),
+                _ => Some((score, song)),
+            }
+        })
+        .max_by(|(first, _), (second, _)| first.total_cmp(second))
+        .map(|(_, song)| song)
+}
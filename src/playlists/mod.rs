@@ -0,0 +1,3 @@
+pub mod format;
+pub mod matching;
+pub mod parse;
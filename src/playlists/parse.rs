@@ -0,0 +1,235 @@
+use std::path::Path;
+
+use serde::Deserialize;
+
+use super::format::PlaylistFormat;
+use crate::error::{Result, YapError};
+
+/**
+ * A song of an imported playlist, as written by the player that exported it
+ */
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ImportedEntry {
+    //Path, url or song id
+    pub location: String,
+    pub title: Option<String>,
+    pub artist: Option<String>,
+}
+
+/**
+ * An imported playlist, the name is only known when the file has one
+ */
+#[derive(Debug)]
+pub struct ImportedPlaylist {
+    pub name: Option<String>,
+    pub entries: Vec<ImportedEntry>,
+}
+
+#[derive(Deserialize)]
+struct JsonPlaylist {
+    name: Option<String>,
+    songs: Vec<JsonSong>,
+}
+
+#[derive(Deserialize)]
+struct JsonSong {
+    id: Option<String>,
+    name: Option<String>,
+    artist: Option<String>,
+    path: Option<String>,
+}
+
+/**
+ * Guesses the format of a playlist file from its extension
+ */
+pub fn format_of(file: &Path) -> Result<PlaylistFormat> {
+    let extension = file
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_string())
+        .unwrap_or_default();
+    extension.parse().map_err(|_| {
+        YapError::NotFound(format!(
+            "Can't tell the format of {}, use --format",
+            file.display()
+        ))
+    })
+}
+
+pub fn parse_playlist(format: PlaylistFormat, content: &str) -> Result<ImportedPlaylist> {
+    match format {
+        PlaylistFormat::M3u | PlaylistFormat::M3u8 => Ok(parse_m3u(content)),
+        PlaylistFormat::Pls => Ok(parse_pls(content)),
+        PlaylistFormat::Xspf => Ok(parse_xspf(content)),
+        PlaylistFormat::Json => parse_json(content),
+    }
+}
+
+/**
+ * Splits an "Artist - Title" display title
+ */
+fn split_title(title: &str) -> (Option<String>, Option<String>) {
+    let title = title.trim();
+    if title.is_empty() {
+        return (None, None);
+    }
+    match title.split_once(" - ") {
+        Some((artist, title)) => (
+            Some(title.trim().to_string()),
+            Some(artist.trim().to_string()),
+        ),
+        None => (Some(title.to_string()), None),
+    }
+}
+
+fn parse_m3u(content: &str) -> ImportedPlaylist {
+    let mut name = None;
+    let mut entries = Vec::new();
+    let mut pending = ImportedEntry::default();
+    for line in content.lines() {
+        let line = line.trim().trim_start_matches('\u{feff}');
+        if let Some(info) = line.strip_prefix("#EXTINF:") {
+            //#EXTINF:duration,Artist - Title
+            let display = info.split_once(',').map_or("", |(_, display)| display);
+            (pending.title, pending.artist) = split_title(display);
+        } else if let Some(playlist) = line.strip_prefix("#PLAYLIST:") {
+            name = Some(playlist.trim().to_string());
+        } else if !line.is_empty() && !line.starts_with('#') {
+            pending.location = line.to_string();
+            entries.push(std::mem::take(&mut pending));
+        }
+    }
+    ImportedPlaylist { name, entries }
+}
+
+fn parse_pls(content: &str) -> ImportedPlaylist {
+    let mut entries: Vec<(u32, ImportedEntry)> = Vec::new();
+    for line in content.lines() {
+        let (key, value) = match line.trim().split_once('=') {
+            Some(pair) => pair,
+            None => continue,
+        };
+        let (field, number) = match key.find(|c: char| c.is_ascii_digit()) {
+            Some(index) => (&key[..index], key[index..].parse::<u32>().ok()),
+            None => continue,
+        };
+        let number = match number {
+            Some(number) => number,
+            None => continue,
+        };
+        let index = match entries.iter().position(|(n, _)| *n == number) {
+            Some(index) => index,
+            None => {
+                entries.push((number, ImportedEntry::default()));
+                entries.len() - 1
+            }
+        };
+        let entry = &mut entries[index].1;
+        match field.to_lowercase().as_str() {
+            "file" => entry.location = value.trim().to_string(),
+            "title" => (entry.title, entry.artist) = split_title(value),
+            _ => {}
+        }
+    }
+    entries.sort_by_key(|(number, _)| *number);
+    ImportedPlaylist {
+        name: None,
+        entries: entries
+            .into_iter()
+            .map(|(_, entry)| entry)
+            .filter(|entry| !entry.location.is_empty())
+            .collect(),
+    }
+}
+
+/**
+ * Text of the first <tag> element in the given xml, unescaped
+ */
+fn xml_element(xml: &str, tag: &str) -> Option<String> {
+    let open = format!("<{}>", tag);
+    let close = format!("</{}>", tag);
+    let start = xml.find(&open)? + open.len();
+    let end = start + xml[start..].find(&close)?;
+    Some(unescape_xml(xml[start..end].trim()))
+}
+
+fn unescape_xml(value: &str) -> String {
+    value
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+fn decode_uri(uri: &str) -> String {
+    let uri = uri.strip_prefix("file://").unwrap_or(uri);
+    let bytes = uri.as_bytes();
+    let mut decoded = Vec::new();
+    let mut index = 0;
+    while index < bytes.len() {
+        let hex = bytes
+            .get(index + 1..index + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[index], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                index += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                index += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
+fn parse_xspf(content: &str) -> ImportedPlaylist {
+    //The playlist title is the one before the track list, tracks have their own
+    let header = content.split("<trackList>").next().unwrap_or_default();
+    let mut entries = Vec::new();
+    for track in content.split("<track>").skip(1) {
+        let track = track.split("</track>").next().unwrap_or_default();
+        let location = match xml_element(track, "location") {
+            Some(location) => location,
+            None => continue,
+        };
+        entries.push(ImportedEntry {
+            location: if location.contains("://") && !location.starts_with("file://") {
+                location
+            } else {
+                decode_uri(&location)
+            },
+            title: xml_element(track, "title"),
+            artist: xml_element(track, "creator"),
+        });
+    }
+    ImportedPlaylist {
+        name: xml_element(header, "title"),
+        entries,
+    }
+}
+
+fn parse_json(content: &str) -> Result<ImportedPlaylist> {
+    let playlist: JsonPlaylist = serde_json::from_str(content).map_err(|error| {
+        YapError::Io(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("Not a yap json playlist: {}", error),
+        ))
+    })?;
+    Ok(ImportedPlaylist {
+        name: playlist.name,
+        entries: playlist
+            .songs
+            .into_iter()
+            .filter_map(|song| {
+                Some(ImportedEntry {
+                    location: song.id.or(song.path)?,
+                    title: song.name,
+                    artist: song.artist,
+                })
+            })
+            .collect(),
+    })
+}
@@ -0,0 +1,80 @@
+use std::{
+    fs::File,
+    path::{Path, PathBuf},
+};
+
+use async_trait::async_trait;
+
+use super::source::{convert_to_opus, delete_audio_file, make_song_id, SongMetadata, Source};
+use crate::error::Result;
+
+const AUDIO_EXTENSIONS: [&str; 8] = ["mp3", "opus", "ogg", "oga", "flac", "m4a", "wav", "aac"];
+
+/**
+ * Downloads direct links to audio files
+ */
+pub struct HttpSource {
+    music_directory: PathBuf,
+}
+
+impl HttpSource {
+    pub fn new(music_directory: PathBuf) -> Self {
+        Self { music_directory }
+    }
+}
+
+fn file_name(location: &str) -> &str {
+    let path = location.split(['?', '#']).next().unwrap_or(location);
+    path.rsplit('/').next().unwrap_or(path)
+}
+
+#[async_trait(?Send)]
+impl Source for HttpSource {
+    fn name(&self) -> &'static str {
+        "http"
+    }
+
+    fn handles(&self, location: &str) -> bool {
+        if !location.starts_with("http://") && !location.starts_with("https://") {
+            return false;
+        }
+        Path::new(file_name(location))
+            .extension()
+            .is_some_and(|ext| {
+                AUDIO_EXTENSIONS.contains(&ext.to_string_lossy().to_lowercase().as_str())
+            })
+    }
+
+    async fn resolve(&self, location: &str) -> Result<SongMetadata> {
+        let stem = Path::new(file_name(location))
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_default();
+        Ok(SongMetadata {
+            id: make_song_id("http", &stem, location),
+            title: Some(stem),
+            artist: None,
+            chapters: Vec::new(),
+        })
+    }
+
+    async fn download(&self, location: &str, metadata: &SongMetadata) -> Result<()> {
+        let response = reqwest::get(location).await?.error_for_status()?;
+        let extension = Path::new(file_name(location))
+            .extension()
+            .map(|ext| ext.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let temporary = self
+            .music_directory
+            .join(format!("{}.download.{}", metadata.id, extension));
+        let mut file = File::create(&temporary)?;
+        std::io::copy(&mut response.bytes().await?.as_ref(), &mut file)?;
+        let converted = convert_to_opus(&temporary, &self.music_directory, &metadata.id);
+        std::fs::remove_file(&temporary)?;
+        converted
+    }
+
+    fn delete(&self, song_id: &str) -> Result<()> {
+        delete_audio_file(&self.music_directory, song_id)
+    }
+}
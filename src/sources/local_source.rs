@@ -0,0 +1,55 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use async_trait::async_trait;
+
+use super::source::{convert_to_opus, delete_audio_file, make_song_id, SongMetadata, Source};
+use crate::error::Result;
+
+/**
+ * Imports audio files that are already on disk
+ */
+pub struct LocalSource {
+    music_directory: PathBuf,
+}
+
+impl LocalSource {
+    pub fn new(music_directory: PathBuf) -> Self {
+        Self { music_directory }
+    }
+}
+
+#[async_trait(?Send)]
+impl Source for LocalSource {
+    fn name(&self) -> &'static str {
+        "local"
+    }
+
+    fn handles(&self, location: &str) -> bool {
+        Path::new(location).is_file()
+    }
+
+    async fn resolve(&self, location: &str) -> Result<SongMetadata> {
+        let path = fs::canonicalize(location)?;
+        let stem = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_default();
+        Ok(SongMetadata {
+            id: make_song_id("local", &stem, &path.to_string_lossy()),
+            title: Some(stem),
+            artist: None,
+            chapters: Vec::new(),
+        })
+    }
+
+    async fn download(&self, location: &str, metadata: &SongMetadata) -> Result<()> {
+        convert_to_opus(Path::new(location), &self.music_directory, &metadata.id)
+    }
+
+    fn delete(&self, song_id: &str) -> Result<()> {
+        delete_audio_file(&self.music_directory, song_id)
+    }
+}
@@ -0,0 +1,4 @@
+pub mod http_source;
+pub mod local_source;
+pub mod source;
+pub mod ytdlp_source;
@@ -0,0 +1,174 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use async_trait::async_trait;
+
+use crate::error::{Result, YapError};
+
+/**
+ * What a source knows about a song before downloading it, the id is the
+ * name the audio file will be stored under in the music directory
+ */
+#[derive(Debug, Clone)]
+pub struct SongMetadata {
+    pub id: String,
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    //Chapter markers of the video, empty when the source has none
+    pub chapters: Vec<Chapter>,
+}
+
+/**
+ * A chapter of a long video, times in seconds
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub struct Chapter {
+    pub title: Option<String>,
+    pub start: f64,
+    //Missing for the last chapter, which lasts until the end
+    pub end: Option<f64>,
+}
+
+/**
+ * A place songs can be downloaded from, every source stores the audio as
+ * {music_directory}/{id}.opus so players don't need to know where it came from
+ */
+#[async_trait(?Send)]
+pub trait Source {
+    /**
+     * Name stored in the source column of the song table
+     */
+    fn name(&self) -> &'static str;
+
+    /**
+     * Whether this source knows how to handle the given id, url or path
+     */
+    fn handles(&self, location: &str) -> bool;
+
+    async fn resolve(&self, location: &str) -> Result<SongMetadata>;
+
+    async fn download(&self, location: &str, metadata: &SongMetadata) -> Result<()>;
+
+    fn delete(&self, song_id: &str) -> Result<()>;
+
+    /**
+     * Where a song of this source can be downloaded again from, knowing only its id
+     */
+    fn redownload_location(&self, _song_id: &str) -> Option<String> {
+        None
+    }
+}
+
+/**
+ * All configured sources, the first one that handles a location wins
+ */
+pub struct Sources {
+    sources: Vec<Box<dyn Source>>,
+}
+
+impl Sources {
+    pub fn new(sources: Vec<Box<dyn Source>>) -> Self {
+        Self { sources }
+    }
+
+    pub fn for_location(&self, location: &str) -> Result<&dyn Source> {
+        self.sources
+            .iter()
+            .find(|source| source.handles(location))
+            .map(|source| source.as_ref())
+            .ok_or(YapError::Download(format!(
+                "No source can download {}",
+                location
+            )))
+    }
+
+    pub fn by_name(&self, name: &str) -> Result<&dyn Source> {
+        self.sources
+            .iter()
+            .find(|source| source.name() == name)
+            .map(|source| source.as_ref())
+            .ok_or(YapError::NotFound(format!("Unknown source {}", name)))
+    }
+}
+
+/**
+ * 32 bit FNV-1a, ids are stored and used as file names so their hash must never change
+ */
+fn fnv1a(text: &str) -> u32 {
+    text.bytes().fold(0x811c9dc5, |hash, byte| {
+        (hash ^ byte as u32).wrapping_mul(0x01000193)
+    })
+}
+
+/**
+ * Builds an id usable as a file name from a prefix, a readable stem and the
+ * full location, so two files with the same name don't collide
+ */
+pub fn make_song_id(prefix: &str, stem: &str, location: &str) -> String {
+    let stem: String = stem
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .take(40)
+        .collect();
+    format!("{}-{}-{:08x}", prefix, stem, fnv1a(location))
+}
+
+/**
+ * Chapters from yt-dlp metadata, a chapter without a start begins where the previous one ends
+ */
+pub fn yt_dlp_chapters(chapters: Option<Vec<youtube_dl::Chapter>>) -> Vec<Chapter> {
+    let mut result: Vec<Chapter> = Vec::new();
+    for chapter in chapters.unwrap_or_default() {
+        let previous_end = result.last().and_then(|previous| previous.end);
+        let start = match chapter.start_time.or(previous_end) {
+            Some(start) => start,
+            None if result.is_empty() => 0.,
+            None => continue,
+        };
+        result.push(Chapter {
+            title: chapter.title,
+            start,
+            end: chapter.end_time,
+        });
+    }
+    result
+}
+
+/**
+ * Where the audio of a song is stored
+ */
+pub fn audio_file(music_directory: &Path, song_id: &str) -> PathBuf {
+    music_directory.join(format!("{}.opus", song_id))
+}
+
+/**
+ * Transcodes any audio file ffmpeg understands to {music_directory}/{id}.opus
+ */
+pub fn convert_to_opus(input: &Path, music_directory: &Path, song_id: &str) -> Result<()> {
+    let output = audio_file(music_directory, song_id);
+    if input.extension().is_some_and(|ext| ext == "opus") {
+        fs::copy(input, &output)?;
+        return Ok(());
+    }
+    let status = Command::new("ffmpeg")
+        .args(["-y", "-v", "quiet", "-i"])
+        .arg(input)
+        .args(["-vn", "-c:a", "libopus"])
+        .arg(&output)
+        .status()?;
+    if !status.success() {
+        return Err(YapError::Download(format!(
+            "Couldn't convert {} to opus",
+            input.display()
+        )));
+    }
+    Ok(())
+}
+
+pub fn delete_audio_file(music_directory: &Path, song_id: &str) -> Result<()> {
+    fs::remove_file(audio_file(music_directory, song_id))?;
+    Ok(())
+}
@@ -0,0 +1,66 @@
+use std::path::PathBuf;
+
+use async_trait::async_trait;
+use youtube_dl::YoutubeDl;
+
+use super::source::{delete_audio_file, make_song_id, yt_dlp_chapters, SongMetadata, Source};
+use crate::error::{Result, YapError};
+
+/**
+ * Anything else yt-dlp has an extractor for, like SoundCloud or Bandcamp
+ */
+pub struct YtDlpSource {
+    music_directory: PathBuf,
+}
+
+impl YtDlpSource {
+    pub fn new(music_directory: PathBuf) -> Self {
+        Self { music_directory }
+    }
+}
+
+#[async_trait(?Send)]
+impl Source for YtDlpSource {
+    fn name(&self) -> &'static str {
+        "ytdlp"
+    }
+
+    fn handles(&self, location: &str) -> bool {
+        location.starts_with("http://") || location.starts_with("https://")
+    }
+
+    async fn resolve(&self, location: &str) -> Result<SongMetadata> {
+        let video =
+            YoutubeDl::new(location)
+                .run()?
+                .into_single_video()
+                .ok_or(YapError::Download(format!(
+                    "{} is not a single song",
+                    location
+                )))?;
+        let extractor = video
+            .extractor
+            .unwrap_or("generic".to_string())
+            .to_lowercase();
+        Ok(SongMetadata {
+            id: make_song_id(&extractor, &video.id, location),
+            title: video.title,
+            artist: video.artist.or(video.uploader),
+            chapters: yt_dlp_chapters(video.chapters),
+        })
+    }
+
+    async fn download(&self, location: &str, metadata: &SongMetadata) -> Result<()> {
+        YoutubeDl::new(location)
+            .extract_audio(true)
+            .extra_arg("--audio-format")
+            .extra_arg("opus")
+            .output_template(&metadata.id)
+            .download_to(&self.music_directory)?;
+        Ok(())
+    }
+
+    fn delete(&self, song_id: &str) -> Result<()> {
+        delete_audio_file(&self.music_directory, song_id)
+    }
+}
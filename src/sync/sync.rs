@@ -0,0 +1,212 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    fs, io,
+    path::{Path, PathBuf},
+    time::UNIX_EPOCH,
+};
+
+use serde::{Deserialize, Serialize};
+
+use super::transcode::{transcode, Encoding};
+use crate::{
+    error::{Result, YapError},
+    playlists::format::{write_playlist, PlaylistEntry, PlaylistFormat},
+};
+
+//Remembers what was synced to a directory, so later syncs only copy what changed
+const STATE: &str = ".yap-sync.json";
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct SyncState {
+    //Synced file name to the song it was written from
+    files: BTreeMap<String, SyncedFile>,
+    //Playlist files written by the last sync
+    playlists: BTreeSet<String>,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct SyncedFile {
+    song_id: String,
+    codec: String,
+    bitrate: Option<String>,
+    //Modification time of the song file, in seconds since the epoch
+    modified: u64,
+}
+
+/**
+ * What a sync wrote and removed
+ */
+#[derive(Debug, Default)]
+pub struct SyncReport {
+    pub written: Vec<String>,
+    pub unchanged: usize,
+    pub removed: Vec<String>,
+    //Songs of the playlists without an audio file
+    pub missing: Vec<String>,
+    pub failed: Vec<(String, YapError)>,
+    pub playlists: Vec<PathBuf>,
+}
+
+/**
+ * Replaces the characters FAT and NTFS devices don't allow in file names
+ */
+fn file_name(name: &str) -> String {
+    let name: String = name
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+    name.trim_end_matches(['.', ' ']).to_string()
+}
+
+/**
+ * "Artist - Title.ext", with the song id added when another song already has that name
+ */
+fn song_file_name(
+    entry: &PlaylistEntry,
+    extension: &str,
+    taken: &BTreeMap<String, SyncedFile>,
+) -> String {
+    let base = file_name(&entry.display_title());
+    let name = format!("{}.{}", base, extension);
+    //Devices are often case insensitive
+    let lowercase = name.to_lowercase();
+    match taken.keys().any(|taken| taken.to_lowercase() == lowercase) {
+        true => format!("{} [{}].{}", base, entry.id, extension),
+        false => name,
+    }
+}
+
+fn modified_time(file: &Path) -> Option<u64> {
+    let modified = fs::metadata(file).ok()?.modified().ok()?;
+    Some(modified.duration_since(UNIX_EPOCH).ok()?.as_secs())
+}
+
+fn remove_file(file: &Path) -> Result<()> {
+    match fs::remove_file(file) {
+        Err(error) if error.kind() != io::ErrorKind::NotFound => Err(error.into()),
+        _ => Ok(()),
+    }
+}
+
+/**
+ * Mirrors the songs of the playlists into a directory with an m3u per playlist,
+ * entries point to the songs' audio files. Songs unchanged since the last sync are
+ * kept and files the playlists no longer have are removed
+ */
+pub fn sync_playlists(
+    playlists: &[(String, Vec<PlaylistEntry>)],
+    target: &Path,
+    encoding: &Encoding,
+) -> Result<SyncReport> {
+    fs::create_dir_all(target)?;
+    let state_file = target.join(STATE);
+    let previous: SyncState = match fs::read_to_string(&state_file) {
+        Ok(state) => serde_json::from_str(&state).unwrap_or_default(),
+        Err(_) => SyncState::default(),
+    };
+    let mut state = SyncState::default();
+    let mut report = SyncReport::default();
+    //Song id to its synced file name, None when it couldn't be synced
+    let mut names: HashMap<String, Option<String>> = HashMap::new();
+
+    for (playlist, entries) in playlists {
+        let mut synced = Vec::new();
+        for entry in entries {
+            if !names.contains_key(&entry.id) {
+                let name = sync_song(entry, target, encoding, &previous, &mut state, &mut report);
+                names.insert(entry.id.clone(), name);
+            }
+            if let Some(Some(name)) = names.get(&entry.id) {
+                synced.push(PlaylistEntry {
+                    path: name.clone(),
+                    ..entry.clone()
+                });
+            }
+        }
+        let name = format!(
+            "{}.{}",
+            file_name(playlist),
+            PlaylistFormat::M3u.extension()
+        );
+        let file = target.join(&name);
+        fs::write(
+            &file,
+            write_playlist(PlaylistFormat::M3u, playlist, &synced)?,
+        )?;
+        state.playlists.insert(name);
+        report.playlists.push(file);
+    }
+
+    for name in previous.files.keys() {
+        if !state.files.contains_key(name) {
+            remove_file(&target.join(name))?;
+            report.removed.push(name.clone());
+        }
+    }
+    for name in previous.playlists.iter() {
+        if !state.playlists.contains(name) {
+            remove_file(&target.join(name))?;
+            report.removed.push(name.clone());
+        }
+    }
+    fs::write(
+        &state_file,
+        serde_json::to_string_pretty(&state).map_err(io::Error::other)?,
+    )?;
+    Ok(report)
+}
+
+/**
+ * Writes a song to the target unless the last sync already did, returns its file name
+ */
+fn sync_song(
+    entry: &PlaylistEntry,
+    target: &Path,
+    encoding: &Encoding,
+    previous: &SyncState,
+    state: &mut SyncState,
+    report: &mut SyncReport,
+) -> Option<String> {
+    let source = Path::new(&entry.path);
+    let modified = match modified_time(source) {
+        Some(modified) => modified,
+        None => {
+            report.missing.push(entry.name.clone());
+            return None;
+        }
+    };
+    let name = song_file_name(entry, encoding.codec.extension(), &state.files);
+    let synced = SyncedFile {
+        song_id: entry.id.clone(),
+        codec: encoding.codec.extension().to_string(),
+        bitrate: encoding.bitrate.clone(),
+        modified,
+    };
+    let file = target.join(&name);
+    if previous.files.get(&name) == Some(&synced) && file.is_file() {
+        report.unchanged += 1;
+    } else {
+        //Written next to the target first, an interrupted sync never leaves half a song
+        let partial = target.join(format!("{}.part", name));
+        let written = transcode(
+            source,
+            &partial,
+            encoding,
+            &entry.name,
+            entry.artist.as_deref(),
+        )
+        .and_then(|_| Ok(fs::rename(&partial, &file)?));
+        if let Err(error) = written {
+            let _ = fs::remove_file(&partial);
+            report.failed.push((entry.name.clone(), error));
+            return None;
+        }
+        report.written.push(name.clone());
+    }
+    state.files.insert(name.clone(), synced);
+    Some(name)
+}
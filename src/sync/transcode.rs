@@ -0,0 +1,129 @@
+use std::{fs, path::Path, process::Command, str::FromStr};
+
+use crate::error::{Result, YapError};
+
+/**
+ * Codecs songs can be synced as, for devices that can't play opus
+ */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Codec {
+    Opus,
+    Mp3,
+    Aac,
+    Vorbis,
+    Flac,
+}
+
+impl Codec {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Codec::Opus => "opus",
+            Codec::Mp3 => "mp3",
+            Codec::Aac => "m4a",
+            Codec::Vorbis => "ogg",
+            Codec::Flac => "flac",
+        }
+    }
+
+    fn encoder(&self) -> &'static str {
+        match self {
+            Codec::Opus => "libopus",
+            Codec::Mp3 => "libmp3lame",
+            Codec::Aac => "aac",
+            Codec::Vorbis => "libvorbis",
+            Codec::Flac => "flac",
+        }
+    }
+
+    //Container ffmpeg writes, it can't guess it from the temporary file name
+    fn container(&self) -> &'static str {
+        match self {
+            Codec::Opus => "opus",
+            Codec::Mp3 => "mp3",
+            Codec::Aac => "ipod",
+            Codec::Vorbis => "ogg",
+            Codec::Flac => "flac",
+        }
+    }
+}
+
+impl FromStr for Codec {
+    type Err = String;
+
+    fn from_str(value: &str) -> std::result::Result<Self, Self::Err> {
+        match value.to_lowercase().as_str() {
+            "opus" => Ok(Codec::Opus),
+            "mp3" => Ok(Codec::Mp3),
+            "aac" | "m4a" => Ok(Codec::Aac),
+            "vorbis" | "ogg" => Ok(Codec::Vorbis),
+            "flac" => Ok(Codec::Flac),
+            _ => Err(format!(
+                "Unknown codec {}, use opus, mp3, aac, vorbis or flac",
+                value
+            )),
+        }
+    }
+}
+
+/**
+ * What synced files are encoded as, songs are copied as they are when it's opus
+ * without a bitrate
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub struct Encoding {
+    pub codec: Codec,
+    //Passed to ffmpeg as is, e.g. 192k
+    pub bitrate: Option<String>,
+}
+
+impl Default for Encoding {
+    fn default() -> Self {
+        Self {
+            codec: Codec::Opus,
+            bitrate: None,
+        }
+    }
+}
+
+/**
+ * Writes an opus song to output with the given encoding, tagging it with its title and artist
+ */
+pub fn transcode(
+    input: &Path,
+    output: &Path,
+    encoding: &Encoding,
+    title: &str,
+    artist: Option<&str>,
+) -> Result<()> {
+    if encoding.codec == Codec::Opus && encoding.bitrate.is_none() {
+        fs::copy(input, output)?;
+        return Ok(());
+    }
+    let mut command = Command::new("ffmpeg");
+    command.args(["-y", "-v", "quiet", "-i"]).arg(input).args([
+        "-vn",
+        "-map_metadata",
+        "0",
+        "-c:a",
+        encoding.codec.encoder(),
+    ]);
+    if let Some(bitrate) = &encoding.bitrate {
+        command.args(["-b:a", bitrate]);
+    }
+    command.arg("-metadata").arg(format!("title={}", title));
+    if let Some(artist) = artist {
+        command.arg("-metadata").arg(format!("artist={}", artist));
+    }
+    let status = command
+        .args(["-f", encoding.codec.container()])
+        .arg(output)
+        .status()?;
+    if !status.success() {
+        return Err(YapError::Audio(format!(
+            "Couldn't transcode {} to {}",
+            input.display(),
+            encoding.codec.extension()
+        )));
+    }
+    Ok(())
+}
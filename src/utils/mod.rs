@@ -1 +1,4 @@
+pub mod paths;
+pub mod template;
+#[allow(clippy::module_inception)]
 pub mod utils;
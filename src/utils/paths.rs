@@ -0,0 +1,96 @@
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+use crate::error::{Result, YapError};
+
+/**
+ * Fallbacks from the XDG base directory spec, relative to the home directory
+ */
+const XDG_DEFAULTS: [(&str, &str); 4] = [
+    ("XDG_CONFIG_HOME", ".config"),
+    ("XDG_DATA_HOME", ".local/share"),
+    ("XDG_STATE_HOME", ".local/state"),
+    ("XDG_CACHE_HOME", ".cache"),
+];
+
+fn home() -> Result<PathBuf> {
+    env::var_os("HOME")
+        .filter(|home| !home.is_empty())
+        .map(PathBuf::from)
+        .ok_or(YapError::Config("HOME is not set".to_string()))
+}
+
+fn variable(name: &str) -> Result<String> {
+    if let Some(value) = env::var_os(name).filter(|value| !value.is_empty()) {
+        return Ok(value.to_string_lossy().to_string());
+    }
+    match XDG_DEFAULTS.iter().find(|(variable, _)| *variable == name) {
+        Some((_, default)) => Ok(home()?.join(default).to_string_lossy().to_string()),
+        None => Err(YapError::Config(format!(
+            "Environment variable {} is not set",
+            name
+        ))),
+    }
+}
+
+/**
+ * Expands a leading ~ and every $VAR or ${VAR} in a configured path,
+ * unset XDG base directories fall back to their defaults from the spec
+ */
+pub fn expand_path(path: &Path) -> Result<PathBuf> {
+    let raw = path.to_string_lossy();
+    let mut expanded = String::new();
+    let mut rest: &str = &raw;
+    if rest == "~" || rest.starts_with("~/") {
+        expanded.push_str(&home()?.to_string_lossy());
+        rest = &rest[1..];
+    }
+    while let Some(start) = rest.find('$') {
+        expanded.push_str(&rest[..start]);
+        rest = &rest[start + 1..];
+        let (name, next) = match rest.strip_prefix('{') {
+            Some(braced) => match braced.find('}') {
+                Some(end) => (&braced[..end], &braced[end + 1..]),
+                None => {
+                    return Err(YapError::Config(format!(
+                        "Unclosed ${{ in {}",
+                        path.display()
+                    )))
+                }
+            },
+            None => {
+                let end = rest
+                    .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+                    .unwrap_or(rest.len());
+                (&rest[..end], &rest[end..])
+            }
+        };
+        if name.is_empty() {
+            expanded.push('$');
+        } else {
+            expanded.push_str(&variable(name)?);
+        }
+        rest = next;
+    }
+    expanded.push_str(rest);
+    Ok(PathBuf::from(expanded))
+}
+
+/**
+ * Creates a directory and its parents if they don't exist yet
+ */
+pub fn create_directory(what: &str, path: &Path) -> Result<()> {
+    if path.is_dir() {
+        return Ok(());
+    }
+    fs::create_dir_all(path).map_err(|error| {
+        YapError::Config(format!(
+            "Couldn't create {} {}: {}",
+            what,
+            path.display(),
+            error
+        ))
+    })
+}
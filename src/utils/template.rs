@@ -0,0 +1,34 @@
+use std::collections::HashMap;
+
+/**
+ * Replaces every {key} of the template with its value, unknown keys are left untouched
+ * and {{ }} can be used to print literal braces
+ */
+pub fn render(template: &str, values: &HashMap<&str, String>) -> String {
+    let mut output = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find(['{', '}']) {
+        output.push_str(&rest[..start]);
+        rest = &rest[start..];
+        if rest.starts_with("{{") || rest.starts_with("}}") {
+            output.push_str(&rest[..1]);
+            rest = &rest[2..];
+            continue;
+        }
+        if rest.starts_with('{') {
+            if let Some(end) = rest.find('}') {
+                let key = &rest[1..end];
+                match values.get(key) {
+                    Some(value) => output.push_str(value),
+                    None => output.push_str(&rest[..=end]),
+                }
+                rest = &rest[end + 1..];
+                continue;
+            }
+        }
+        output.push_str(&rest[..1]);
+        rest = &rest[1..];
+    }
+    output.push_str(rest);
+    output
+}
@@ -1,9 +1,56 @@
-#[macro_export]
-macro_rules! check {
-    ($expr:expr) => {
-        match $expr {
-            Ok(_) => {}
-            Err(err) => println!("{:?}", err),
+use crate::error::{Result, YapError};
+
+/**
+ * Parses a duration written as h:m:s, m:s or a number with an optional s/m/h unit
+ */
+pub fn parse_duration(value: &str) -> Result<std::time::Duration> {
+    let invalid = || YapError::Invalid(format!("Invalid time {}", value));
+    let value = value.trim();
+    let seconds = if value.contains(':') {
+        let mut seconds = 0.;
+        for part in value.split(':') {
+            let part = part.parse::<f64>().map_err(|_| invalid())?;
+            if part < 0. {
+                return Err(invalid());
+            }
+            seconds = seconds * 60. + part;
         }
+        seconds
+    } else {
+        let (number, unit) = match value.char_indices().last() {
+            Some((index, 's')) => (&value[..index], 1.),
+            Some((index, 'm')) => (&value[..index], 60.),
+            Some((index, 'h')) => (&value[..index], 3600.),
+            _ => (value, 1.),
+        };
+        number.parse::<f64>().map_err(|_| invalid())? * unit
     };
+    if !seconds.is_finite() || seconds < 0. {
+        return Err(invalid());
+    }
+    Ok(std::time::Duration::from_secs_f64(seconds))
+}
+
+/**
+ * Length in seconds of an audio file, as reported by ffprobe
+ */
+pub fn probe_duration(path: &std::path::Path) -> Option<f64> {
+    crate::audio::properties::probe(path).ok()?.duration
+}
+
+/**
+ * Formats a duration as m:ss, or h:mm:ss when longer than an hour
+ */
+pub fn format_duration(duration: std::time::Duration) -> String {
+    let seconds = duration.as_secs();
+    if seconds >= 3600 {
+        format!(
+            "{}:{:02}:{:02}",
+            seconds / 3600,
+            seconds % 3600 / 60,
+            seconds % 60
+        )
+    } else {
+        format!("{}:{:02}", seconds / 60, seconds % 60)
+    }
 }
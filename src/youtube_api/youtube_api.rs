@@ -1,20 +1,21 @@
-use std::{
-    fs::{self, File},
-    path::Path,
-};
+use std::{fs::File, path::PathBuf};
 
+use async_trait::async_trait;
 use youtube_dl::YoutubeDl;
 
+use crate::error::{Result, YapError};
+use crate::sources::source::{delete_audio_file, yt_dlp_chapters, SongMetadata, Source};
+
 pub struct YoutubeAPI {
-    music_directory: String,
-    miniature_directory: String,
+    music_directory: PathBuf,
+    miniature_directory: PathBuf,
     download_miniature: bool,
 }
 
 impl YoutubeAPI {
     pub fn new(
-        music_directory: String,
-        miniature_directory: String,
+        music_directory: PathBuf,
+        miniature_directory: PathBuf,
         download_miniature: bool,
     ) -> Self {
         Self {
@@ -24,27 +25,111 @@ impl YoutubeAPI {
         }
     }
 
-    pub async fn download_audio(&self, path: &str, song_name: &str) -> anyhow::Result<()> {
+    pub async fn download_audio(&self, path: &str, song_name: &str) -> Result<()> {
         YoutubeDl::new(path)
             .extract_audio(true)
+            //Players look for {id}.opus, whatever format the video offers
+            .extra_arg("--audio-format")
+            .extra_arg("opus")
             .output_template(path)
-            .download_to(Path::new(&self.music_directory))?;
+            .download_to(&self.music_directory)?;
         if self.download_miniature {
-            let result = reqwest::get(format!("https://img.youtube.com/vi/{}/sddefault.jpg", path))
-                .await
-                .unwrap();
-            let mut file = File::create(
-                Path::new(&self.miniature_directory).join(format!("{}.jpg", song_name)),
-            )
-            .unwrap();
-            std::io::copy(&mut result.bytes().await.unwrap().as_ref(), &mut file).unwrap();
+            let result =
+                reqwest::get(format!("https://img.youtube.com/vi/{}/sddefault.jpg", path)).await?;
+            let mut file =
+                File::create(self.miniature_directory.join(format!("{}.jpg", song_name)))?;
+            std::io::copy(&mut result.bytes().await?.as_ref(), &mut file)?;
         }
         Ok(())
     }
 
-    pub fn delete_audio(&self, song_id: &str) -> anyhow::Result<()> {
-        let path = format!("{}{}.opus", self.music_directory, song_id);
-        fs::remove_file(Path::new(&path))?;
-        Ok(())
+    pub fn delete_audio(&self, song_id: &str) -> Result<()> {
+        delete_audio_file(&self.music_directory, song_id)
+    }
+}
+
+fn is_video_id(location: &str) -> bool {
+    location.len() == 11
+        && location
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+fn is_youtube_url(location: &str) -> bool {
+    let host = location
+        .trim_start_matches("https://")
+        .trim_start_matches("http://")
+        .split('/')
+        .next()
+        .unwrap_or_default();
+    host == "youtu.be" || host == "youtube.com" || host.ends_with(".youtube.com")
+}
+
+/**
+ * The video id of a youtube url, or the location itself when it already is one
+ */
+pub fn video_id(location: &str) -> Option<String> {
+    if is_video_id(location) {
+        return Some(location.to_string());
+    }
+    if !is_youtube_url(location) {
+        return None;
+    }
+    let (host, path) = location
+        .trim_start_matches("https://")
+        .trim_start_matches("http://")
+        .split_once('/')?;
+    let id = if host == "youtu.be" {
+        path.split(['?', '#', '/']).next()
+    } else if let Some((_, query)) = path.split_once('?') {
+        query
+            .split(['&', '#'])
+            .find_map(|parameter| parameter.strip_prefix("v="))
+    } else {
+        //youtube.com/shorts/<id>, /embed/<id> or /live/<id>
+        path.split('/').nth(1)
+    }?;
+    is_video_id(id).then(|| id.to_string())
+}
+
+#[async_trait(?Send)]
+impl Source for YoutubeAPI {
+    fn name(&self) -> &'static str {
+        "youtube"
+    }
+
+    fn handles(&self, location: &str) -> bool {
+        is_video_id(location) || is_youtube_url(location)
+    }
+
+    async fn resolve(&self, location: &str) -> Result<SongMetadata> {
+        let video =
+            YoutubeDl::new(location)
+                .run()?
+                .into_single_video()
+                .ok_or(YapError::Download(format!(
+                    "{} is not a single video",
+                    location
+                )))?;
+        Ok(SongMetadata {
+            id: video.id,
+            title: video.title,
+            artist: video.artist,
+            chapters: yt_dlp_chapters(video.chapters),
+        })
+    }
+
+    async fn download(&self, _location: &str, metadata: &SongMetadata) -> Result<()> {
+        let song_name = metadata.title.as_deref().unwrap_or(&metadata.id);
+        self.download_audio(&metadata.id, song_name).await
+    }
+
+    fn delete(&self, song_id: &str) -> Result<()> {
+        self.delete_audio(song_id)
+    }
+
+    fn redownload_location(&self, song_id: &str) -> Option<String> {
+        //Songs cut from chapters have longer ids, only whole videos can be downloaded again
+        is_video_id(song_id).then(|| song_id.to_string())
     }
 }
@@ -0,0 +1,236 @@
+mod common;
+
+use std::path::Path;
+
+use common::TestLibrary;
+use yap::{
+    backup::{
+        archive::format_of,
+        backup::{create_backup, restore_backup, RestoreMode},
+    },
+    config::Config,
+    db::models::{NewPlaylistSong, NewSong, SongLoudness, SongProperties},
+    error::YapError,
+};
+
+fn config_of(directory: &Path) -> Config {
+    let mut config = Config::default();
+    config.general.music_directory = directory.to_path_buf();
+    config.general.miniature_directory = directory.join("miniatures");
+    config.database.database_path = directory.join("yap.db");
+    config
+}
+
+#[tokio::test]
+async fn backups_merge_into_another_library() {
+    let library = TestLibrary::new();
+    library.download("a").await;
+    library.download("b").await;
+    library
+        .handler
+        .create_playlist(
+            "P",
+            vec!["Title of b".to_string(), "Title of a".to_string()],
+        )
+        .unwrap();
+    let config = config_of(library.directory.path());
+    let config_path = library.directory.path().join("yap.toml");
+    config.store(&config_path).unwrap();
+    let archive = library.directory.path().join("backup.tar.gz");
+    let manifest = create_backup(&config, &config_path, &archive, true, false).unwrap();
+    assert_eq!(manifest.songs, 2);
+    assert_eq!(manifest.playlists, 1);
+
+    let other = TestLibrary::new();
+    other.download("a").await;
+    let other_config = config_of(other.directory.path());
+    let other_config_path = other.directory.path().join("yap.toml");
+
+    let preview = restore_backup(
+        &other_config,
+        &other_config_path,
+        &archive,
+        RestoreMode::Merge,
+        true,
+        None,
+    )
+    .unwrap();
+    assert_eq!(preview.songs_added, ["Title of b"]);
+    assert_eq!(preview.playlists_added, ["P"]);
+    assert_eq!(preview.files_copied, 1);
+    assert!(!other.music_file("b"));
+
+    let report = restore_backup(
+        &other_config,
+        &other_config_path,
+        &archive,
+        RestoreMode::Merge,
+        false,
+        None,
+    )
+    .unwrap();
+    assert_eq!(report.songs_added, ["Title of b"]);
+    assert!(!report.config_replaced);
+    assert!(other.music_file("b"));
+    let songs = other.database.get_songs_of_playlist("P").unwrap();
+    let ids: Vec<&str> = songs.iter().map(|song| song.song_id.as_str()).collect();
+    assert_eq!(ids, ["b", "a"]);
+    assert!(!other_config_path.exists());
+}
+
+#[tokio::test]
+async fn replacing_drops_songs_missing_from_the_backup() {
+    let library = TestLibrary::new();
+    library.download("a").await;
+    let config = config_of(library.directory.path());
+    let config_path = library.directory.path().join("yap.toml");
+    let archive = library.directory.path().join("backup.zip");
+    create_backup(&config, &config_path, &archive, false, false).unwrap();
+
+    library.download("b").await;
+    let report = restore_backup(
+        &config,
+        &config_path,
+        &archive,
+        RestoreMode::Replace,
+        false,
+        None,
+    )
+    .unwrap();
+    assert_eq!(report.songs_removed, ["Title of b"]);
+    assert!(library.database.get_song_by_id("b").unwrap().is_none());
+    assert!(library.database.get_song_by_id("a").unwrap().is_some());
+}
+
+#[tokio::test]
+async fn merging_keeps_trims_and_measurements() {
+    let library = TestLibrary::new();
+    library.download("a").await;
+    let seconds = std::time::Duration::from_secs;
+    library
+        .handler
+        .trim_song("Title of a", Some(seconds(3)), Some(seconds(90)))
+        .unwrap();
+    library
+        .database
+        .set_song_loudness(
+            "a",
+            SongLoudness {
+                loudness: -12.,
+                true_peak: -0.5,
+                gain: -6.,
+            },
+        )
+        .unwrap();
+    library
+        .database
+        .set_song_properties(
+            "a",
+            SongProperties {
+                duration: Some(120.),
+                bitrate: Some(128000),
+                sample_rate: Some(48000),
+                channels: Some(2),
+                codec: Some("opus".to_string()),
+                file_size: Some(7),
+            },
+        )
+        .unwrap();
+    let config = config_of(library.directory.path());
+    let config_path = library.directory.path().join("yap.toml");
+    let archive = library.directory.path().join("backup.tar");
+    create_backup(&config, &config_path, &archive, false, false).unwrap();
+
+    let other = TestLibrary::new();
+    restore_backup(
+        &config_of(other.directory.path()),
+        &other.directory.path().join("yap.toml"),
+        &archive,
+        RestoreMode::Merge,
+        false,
+        None,
+    )
+    .unwrap();
+    let song = other.database.get_song_by_id("a").unwrap().unwrap();
+    assert_eq!((song.start_offset, song.end_offset), (Some(3.), Some(90.)));
+    assert_eq!((song.loudness, song.gain), (Some(-12.), Some(-6.)));
+    assert_eq!(
+        (song.duration, song.codec.as_deref()),
+        (Some(120.), Some("opus"))
+    );
+}
+
+#[tokio::test]
+async fn merging_keeps_playlist_order_and_chapter_parents() {
+    let library = TestLibrary::new();
+    library.download("a").await;
+    library.download("b").await;
+    library
+        .database
+        .insert_song(NewSong {
+            id: "mix-chapters-01",
+            name: "Intro",
+            artist: None,
+            source: "fake://mix-chapters",
+            parent_id: Some("mix-chapters"),
+        })
+        .unwrap();
+    for (playlist, songs) in [("P", ["a", "b", "a"]), ("Q", ["b", "a", "b"])] {
+        library
+            .handler
+            .create_playlist(playlist, Vec::new())
+            .unwrap();
+        for song_id in songs {
+            library
+                .database
+                .append_to_playlist(NewPlaylistSong {
+                    playlist_name: playlist,
+                    song_id,
+                })
+                .unwrap();
+        }
+    }
+    let config = config_of(library.directory.path());
+    let config_path = library.directory.path().join("yap.toml");
+    let archive = library.directory.path().join("backup.tar");
+    create_backup(&config, &config_path, &archive, false, false).unwrap();
+
+    let other = TestLibrary::new();
+    other.download("b").await;
+    other
+        .handler
+        .create_playlist("Q", vec!["Title of b".to_string()])
+        .unwrap();
+    let report = restore_backup(
+        &config_of(other.directory.path()),
+        &other.directory.path().join("yap.toml"),
+        &archive,
+        RestoreMode::Merge,
+        false,
+        None,
+    )
+    .unwrap();
+    assert_eq!(report.playlists_added, ["P"]);
+    assert_eq!(report.playlists_merged, ["Q"]);
+
+    let ids_of = |playlist: &str| -> Vec<String> {
+        other
+            .database
+            .get_songs_of_playlist(playlist)
+            .unwrap()
+            .into_iter()
+            .map(|song| song.song_id)
+            .collect()
+    };
+    assert_eq!(ids_of("P"), ["a", "b", "a"]);
+    //Existing playlists keep their order and get the missing songs at the end
+    assert_eq!(ids_of("Q"), ["b", "a"]);
+    let chapter = other.database.get_song_by_id("mix-chapters-01").unwrap();
+    assert_eq!(chapter.unwrap().parent_id.as_deref(), Some("mix-chapters"));
+}
+
+#[test]
+fn unknown_archive_formats_are_configuration_errors() {
+    let error = format_of(Path::new("backup.rar")).unwrap_err();
+    assert!(matches!(error, YapError::Config(_)));
+}
@@ -0,0 +1,97 @@
+mod common;
+
+use common::{fake_chapters, TestLibrary};
+use yap::{audio::chapters::chapter_songs, db::models::NewSong, error::YapError};
+
+const MIX: &str = "fake://mix-chapters";
+
+fn add_chapter(library: &TestLibrary, number: usize, name: &str) {
+    library
+        .database
+        .insert_song(NewSong {
+            id: &format!("mix-chapters-{:02}", number),
+            name,
+            artist: None,
+            source: "fake",
+            parent_id: Some("mix-chapters"),
+        })
+        .unwrap();
+}
+
+#[test]
+fn chapters_are_numbered_and_end_where_the_next_starts() {
+    let songs = chapter_songs("mix", "Mix", &fake_chapters());
+    let ids: Vec<&str> = songs.iter().map(|song| song.id.as_str()).collect();
+    assert_eq!(ids, ["mix-01", "mix-02", "mix-03"]);
+    let names: Vec<&str> = songs.iter().map(|song| song.name.as_str()).collect();
+    assert_eq!(names, ["Intro", "Mix - part 2", "Outro"]);
+    let ends: Vec<Option<f64>> = songs.iter().map(|song| song.end).collect();
+    assert_eq!(ends, [Some(60.), Some(200.), None]);
+}
+
+#[tokio::test]
+async fn videos_without_chapters_are_not_split() {
+    let library = TestLibrary::new();
+    let error = library
+        .handler
+        .download_chapters("fake://a", None, true)
+        .await
+        .unwrap_err();
+    assert!(matches!(error, YapError::NotFound(_)));
+    assert!(library.database.get_songs().unwrap().is_empty());
+    assert!(library.database.get_playlists().unwrap().is_empty());
+    assert!(!library.music_file("a"));
+}
+
+#[tokio::test]
+async fn chapters_in_the_library_are_grouped_without_downloading() {
+    let library = TestLibrary::new();
+    add_chapter(&library, 1, "Intro");
+    add_chapter(&library, 2, "Part 2");
+    add_chapter(&library, 3, "Outro");
+
+    let download = library
+        .handler
+        .download_chapters(MIX, None, true)
+        .await
+        .unwrap();
+    assert_eq!(download.title, "Title of mix-chapters");
+    assert_eq!(download.existing.len(), 3);
+    assert_eq!(download.playlist.as_deref(), Some("Title of mix-chapters"));
+    assert!(!library.music_file("mix-chapters"));
+    let songs: Vec<String> = library
+        .database
+        .get_songs_of_playlist("Title of mix-chapters")
+        .unwrap()
+        .into_iter()
+        .map(|song| song.song_id)
+        .collect();
+    assert_eq!(
+        songs,
+        ["mix-chapters-01", "mix-chapters-02", "mix-chapters-03"]
+    );
+}
+
+#[tokio::test]
+async fn failed_splits_leave_the_library_as_it_was() {
+    let library = TestLibrary::new();
+    add_chapter(&library, 1, "Intro");
+
+    //The fixture isn't audio, so the second chapter can't be cut
+    assert!(library
+        .handler
+        .download_chapters(MIX, None, true)
+        .await
+        .is_err());
+    let songs: Vec<String> = library
+        .database
+        .get_songs()
+        .unwrap()
+        .into_iter()
+        .map(|song| song.id)
+        .collect();
+    assert_eq!(songs, ["mix-chapters-01"]);
+    assert!(library.database.get_playlists().unwrap().is_empty());
+    assert!(!library.music_file("mix-chapters"));
+    assert!(!library.music_file("mix-chapters-02"));
+}
@@ -0,0 +1,185 @@
+//Each test crate only uses part of the helpers
+#![allow(dead_code)]
+
+use std::{
+    fs,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
+
+use async_trait::async_trait;
+use tempfile::TempDir;
+use yap::{
+    config,
+    db::{database::Database, models::Song},
+    error::{Result, YapError},
+    handler::Handler,
+    notifications::notifier::Notifier,
+    player::local_player::LocalPlayer,
+    sources::source::{audio_file, delete_audio_file, Chapter, SongMetadata, Source, Sources},
+};
+
+/**
+ * Source that "downloads" by writing a fixture file, locations are fake://<id>.
+ * Videos whose id ends with chapters have three: Intro, an untitled one and Outro
+ */
+pub struct FakeSource {
+    music_directory: PathBuf,
+}
+
+pub fn fake_chapters() -> Vec<Chapter> {
+    vec![
+        Chapter {
+            title: Some("Intro".to_string()),
+            start: 0.,
+            end: Some(60.),
+        },
+        Chapter {
+            title: None,
+            start: 60.,
+            end: None,
+        },
+        Chapter {
+            title: Some("Outro".to_string()),
+            start: 200.,
+            end: None,
+        },
+    ]
+}
+
+#[async_trait(?Send)]
+impl Source for FakeSource {
+    fn name(&self) -> &'static str {
+        "fake"
+    }
+
+    fn handles(&self, location: &str) -> bool {
+        location.starts_with("fake://")
+    }
+
+    async fn resolve(&self, location: &str) -> Result<SongMetadata> {
+        let id = location.trim_start_matches("fake://");
+        Ok(SongMetadata {
+            id: id.to_string(),
+            title: Some(format!("Title of {}", id)),
+            artist: Some("Fake artist".to_string()),
+            chapters: match id.ends_with("chapters") {
+                true => fake_chapters(),
+                false => Vec::new(),
+            },
+        })
+    }
+
+    async fn download(&self, location: &str, metadata: &SongMetadata) -> Result<()> {
+        if location.ends_with("broken") {
+            return Err(YapError::Download("Download failed".to_string()));
+        }
+        fs::write(audio_file(&self.music_directory, &metadata.id), b"fixture")?;
+        Ok(())
+    }
+
+    fn delete(&self, song_id: &str) -> Result<()> {
+        delete_audio_file(&self.music_directory, song_id)
+    }
+
+    fn redownload_location(&self, song_id: &str) -> Option<String> {
+        Some(format!("fake://{}", song_id))
+    }
+}
+
+/**
+ * Notifier remembering the ids of the songs it was told about instead of showing them
+ */
+pub struct StubNotifier {
+    songs: Arc<Mutex<Vec<String>>>,
+}
+
+impl Notifier for StubNotifier {
+    fn is_enabled(&self) -> bool {
+        true
+    }
+
+    fn song_changed(&self, song: &Song) -> Result<()> {
+        self.songs.lock().unwrap().push(song.id.clone());
+        Ok(())
+    }
+}
+
+/**
+ * A library in a temporary directory, removed when dropped
+ */
+pub struct TestLibrary {
+    pub directory: TempDir,
+    pub database: Database,
+    pub handler: Handler,
+    //Songs notified by the handler
+    pub notified: Arc<Mutex<Vec<String>>>,
+}
+
+impl TestLibrary {
+    pub fn new() -> Self {
+        let directory = tempfile::tempdir().unwrap();
+        let music_directory = directory.path().to_path_buf();
+        let database = Database::new(
+            format!("sqlite://{}", directory.path().join("yap.db").display()),
+            4,
+        )
+        .unwrap();
+        database.run_embedded_migrations().unwrap();
+        let sources = Sources::new(vec![Box::new(FakeSource {
+            music_directory: music_directory.clone(),
+        })]);
+        let player = LocalPlayer::new(
+            database.clone(),
+            music_directory.clone(),
+            config::Player::default(),
+        );
+        let notified = Arc::new(Mutex::new(Vec::new()));
+        let notifier = StubNotifier {
+            songs: notified.clone(),
+        };
+        let handler = Handler::new(
+            database.clone(),
+            sources,
+            Box::new(player),
+            Box::new(notifier),
+            music_directory,
+            config::TrimSilence::Off,
+        )
+        .unwrap();
+        Self {
+            directory,
+            database,
+            handler,
+            notified,
+        }
+    }
+
+    /**
+     * A library with songs a and b, in a playlist holding b then a
+     */
+    pub async fn with_playlist(name: &str) -> Self {
+        let library = Self::new();
+        library.download("a").await;
+        library.download("b").await;
+        library
+            .handler
+            .create_playlist(
+                name,
+                vec!["Title of b".to_string(), "Title of a".to_string()],
+            )
+            .unwrap();
+        library
+    }
+
+    pub fn music_file(&self, song_id: &str) -> bool {
+        audio_file(self.directory.path(), song_id).is_file()
+    }
+
+    pub async fn download(&self, song_id: &str) {
+        self.handler
+            .add_song_to_registry(&format!("fake://{}", song_id), None, None)
+            .await
+            .unwrap();
+    }
+}
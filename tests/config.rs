@@ -0,0 +1,92 @@
+use std::path::PathBuf;
+
+use yap::{
+    config::{Backend, Config, Profile},
+    error::YapError,
+};
+
+#[test]
+fn settings_are_read_and_written_by_key() {
+    let mut config = Config::default();
+    assert_eq!(
+        config.get("player.backend").unwrap(),
+        toml::Value::String("mpd".to_string())
+    );
+
+    config.set("player.backend", "local").unwrap();
+    config.set("notifications.timeout", "1000").unwrap();
+    config.set("general.download_miniature", "true").unwrap();
+    assert!(config.player.backend == Backend::Local);
+    assert_eq!(config.notifications.timeout, 1000);
+    assert!(config.general.download_miniature);
+}
+
+#[test]
+fn invalid_settings_are_rejected() {
+    let mut config = Config::default();
+    assert!(matches!(
+        config.get("general.missing"),
+        Err(YapError::NotFound(_))
+    ));
+    assert!(matches!(
+        config.set("player.backend", "vlc"),
+        Err(YapError::Config(_))
+    ));
+    assert!(matches!(
+        config.set("notifications.timeout", "soon"),
+        Err(YapError::Config(_))
+    ));
+    assert!(matches!(
+        config.set("player", "local"),
+        Err(YapError::NotFound(_))
+    ));
+    assert!(config.player.backend == Backend::Mpd);
+}
+
+#[test]
+fn config_survives_a_round_trip() {
+    let directory = tempfile::tempdir().unwrap();
+    let path = directory.path().join("yap.toml");
+    let mut config = Config::load(&path).unwrap();
+    config.set("general.music_directory", "/srv/music").unwrap();
+    config.store(&path).unwrap();
+    let loaded = Config::load(&path).unwrap();
+    assert_eq!(
+        loaded.general.music_directory.to_string_lossy(),
+        "/srv/music"
+    );
+}
+
+#[test]
+fn profiles_override_the_main_configuration() {
+    let mut config = Config::default();
+    config.profiles.insert(
+        "office".to_string(),
+        Profile {
+            database_path: Some(PathBuf::from("/srv/office/yap.db")),
+            mpd_address: Some("10.0.0.5:6600".to_string()),
+            ..Default::default()
+        },
+    );
+    config.profile = Some("office".to_string());
+
+    let mut home = config.clone();
+    home.select_profile(Some("default")).unwrap();
+    assert_eq!(home.mpd.address, "127.0.0.1:6600");
+
+    config.select_profile(None).unwrap();
+    assert_eq!(
+        config.database.database_path,
+        PathBuf::from("/srv/office/yap.db")
+    );
+    assert_eq!(config.mpd.address, "10.0.0.5:6600");
+    assert_eq!(
+        config.general.music_directory,
+        PathBuf::from("~/Music/songs")
+    );
+
+    assert!(matches!(
+        config.select_profile(Some("jukebox")),
+        Err(YapError::NotFound(_))
+    ));
+}
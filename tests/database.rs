@@ -0,0 +1,118 @@
+use yap::{
+    db::{
+        database::Database,
+        models::{NewPlaylist, NewPlaylistSong, NewSong, SongLoudness},
+    },
+    error::YapError,
+};
+
+fn database() -> (tempfile::TempDir, Database) {
+    let directory = tempfile::tempdir().unwrap();
+    let database = Database::new(
+        format!("sqlite://{}", directory.path().join("yap.db").display()),
+        4,
+    )
+    .unwrap();
+    database.run_embedded_migrations().unwrap();
+    (directory, database)
+}
+
+fn insert_song(database: &Database, id: &str, name: &str) {
+    database
+        .insert_song(NewSong {
+            id,
+            name,
+            artist: None,
+            source: "youtube",
+            parent_id: None,
+        })
+        .unwrap();
+}
+
+#[test]
+fn songs_are_unique_by_id() {
+    let (_directory, database) = database();
+    insert_song(&database, "a", "A");
+    let duplicate = database.insert_song(NewSong {
+        id: "a",
+        name: "Other",
+        artist: None,
+        source: "youtube",
+        parent_id: None,
+    });
+    assert!(matches!(duplicate, Err(YapError::AlreadyExists(_))));
+    let song = database.get_song_by_name("A").unwrap().unwrap();
+    assert_eq!(song.id, "a");
+    assert_eq!(song.source, "youtube");
+    assert_eq!(database.get_songs().unwrap().len(), 1);
+}
+
+#[test]
+fn playlist_songs_keep_insertion_order() {
+    let (_directory, database) = database();
+    for (id, name) in [("c", "C"), ("a", "A"), ("b", "B")] {
+        insert_song(&database, id, name);
+    }
+    database.insert_playlist(NewPlaylist { name: "P" }).unwrap();
+    for song_id in ["c", "a", "b"] {
+        database
+            .add_songs_to_playlist(NewPlaylistSong {
+                playlist_name: "P",
+                song_id,
+            })
+            .unwrap();
+    }
+    assert!(matches!(
+        database.insert_playlist(NewPlaylist { name: "P" }),
+        Err(YapError::AlreadyExists(_))
+    ));
+    assert!(database.delete_playlist_song("P", "a").unwrap());
+    assert!(!database.delete_playlist_song("P", "a").unwrap());
+
+    let songs: Vec<String> = database
+        .get_songs_of_playlist("P")
+        .unwrap()
+        .into_iter()
+        .map(|song| song.song_id)
+        .collect();
+    assert_eq!(songs, vec!["c", "b"]);
+}
+
+#[test]
+fn settings_compare_and_set() {
+    let (_directory, database) = database();
+    assert_eq!(database.get_setting("key").unwrap(), None);
+    assert!(database.compare_and_set_setting("key", None, "1").unwrap());
+    assert!(!database.compare_and_set_setting("key", None, "2").unwrap());
+    assert!(!database
+        .compare_and_set_setting("key", Some("0"), "2")
+        .unwrap());
+    assert!(database
+        .compare_and_set_setting("key", Some("1"), "2")
+        .unwrap());
+    assert_eq!(database.get_setting("key").unwrap(), Some("2".to_string()));
+}
+
+#[test]
+fn loudness_is_stored_once_analyzed() {
+    let (_directory, database) = database();
+    insert_song(&database, "a", "A");
+    let song = database.get_song_by_id("a").unwrap().unwrap();
+    assert_eq!(song.loudness, None);
+
+    database
+        .set_song_loudness(
+            "a",
+            SongLoudness {
+                loudness: -9.5,
+                true_peak: 0.4,
+                gain: -8.5,
+            },
+        )
+        .unwrap();
+    let song = database.get_song_by_id("a").unwrap().unwrap();
+    assert_eq!(
+        (song.loudness, song.true_peak, song.gain),
+        (Some(-9.5), Some(0.4), Some(-8.5))
+    );
+}
@@ -0,0 +1,314 @@
+mod common;
+
+use chrono::{Duration, Local};
+use common::TestLibrary;
+use yap::{
+    config,
+    error::YapError,
+    player::{
+        backend::{PlayRange, Player},
+        local_player::LocalPlayer,
+    },
+};
+
+#[tokio::test]
+async fn download_names_song_after_its_title() {
+    let library = TestLibrary::new();
+    let added = library
+        .handler
+        .add_song_to_registry("fake://abc", None, None)
+        .await
+        .unwrap();
+    let song = added.song;
+    assert_eq!(song.id, "abc");
+    assert_eq!(song.name, "Title of abc");
+    assert_eq!(song.artist.as_deref(), Some("Fake artist"));
+    assert_eq!(song.source, "fake");
+    assert!(library.music_file("abc"));
+    //The fixture isn't audio, the song is added but can't be analyzed
+    assert_eq!(added.warnings.len(), 1);
+    assert!(added.warnings[0].to_string().contains("library analyze"));
+
+    let added = library
+        .handler
+        .add_song_to_registry("fake://def", Some("Named".to_string()), None)
+        .await
+        .unwrap();
+    assert_eq!(added.song.name, "Named");
+}
+
+#[tokio::test]
+async fn failed_download_is_not_registered() {
+    let library = TestLibrary::new();
+    assert!(library
+        .handler
+        .add_song_to_registry("fake://broken", None, None)
+        .await
+        .is_err());
+    assert!(library.handler.get_songs().unwrap().is_empty());
+    assert!(library
+        .handler
+        .add_song_to_registry("youtube.com/watch?v=x", None, None)
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn delete_song_removes_file_and_row() {
+    let library = TestLibrary::new();
+    library.download("abc").await;
+    let song = library.handler.delete_song("Title of abc").unwrap();
+    assert_eq!(song.id, "abc");
+    assert!(!library.music_file("abc"));
+    assert!(library.database.get_song_by_id("abc").unwrap().is_none());
+    assert!(library.handler.delete_song("Title of abc").is_err());
+}
+
+#[tokio::test]
+async fn playlists_are_created_from_existing_songs() {
+    let library = TestLibrary::new();
+    library.download("a").await;
+    library.download("b").await;
+
+    assert!(library
+        .handler
+        .create_playlist(
+            "Missing",
+            vec!["Title of a".to_string(), "Nope".to_string()]
+        )
+        .is_err());
+    assert!(library
+        .database
+        .get_playlist_by_name("Missing")
+        .unwrap()
+        .is_none());
+
+    let songs = library
+        .handler
+        .create_playlist(
+            "P",
+            vec!["Title of b".to_string(), " Title of a".to_string()],
+        )
+        .unwrap();
+    let ids: Vec<&str> = songs.iter().map(|song| song.id.as_str()).collect();
+    assert_eq!(ids, vec!["b", "a"]);
+    assert_eq!(library.handler.get_playlists().unwrap().len(), 1);
+
+    library.handler.delete_playlist("P").unwrap();
+    assert!(library.handler.get_playlists().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn queue_resolves_songs_and_saves_as_playlist() {
+    let library = TestLibrary::new();
+    library.download("a").await;
+    library.download("b").await;
+    library.handler.add_to_queue("Title of a", false).unwrap();
+    library.handler.add_to_queue("Title of b", false).unwrap();
+    library.handler.add_to_queue("Title of a", false).unwrap();
+
+    let queue = library.handler.queue().unwrap();
+    let names: Vec<String> = queue
+        .iter()
+        .map(|entry| entry.song.as_ref().unwrap().name.clone())
+        .collect();
+    assert_eq!(names, vec!["Title of a", "Title of b", "Title of a"]);
+
+    let skipped = library.handler.save_queue("Saved").unwrap();
+    assert!(skipped.is_empty());
+    let saved: Vec<String> = library
+        .database
+        .get_songs_of_playlist("Saved")
+        .unwrap()
+        .into_iter()
+        .map(|song| song.song_id)
+        .collect();
+    assert_eq!(saved, vec!["a", "b", "a"]);
+
+    assert_eq!(library.handler.dedupe_queue().unwrap(), 1);
+    assert_eq!(
+        library
+            .handler
+            .remove_from_queue("Title of b", false)
+            .unwrap(),
+        1
+    );
+    assert_eq!(library.handler.queue().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn clear_queue_can_be_restored() {
+    let library = TestLibrary::new();
+    library.download("a").await;
+    library.download("b").await;
+    library.handler.add_to_queue("Title of a", false).unwrap();
+    library.handler.add_to_queue("Title of b", false).unwrap();
+    library.handler.clear_queue().unwrap();
+    assert!(library.handler.queue().unwrap().is_empty());
+    library.download("c").await;
+    library.handler.add_to_queue("Title of c", false).unwrap();
+
+    library.handler.restore_queue().unwrap();
+    let ids = |library: &TestLibrary| -> Vec<String> {
+        library
+            .handler
+            .queue()
+            .unwrap()
+            .iter()
+            .map(|entry| entry.song_id())
+            .collect()
+    };
+    assert_eq!(ids(&library), vec!["a", "b"]);
+
+    //The queue replaced by the restore was saved first
+    library.handler.restore_queue().unwrap();
+    assert_eq!(ids(&library), vec!["c"]);
+}
+
+#[tokio::test]
+async fn restored_queues_keep_the_trim_they_were_queued_with() {
+    let library = TestLibrary::new();
+    library.download("a").await;
+    let seconds = std::time::Duration::from_secs;
+    library
+        .handler
+        .trim_song("Title of a", Some(seconds(5)), Some(seconds(60)))
+        .unwrap();
+    library.handler.add_to_queue("Title of a", false).unwrap();
+    library.handler.clear_queue().unwrap();
+    library.handler.reset_trim("Title of a").unwrap();
+
+    library.handler.restore_queue().unwrap();
+    let player = LocalPlayer::new(
+        library.database.clone(),
+        library.directory.path().to_path_buf(),
+        config::Player::default(),
+    );
+    assert_eq!(
+        player.snapshot().unwrap().ranges,
+        vec![PlayRange {
+            start: Some(5.),
+            end: Some(60.)
+        }]
+    );
+}
+
+#[test]
+fn volume_and_mute() {
+    let library = TestLibrary::new();
+    assert_eq!(library.handler.volume(Some("40".to_string())).unwrap(), 40);
+    assert_eq!(
+        library.handler.volume(Some("+70".to_string())).unwrap(),
+        100
+    );
+    assert_eq!(library.handler.volume(Some("-30".to_string())).unwrap(), 70);
+    assert!(library.handler.volume(Some("loud".to_string())).is_err());
+
+    assert!(library.handler.mute().unwrap());
+    assert_eq!(library.handler.volume(None).unwrap(), 0);
+    assert!(!library.handler.mute().unwrap());
+    assert_eq!(library.handler.volume(None).unwrap(), 70);
+}
+
+#[tokio::test]
+async fn due_alarm_starts_playlist_and_moves_to_next_day() {
+    let library = TestLibrary::new();
+    library.download("a").await;
+    library
+        .handler
+        .create_playlist("Morning", vec!["Title of a".to_string()])
+        .unwrap();
+    let time = (Local::now() + Duration::minutes(5))
+        .format("%H:%M")
+        .to_string();
+    let run_at = library.handler.alarm(&time, "Morning", 0, true).unwrap();
+
+    assert!(library
+        .handler
+        .run_due_schedules(run_at - Duration::minutes(1))
+        .unwrap()
+        .is_empty());
+
+    let runs = library.handler.run_due_schedules(run_at).unwrap();
+    assert_eq!(runs.len(), 1);
+    assert!(runs[0].result.is_ok());
+    let (status, song) = library.handler.status().unwrap();
+    assert_eq!(status.state, "play");
+    assert_eq!(song.unwrap().id, "a");
+
+    let schedules = library.handler.get_schedules().unwrap();
+    assert_eq!(schedules.len(), 1);
+    assert!(schedules[0].run_at > run_at.timestamp());
+}
+
+#[tokio::test]
+async fn errors_keep_their_kind() {
+    let library = TestLibrary::new();
+    library.download("abc").await;
+
+    let error = library
+        .handler
+        .add_song_to_registry("fake://abc", None, None)
+        .await
+        .unwrap_err();
+    assert!(matches!(error, YapError::AlreadyExists(_)));
+    assert_eq!(error.exit_code(), 3);
+
+    let error = library.handler.play_song("Missing").unwrap_err();
+    assert!(matches!(error, YapError::NotFound(_)));
+    assert_eq!(error.exit_code(), 2);
+
+    let error = library
+        .handler
+        .add_song_to_registry("fake://broken", None, None)
+        .await
+        .unwrap_err();
+    assert_eq!(error.exit_code(), 6);
+
+    let error = library
+        .handler
+        .volume(Some("loud".to_string()))
+        .unwrap_err();
+    assert!(matches!(error, YapError::Invalid(_)));
+    assert_eq!(error.exit_code(), 11);
+
+    let error = library
+        .handler
+        .alarm("25:00", "Morning", 0, false)
+        .unwrap_err();
+    assert_eq!(error.exit_code(), 11);
+}
+
+#[tokio::test]
+async fn trim_keeps_the_offsets_not_given() {
+    let library = TestLibrary::new();
+    library.download("a").await;
+    let seconds = std::time::Duration::from_secs;
+
+    let song = library
+        .handler
+        .trim_song("Title of a", Some(seconds(12)), Some(seconds(220)))
+        .unwrap();
+    assert_eq!(
+        (song.start_offset, song.end_offset),
+        (Some(12.), Some(220.))
+    );
+
+    let song = library
+        .handler
+        .trim_song("Title of a", Some(seconds(10)), None)
+        .unwrap();
+    assert_eq!(
+        (song.start_offset, song.end_offset),
+        (Some(10.), Some(220.))
+    );
+
+    let error = library
+        .handler
+        .trim_song("Title of a", Some(seconds(300)), None)
+        .unwrap_err();
+    assert!(matches!(error, YapError::Invalid(_)));
+
+    let song = library.handler.reset_trim("Title of a").unwrap();
+    assert_eq!((song.start_offset, song.end_offset), (None, None));
+}
@@ -0,0 +1,117 @@
+mod common;
+
+use common::TestLibrary;
+use yap::{
+    db::models::SongLoudness,
+    library::manifest::{LibraryManifest, MANIFEST_VERSION},
+};
+
+#[tokio::test]
+async fn import_downloads_the_exported_library_again() {
+    let library = TestLibrary::with_playlist("Mix").await;
+    let json = library.handler.export_library().unwrap().to_json().unwrap();
+
+    let other = TestLibrary::new();
+    other.download("c").await;
+    let manifest = LibraryManifest::from_json(&json).unwrap();
+    assert_eq!(manifest.version, MANIFEST_VERSION);
+    assert_eq!(manifest.playlists[0].songs, vec!["b", "a"]);
+
+    let report = other.handler.import_library(manifest, true).await.unwrap();
+    assert_eq!(report.downloaded.len(), 2);
+    assert_eq!(report.existing, 0);
+    assert_eq!(report.playlists_added, vec!["Mix"]);
+    assert!(other.music_file("a") && other.music_file("b"));
+    let songs: Vec<String> = other
+        .database
+        .get_songs_of_playlist("Mix")
+        .unwrap()
+        .into_iter()
+        .map(|song| song.song_id)
+        .collect();
+    assert_eq!(songs, vec!["b", "a"]);
+
+    //Importing twice changes nothing
+    let manifest = LibraryManifest::from_json(&json).unwrap();
+    let report = other.handler.import_library(manifest, true).await.unwrap();
+    assert_eq!(report.existing, 2);
+    assert!(report.downloaded.is_empty() && report.playlists_merged.is_empty());
+}
+
+#[tokio::test]
+async fn missing_songs_are_skipped_without_downloads() {
+    let library = TestLibrary::with_playlist("Mix").await;
+    let manifest = library.handler.export_library().unwrap();
+
+    let other = TestLibrary::new();
+    other.download("a").await;
+    let report = other.handler.import_library(manifest, false).await.unwrap();
+    assert_eq!(report.existing, 1);
+    assert_eq!(report.skipped.len(), 1);
+    assert!(!other.music_file("b"));
+    assert_eq!(
+        other.database.get_songs_of_playlist("Mix").unwrap().len(),
+        1
+    );
+}
+
+#[tokio::test]
+async fn import_keeps_trims_and_replay_gain_tags() {
+    let library = TestLibrary::with_playlist("Mix").await;
+    let seconds = std::time::Duration::from_secs;
+    library
+        .handler
+        .trim_song("Title of a", Some(seconds(5)), Some(seconds(60)))
+        .unwrap();
+    library
+        .database
+        .set_song_loudness(
+            "a",
+            SongLoudness {
+                loudness: -14.,
+                true_peak: -1.,
+                gain: -4.,
+            },
+        )
+        .unwrap();
+    let json = library.handler.export_library().unwrap().to_json().unwrap();
+
+    let other = TestLibrary::new();
+    let manifest = LibraryManifest::from_json(&json).unwrap();
+    other.handler.import_library(manifest, true).await.unwrap();
+    let song = other.database.get_song_by_id("a").unwrap().unwrap();
+    assert_eq!((song.start_offset, song.end_offset), (Some(5.), Some(60.)));
+    assert_eq!((song.loudness, song.gain), (Some(-14.), Some(-4.)));
+    let song = other.database.get_song_by_id("b").unwrap().unwrap();
+    assert_eq!((song.start_offset, song.loudness), (None, None));
+}
+
+#[test]
+fn first_version_manifests_are_still_read() {
+    let json = r#"{"version": 1, "exported_at": "", "playlists": [],
+        "songs": [{"id": "a", "name": "A", "artist": null, "source": "fake"}]}"#;
+    let manifest = LibraryManifest::from_json(json).unwrap();
+    assert_eq!(manifest.songs[0].start_offset, None);
+}
+
+#[test]
+fn newer_manifests_are_rejected() {
+    let json = format!(
+        r#"{{"version": {}, "exported_at": "", "songs": [], "playlists": []}}"#,
+        MANIFEST_VERSION + 1
+    );
+    assert!(LibraryManifest::from_json(&json).is_err());
+}
+
+#[tokio::test]
+async fn songs_that_cant_be_analyzed_are_reported() {
+    let library = TestLibrary::new();
+    //The fixture isn't audio, the download still succeeds without measurements
+    library.download("a").await;
+    let song = library.database.get_song_by_id("a").unwrap().unwrap();
+    assert_eq!(song.loudness, None);
+
+    let results = library.handler.analyze_library(false).unwrap();
+    assert_eq!(results.len(), 1);
+    assert!(results[0].1.is_err());
+}
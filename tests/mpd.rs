@@ -0,0 +1,132 @@
+use std::{
+    io::{BufRead, BufReader, Write},
+    net::TcpListener,
+    thread,
+    time::Duration,
+};
+
+use mpd::{idle::Subsystem, State, Status};
+use yap::{
+    mpd::{mpd_client::MpdClient, mpd_events::subsystem_events},
+    player::{
+        backend::{PlayRange, Player},
+        events::PlayerEvent,
+    },
+};
+
+/**
+ * Answers every connection like an mpd server with a paused queue of two songs,
+ * the first trimmed
+ */
+fn fake_mpd() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap().to_string();
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            //The client keeps its connection open while it opens another one
+            thread::spawn(move || {
+                let reader = BufReader::new(stream.try_clone().unwrap());
+                stream.write_all(b"OK MPD 0.23.5\n").unwrap();
+                //Commands of a list are answered together when it ends
+                let mut in_list = false;
+                for command in reader.lines().map_while(Result::ok) {
+                    let response = match command.as_str() {
+                        "command_list_begin" => {
+                            in_list = true;
+                            continue;
+                        }
+                        "command_list_end" => {
+                            in_list = false;
+                            ""
+                        }
+                        "status" => "state: pause\nsong: 1\nsongid: 2\nelapsed: 3.500\n",
+                        "playlistinfo" => {
+                            "file: a.opus\nRange: 12.500-220.000\nPos: 0\nId: 1\n\
+                             file: b.opus\nPos: 1\nId: 2\n"
+                        }
+                        _ => "",
+                    };
+                    stream.write_all(response.as_bytes()).unwrap();
+                    if !in_list {
+                        stream.write_all(b"OK\n").unwrap();
+                    }
+                }
+            });
+        }
+    });
+    address
+}
+
+#[test]
+fn snapshots_keep_the_range_of_each_song() {
+    let client = MpdClient::new(fake_mpd());
+    let snapshot = client.snapshot().unwrap();
+    assert_eq!(snapshot.files, vec!["a.opus", "b.opus"]);
+    assert_eq!(
+        snapshot.ranges,
+        vec![
+            PlayRange {
+                start: Some(12.5),
+                end: Some(220.)
+            },
+            PlayRange::default()
+        ]
+    );
+    assert_eq!(snapshot.current, Some(1));
+    assert_eq!(snapshot.elapsed, Duration::from_millis(3500));
+    assert!(snapshot.paused);
+}
+
+#[test]
+fn changed_subsystems_become_events() {
+    let playing = Status {
+        state: State::Play,
+        queue_len: 2,
+        ..Default::default()
+    };
+    let paused = Status {
+        state: State::Pause,
+        random: true,
+        ..playing.clone()
+    };
+    let (a, b) = (Some("a".to_string()), Some("b".to_string()));
+
+    assert_eq!(
+        subsystem_events(&[Subsystem::Player], (&playing, &a), (&paused, &b)),
+        vec![
+            PlayerEvent::SongChanged { song_id: b.clone() },
+            PlayerEvent::Paused { paused: true }
+        ]
+    );
+    //Seeking also wakes the player subsystem, without changing anything we report
+    assert_eq!(
+        subsystem_events(&[Subsystem::Player], (&playing, &a), (&playing, &a)),
+        vec![]
+    );
+    assert_eq!(
+        subsystem_events(&[Subsystem::Player], (&playing, &a), (&playing, &None)),
+        vec![PlayerEvent::SongChanged { song_id: None }]
+    );
+    assert_eq!(
+        subsystem_events(
+            &[Subsystem::Queue, Subsystem::Options, Subsystem::Database],
+            (&playing, &a),
+            (&paused, &a)
+        ),
+        vec![
+            PlayerEvent::QueueChanged { length: 2 },
+            PlayerEvent::OptionsChanged {
+                repeat: false,
+                random: true,
+                single: false,
+                consume: false
+            },
+            PlayerEvent::DatabaseUpdated
+        ]
+    );
+    assert_eq!(
+        subsystem_events(&[Subsystem::Mixer], (&playing, &a), (&paused, &b)),
+        vec![]
+    );
+}
@@ -0,0 +1,50 @@
+use std::{env, path::PathBuf};
+
+use yap::{error::YapError, utils::paths::expand_path};
+
+fn expand(path: &str) -> Result<PathBuf, YapError> {
+    expand_path(&PathBuf::from(path))
+}
+
+#[test]
+fn tilde_is_the_home_directory() {
+    let home = PathBuf::from(env::var("HOME").unwrap());
+    assert_eq!(expand("~").unwrap(), home);
+    assert_eq!(expand("~/Music/songs").unwrap(), home.join("Music/songs"));
+    assert_eq!(expand("/tmp/~music").unwrap(), PathBuf::from("/tmp/~music"));
+}
+
+#[test]
+fn variables_are_expanded() {
+    env::set_var("YAP_TEST_MUSIC", "/srv/music");
+    assert_eq!(
+        expand("$YAP_TEST_MUSIC/songs").unwrap(),
+        PathBuf::from("/srv/music/songs")
+    );
+    assert_eq!(
+        expand("${YAP_TEST_MUSIC}_backup").unwrap(),
+        PathBuf::from("/srv/music_backup")
+    );
+    assert_eq!(expand("/tmp/$/yap").unwrap(), PathBuf::from("/tmp/$/yap"));
+}
+
+#[test]
+fn unset_variables_are_errors() {
+    assert!(matches!(
+        expand("$YAP_TEST_UNSET/yap.db"),
+        Err(YapError::Config(_))
+    ));
+    assert!(matches!(
+        expand("${YAP_TEST_MUSIC"),
+        Err(YapError::Config(_))
+    ));
+}
+
+#[test]
+fn xdg_directories_have_defaults() {
+    let expected = match env::var("XDG_CACHE_HOME") {
+        Ok(cache) if !cache.is_empty() => PathBuf::from(cache),
+        _ => PathBuf::from(env::var("HOME").unwrap()).join(".cache"),
+    };
+    assert_eq!(expand("$XDG_CACHE_HOME/yap").unwrap(), expected.join("yap"));
+}
@@ -0,0 +1,67 @@
+mod common;
+
+use std::{
+    env, fs,
+    os::unix::fs::PermissionsExt,
+    path::Path,
+    thread,
+    time::{Duration, Instant},
+};
+
+use common::TestLibrary;
+use yap::{
+    config::{self, SinkKind},
+    player::{backend::Player, local_player::LocalPlayer},
+};
+
+/**
+ * Puts first in PATH an ffmpeg that decodes every file to a minute of silence
+ */
+fn fake_ffmpeg(directory: &Path) {
+    let ffmpeg = directory.join("ffmpeg");
+    fs::write(&ffmpeg, "#!/bin/sh\nexec head -c 11520000 /dev/zero\n").unwrap();
+    fs::set_permissions(&ffmpeg, fs::Permissions::from_mode(0o755)).unwrap();
+    let path = env::var("PATH").unwrap_or_default();
+    env::set_var("PATH", format!("{}:{}", directory.display(), path));
+}
+
+#[tokio::test]
+async fn commands_during_playback_keep_its_progress() {
+    let library = TestLibrary::new();
+    library.download("a").await;
+    fake_ffmpeg(library.directory.path());
+    let player = LocalPlayer::new(
+        library.database.clone(),
+        library.directory.path().to_path_buf(),
+        config::Player {
+            sink: SinkKind::Null,
+            ..Default::default()
+        },
+    );
+    let running = thread::spawn(move || player.run());
+
+    library.handler.play_song("Title of a").unwrap();
+    let started = Instant::now();
+    //Each chunk takes 100ms to play, so volume changes keep landing while one is written
+    for volume in [40, 60].iter().cycle() {
+        if started.elapsed() > Duration::from_secs(2) {
+            break;
+        }
+        library.handler.volume(Some(volume.to_string())).unwrap();
+        thread::sleep(Duration::from_millis(20));
+    }
+
+    if running.is_finished() {
+        panic!("{:?}", running.join().unwrap().err());
+    }
+    let (status, song) = library.handler.status().unwrap();
+    assert_eq!(song.unwrap().id, "a");
+    assert_eq!(status.state, "play");
+    let elapsed = status.elapsed.unwrap();
+    assert!(
+        elapsed >= Duration::from_millis(1500) && elapsed <= started.elapsed(),
+        "{:?} played in {:?}",
+        elapsed,
+        started.elapsed()
+    );
+}
@@ -0,0 +1,175 @@
+mod common;
+
+use std::{fs, time::Duration};
+
+use common::TestLibrary;
+use yap::{
+    db::models::SongProperties,
+    playlists::format::{PathStyle, PlaylistFormat},
+};
+
+#[tokio::test]
+async fn m3u_lists_songs_in_playlist_order() {
+    let library = TestLibrary::with_playlist("Mix & Go").await;
+    let playlist = library
+        .handler
+        .export_playlist("Mix & Go", PlaylistFormat::M3u8, PathStyle::Relative)
+        .unwrap();
+    assert_eq!(
+        playlist,
+        "#EXTM3U\n#PLAYLIST:Mix & Go\n\
+         #EXTINF:-1,Fake artist - Title of b\nb.opus\n\
+         #EXTINF:-1,Fake artist - Title of a\na.opus\n"
+    );
+
+    let playlist = library
+        .handler
+        .export_playlist("Mix & Go", PlaylistFormat::Pls, PathStyle::Absolute)
+        .unwrap();
+    let absolute = library.directory.path().join("b.opus");
+    assert!(playlist.contains(&format!("File1={}\n", absolute.display())));
+    assert!(playlist.contains("NumberOfEntries=2\n"));
+}
+
+#[tokio::test]
+async fn xspf_escapes_names() {
+    let library = TestLibrary::with_playlist("Mix & Go").await;
+    let playlist = library
+        .handler
+        .export_playlist("Mix & Go", PlaylistFormat::Xspf, PathStyle::Relative)
+        .unwrap();
+    assert!(playlist.contains("<title>Mix &amp; Go</title>"));
+    assert!(playlist.contains("<location>a.opus</location>"));
+    assert!(playlist.contains("<creator>Fake artist</creator>"));
+}
+
+#[tokio::test]
+async fn every_playlist_is_exported_to_a_directory() {
+    let library = TestLibrary::with_playlist("Mix & Go").await;
+    library
+        .handler
+        .create_playlist("Only a", vec!["Title of a".to_string()])
+        .unwrap();
+    let directory = library.directory.path().join("playlists");
+    let files = library
+        .handler
+        .export_playlists(Some(&directory), PlaylistFormat::Json, PathStyle::Absolute)
+        .unwrap();
+    assert_eq!(files.len(), 2);
+    assert!(directory.join("Mix & Go.json").is_file());
+    assert!(directory.join("Only a.json").is_file());
+
+    assert!(library
+        .handler
+        .export_playlist("Missing", PlaylistFormat::M3u, PathStyle::Absolute)
+        .is_err());
+}
+
+#[tokio::test]
+async fn exported_playlists_import_back() {
+    let library = TestLibrary::with_playlist("Mix & Go").await;
+    for (format, style) in [
+        (PlaylistFormat::M3u8, PathStyle::Relative),
+        (PlaylistFormat::Xspf, PathStyle::Absolute),
+        (PlaylistFormat::Pls, PathStyle::Absolute),
+        (PlaylistFormat::Json, PathStyle::Relative),
+    ] {
+        let file = library
+            .directory
+            .path()
+            .join(format!("exported.{}", format.extension()));
+        let playlist = library
+            .handler
+            .export_playlist("Mix & Go", format, style)
+            .unwrap();
+        fs::write(&file, playlist).unwrap();
+
+        let name = format!("Imported {}", format.extension());
+        let report = library
+            .handler
+            .import_playlist(&file, None, Some(name.clone()), false)
+            .await
+            .unwrap();
+        assert!(report.unmatched.is_empty());
+        let songs = library.database.get_songs_of_playlist(&name).unwrap();
+        let ids: Vec<&str> = songs.iter().map(|song| song.song_id.as_str()).collect();
+        assert_eq!(ids, ["b", "a"]);
+    }
+}
+
+#[tokio::test]
+async fn entries_are_matched_by_title_and_unknown_ones_reported() {
+    let library = TestLibrary::with_playlist("Mix & Go").await;
+    let file = library.directory.path().join("Road trip.m3u");
+    fs::write(
+        &file,
+        "#EXTM3U\n\
+         #EXTINF:180,Fake Artist - title of A\n/elsewhere/song.mp3\n\
+         #EXTINF:200,Someone - Unknown song\n/elsewhere/unknown.mp3\n\
+         https://youtu.be/dQw4w9WgXcQ\n",
+    )
+    .unwrap();
+
+    let report = library
+        .handler
+        .import_playlist(&file, None, None, false)
+        .await
+        .unwrap();
+    assert_eq!(report.playlist_name, "Road trip");
+    assert_eq!(report.songs.len(), 1);
+    assert_eq!(report.songs[0].id, "a");
+    let unmatched: Vec<&str> = report
+        .unmatched
+        .iter()
+        .map(|entry| entry.location.as_str())
+        .collect();
+    assert_eq!(
+        unmatched,
+        ["/elsewhere/unknown.mp3", "https://youtu.be/dQw4w9WgXcQ"]
+    );
+
+    assert!(library
+        .handler
+        .import_playlist(&file, None, None, false)
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn summaries_add_up_known_durations() {
+    let library = TestLibrary::with_playlist("Mix & Go").await;
+    library.handler.create_playlist("Empty", vec![]).unwrap();
+    //The fixture isn't audio, only its size is known
+    let song = library.database.get_song_by_id("a").unwrap().unwrap();
+    assert_eq!((song.file_size, song.duration), (Some(7), None));
+
+    library
+        .database
+        .set_song_properties(
+            "a",
+            SongProperties {
+                duration: Some(90.5),
+                bitrate: Some(128000),
+                sample_rate: Some(48000),
+                channels: Some(2),
+                codec: Some("opus".to_string()),
+                file_size: None,
+            },
+        )
+        .unwrap();
+    let summaries = library.handler.get_playlist_summaries().unwrap();
+    let mix = summaries
+        .iter()
+        .find(|summary| summary.name == "Mix & Go")
+        .unwrap();
+    assert_eq!((mix.songs, mix.untimed), (2, 1));
+    assert_eq!(mix.duration, Duration::from_secs_f64(90.5));
+    let empty = summaries.iter().find(|summary| summary.name == "Empty");
+    assert_eq!(empty.unwrap().songs, 0);
+
+    let song = library.database.get_song_by_id("a").unwrap().unwrap();
+    assert_eq!(
+        (song.file_size, song.codec.as_deref()),
+        (Some(7), Some("opus"))
+    );
+}
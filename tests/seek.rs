@@ -0,0 +1,92 @@
+use std::time::Duration;
+
+use yap::{
+    error::YapError,
+    player::backend::SeekTarget,
+    utils::utils::{format_duration, parse_duration},
+};
+
+fn seconds(seconds: u64) -> Duration {
+    Duration::from_secs(seconds)
+}
+
+#[test]
+fn durations_are_parsed_from_every_syntax() {
+    assert_eq!(parse_duration("90").unwrap(), seconds(90));
+    assert_eq!(parse_duration("45s").unwrap(), seconds(45));
+    assert_eq!(parse_duration("2m").unwrap(), seconds(120));
+    assert_eq!(parse_duration("1h").unwrap(), seconds(3600));
+    assert_eq!(parse_duration("1.5").unwrap(), Duration::from_millis(1500));
+    assert_eq!(parse_duration("1:23").unwrap(), seconds(83));
+    assert_eq!(parse_duration("1:02:03").unwrap(), seconds(3723));
+    assert_eq!(parse_duration(" 0:05 ").unwrap(), seconds(5));
+
+    for invalid in ["", "abc", "1:xx", "-5", "1:-5", "5d", "inf"] {
+        assert!(
+            matches!(parse_duration(invalid), Err(YapError::Invalid(_))),
+            "{} was parsed",
+            invalid
+        );
+    }
+}
+
+#[test]
+fn durations_are_formatted_with_hours_only_when_needed() {
+    assert_eq!(format_duration(seconds(0)), "0:00");
+    assert_eq!(format_duration(seconds(83)), "1:23");
+    assert_eq!(format_duration(seconds(3599)), "59:59");
+    assert_eq!(format_duration(seconds(3723)), "1:02:03");
+    assert_eq!(format_duration(Duration::from_millis(1999)), "0:01");
+}
+
+#[test]
+fn seek_targets_are_parsed() {
+    let parse = |value: &str| value.parse::<SeekTarget>();
+    assert_eq!(parse("1:23"), Ok(SeekTarget::Absolute(seconds(83))));
+    assert_eq!(parse("1:00:00"), Ok(SeekTarget::Absolute(seconds(3600))));
+    assert_eq!(parse("+10s"), Ok(SeekTarget::Forward(seconds(10))));
+    assert_eq!(parse("-0:30"), Ok(SeekTarget::Backward(seconds(30))));
+    assert_eq!(parse("50%"), Ok(SeekTarget::Percentage(50)));
+    assert_eq!(parse("100%"), Ok(SeekTarget::Percentage(100)));
+
+    for invalid in ["101%", "-5%", "x%", "+", "-abc", "later"] {
+        assert!(parse(invalid).is_err(), "{} was parsed", invalid);
+    }
+}
+
+#[test]
+fn seek_targets_stay_inside_the_song() {
+    let (elapsed, duration) = (seconds(60), seconds(200));
+    let resolve = |target: SeekTarget| target.resolve(elapsed, duration);
+
+    assert_eq!(
+        resolve(SeekTarget::Absolute(seconds(83))).unwrap(),
+        seconds(83)
+    );
+    assert_eq!(resolve(SeekTarget::Absolute(duration)).unwrap(), duration);
+    assert!(matches!(
+        resolve(SeekTarget::Absolute(seconds(201))),
+        Err(YapError::Player(_))
+    ));
+
+    assert_eq!(
+        resolve(SeekTarget::Forward(seconds(30))).unwrap(),
+        seconds(90)
+    );
+    assert_eq!(
+        resolve(SeekTarget::Forward(seconds(300))).unwrap(),
+        duration
+    );
+    assert_eq!(
+        resolve(SeekTarget::Backward(seconds(30))).unwrap(),
+        seconds(30)
+    );
+    assert_eq!(
+        resolve(SeekTarget::Backward(seconds(90))).unwrap(),
+        seconds(0)
+    );
+
+    assert_eq!(resolve(SeekTarget::Percentage(0)).unwrap(), seconds(0));
+    assert_eq!(resolve(SeekTarget::Percentage(25)).unwrap(), seconds(50));
+    assert_eq!(resolve(SeekTarget::Percentage(100)).unwrap(), duration);
+}
@@ -0,0 +1,13 @@
+use yap::sources::source::make_song_id;
+
+#[test]
+fn song_ids_dont_change_between_builds() {
+    assert_eq!(
+        make_song_id("local", "My Song", "/music/My Song.mp3"),
+        "local-My_Song-5e323d41"
+    );
+    assert_eq!(
+        make_song_id("http", "a", "https://example.com/a.mp3"),
+        "http-a-4bb2eef7"
+    );
+}
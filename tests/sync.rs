@@ -0,0 +1,63 @@
+mod common;
+
+use std::fs;
+
+use common::TestLibrary;
+use yap::sync::transcode::Encoding;
+
+#[tokio::test]
+async fn songs_are_copied_with_readable_names() {
+    let library = TestLibrary::with_playlist("Road/Trip").await;
+    let target = tempfile::tempdir().unwrap();
+    let report = library
+        .handler
+        .sync_playlists(
+            &["Road/Trip".to_string()],
+            target.path(),
+            &Encoding::default(),
+        )
+        .unwrap();
+    assert_eq!(report.written.len(), 2);
+    assert!(target
+        .path()
+        .join("Fake artist - Title of a.opus")
+        .is_file());
+
+    let playlist = fs::read_to_string(target.path().join("Road_Trip.m3u")).unwrap();
+    assert!(playlist.contains(
+        "Fake artist - Title of b.opus\n#EXTINF:-1,Fake artist - Title of a\n\
+         Fake artist - Title of a.opus\n"
+    ));
+}
+
+#[tokio::test]
+async fn later_syncs_only_copy_what_changed() {
+    let library = TestLibrary::with_playlist("Road/Trip").await;
+    let target = tempfile::tempdir().unwrap();
+    let playlists = ["Road/Trip".to_string()];
+    library
+        .handler
+        .sync_playlists(&playlists, target.path(), &Encoding::default())
+        .unwrap();
+
+    let report = library
+        .handler
+        .sync_playlists(&playlists, target.path(), &Encoding::default())
+        .unwrap();
+    assert!(report.written.is_empty());
+    assert_eq!(report.unchanged, 2);
+
+    library
+        .handler
+        .remove_song_from_playlist("Road/Trip", "Title of a")
+        .unwrap();
+    //Files the sync didn't write are left alone
+    fs::write(target.path().join("notes.txt"), "keep").unwrap();
+    let report = library
+        .handler
+        .sync_playlists(&playlists, target.path(), &Encoding::default())
+        .unwrap();
+    assert_eq!(report.removed, vec!["Fake artist - Title of a.opus"]);
+    assert!(!target.path().join("Fake artist - Title of a.opus").exists());
+    assert!(target.path().join("notes.txt").is_file());
+}
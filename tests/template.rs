@@ -0,0 +1,35 @@
+use std::collections::HashMap;
+
+use yap::utils::template::render;
+
+fn values() -> HashMap<&'static str, String> {
+    HashMap::from([
+        ("title", "Song".to_string()),
+        ("artist", "Band".to_string()),
+        ("elapsed", "1:23".to_string()),
+    ])
+}
+
+#[test]
+fn keys_are_replaced_by_their_values() {
+    assert_eq!(render("{artist} - {title}", &values()), "Band - Song");
+    assert_eq!(render("[{elapsed}]{title}", &values()), "[1:23]Song");
+    assert_eq!(render("{title}{title}", &values()), "SongSong");
+    assert_eq!(render("no keys", &values()), "no keys");
+    assert_eq!(render("", &values()), "");
+}
+
+#[test]
+fn doubled_braces_are_printed_once() {
+    assert_eq!(render("{{title}}", &values()), "{title}");
+    assert_eq!(render("{{{title}}}", &values()), "{Song}");
+    assert_eq!(render("a }} b {{", &values()), "a } b {");
+}
+
+#[test]
+fn unknown_keys_and_lone_braces_are_left_untouched() {
+    assert_eq!(render("{album} - {title}", &values()), "{album} - Song");
+    assert_eq!(render("{}", &values()), "{}");
+    assert_eq!(render("{title", &values()), "{title");
+    assert_eq!(render("title}", &values()), "title}");
+}
@@ -0,0 +1,48 @@
+mod common;
+
+use std::{thread, time::Duration};
+
+use common::TestLibrary;
+use yap::{
+    config,
+    error::YapError,
+    player::{backend::Player, events::PlayerEvent, local_player::LocalPlayer},
+};
+
+#[tokio::test]
+async fn watch_notifies_every_song_change() {
+    let library = TestLibrary::new();
+    library.download("a").await;
+    library.download("b").await;
+    library.handler.add_to_queue("Title of a", false).unwrap();
+    library.handler.add_to_queue("Title of b", false).unwrap();
+
+    //Playback starts from another process once watch is listening
+    let player = LocalPlayer::new(
+        library.database.clone(),
+        library.directory.path().to_path_buf(),
+        config::Player::default(),
+    );
+    thread::spawn(move || {
+        thread::sleep(Duration::from_millis(300));
+        player.play()
+    });
+
+    let mut changes = Vec::new();
+    let error = library
+        .handler
+        .watch(|event| {
+            if let PlayerEvent::SongChanged { song_id } = event.event {
+                changes.push(song_id.clone());
+                match song_id.as_deref() {
+                    Some("a") => library.handler.next()?,
+                    _ => return Err(YapError::Player("Done watching".to_string())),
+                }
+            }
+            Ok(())
+        })
+        .unwrap_err();
+    assert!(matches!(error, YapError::Player(_)));
+    assert_eq!(changes, vec![Some("a".to_string()), Some("b".to_string())]);
+    assert_eq!(*library.notified.lock().unwrap(), vec!["a", "b"]);
+}